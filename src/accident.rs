@@ -0,0 +1,348 @@
+use std::collections::{HashMap, HashSet};
+use bevy::{prelude::*, color::{Mix, palettes::tailwind}};
+use hexx::Hex;
+use rand::random;
+use serde::{Serialize, Deserialize};
+
+use crate::clock::{GameClock, DayStarted};
+use crate::guest::{Guest, Activity};
+use crate::satisfaction::GuestLeft;
+use crate::save::ResortLoaded;
+use crate::staff::{StaffMember, StaffRole, Staffing};
+use crate::grid::{
+	CellHeights, PlacedObjects,
+	surface::{Surface, PisteGrade},
+	snow::SnowQuality,
+	geometry::gizmo::cell_outline,
+	object::{ObjectInstance, structure::{StructureTypes, StructureRole}},
+};
+
+/// Guests fall and collide on the pistes, and the ski patrol carries the injured to first aid.
+pub struct AccidentPlugin;
+impl Plugin for AccidentPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Incidents>();
+		app.add_systems(Update, (
+			respond_to_incidents,
+			draw_incidents,
+			draw_heatmap.run_if(|incidents: Res<Incidents>| incidents.show_heatmap),
+		));
+		app.add_observer(injure_guest);
+		app.add_observer(close_day);
+		app.add_observer(reopen_incidents);
+	}
+}
+
+/// Chance of an accident on a run for a skilled guest on good snow with nobody around.
+pub const BASE_ACCIDENT_CHANCE: f32 = 0.002;
+/// Extra accident risk per unit of grade difficulty above the guest's skill.
+pub const OVERREACH_RISK: f32 = 10.;
+/// Extra accident risk per other guest on the same cell.
+pub const COLLISION_RISK: f32 = 0.3;
+/// Accidents less severe than this are shrugged off, anything worse needs the ski patrol.
+pub const INJURY_SEVERITY: f32 = 0.4;
+
+/// How much more dangerous the snow makes a run, where 1 is good snow.
+pub const fn snow_risk(quality: SnowQuality) -> f32 {
+	match quality {
+		SnowQuality::Powder | SnowQuality::Groomed | SnowQuality::Packed => 1.,
+		SnowQuality::Moguls => 1.5,
+		SnowQuality::Slush => 1.2,
+		SnowQuality::Icy => 3.,
+		SnowQuality::Bare => 2.,
+	}
+}
+
+/// Chance that a run ends in an accident.
+/// Runs above the guest's skill, bad snow and other guests on the same cell all make accidents more likely.
+pub fn accident_chance(grade: PisteGrade, skill: f32, quality: SnowQuality, others: usize) -> f64 {
+	let difficulty = grade as usize as f32 / PisteGrade::ALL.len() as f32;
+	let overreach = (difficulty - skill).max(0.);
+	let chance = BASE_ACCIDENT_CHANCE
+	* (2. - skill)
+	* (1. + OVERREACH_RISK * overreach)
+	* snow_risk(quality)
+	* (1. + COLLISION_RISK * others as f32);
+	chance.clamp(0., 1.) as f64
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum RescueStage {
+	#[default]
+	Waiting,
+	/// The ski patrol is on their way to the guest.
+	Reaching,
+	/// The ski patrol is carrying the guest to a first-aid hut.
+	Transporting,
+}
+impl RescueStage {
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Waiting => "Waiting for patrol",
+			Self::Reaching => "Patrol on the way",
+			Self::Transporting => "Carried to first aid",
+		}
+	}
+}
+
+/// An injured guest who needs the ski patrol.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Incident {
+	pub guest: Entity,
+	/// Where the guest is, which changes while they are carried.
+	pub position: Hex,
+	/// 0-1, where 1 is as bad as it gets.
+	pub severity: f32,
+	/// In-game minutes since the accident.
+	pub minutes: f32,
+	pub responder: Option<Entity>,
+	pub stage: RescueStage,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct IncidentStats {
+	pub accidents: u32,
+	pub injuries: u32,
+	pub rescued: u32,
+	/// Injured guests that went home without being rescued.
+	pub unresolved: u32,
+}
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Incidents {
+	/// Not saved, since they hold guest and staff entities.
+	#[serde(skip)]
+	pub active: Vec<Incident>,
+	/// Accidents on each piste cell, to find dangerous runs.
+	pub heatmap: HashMap<Hex, u32>,
+	pub show_heatmap: bool,
+	pub today: IncidentStats,
+	pub yesterday: IncidentStats,
+}
+impl Incidents {
+	/// The runs with the most accidents, most first.
+	/// A run is a stretch of connected piste cells, and accidents off the pistes count as their own run.
+	pub fn most_dangerous(&self, surfaces: &HashMap<Hex, Surface>, count: usize) -> Vec<DangerousRun> {
+		let accidents = |pos: &Hex| self.heatmap.get(pos).copied().unwrap_or_default();
+		let mut runs: Vec<DangerousRun> = Vec::new();
+		let mut seen: HashSet<Hex> = HashSet::new();
+		for start in self.heatmap.keys() {
+			if !seen.insert(*start) {continue}
+			let mut cells: Vec<Hex> = Vec::new();
+			let mut frontier = vec![*start];
+			while let Some(pos) = frontier.pop() {
+				cells.push(pos);
+				if surfaces.get(&pos) != Some(&Surface::Piste) {continue}
+				for next in pos.all_neighbors() {
+					if surfaces.get(&next) == Some(&Surface::Piste) && seen.insert(next) {frontier.push(next)}
+				}
+			}
+			let worst = cells.iter().copied().max_by_key(|pos| (accidents(pos), std::cmp::Reverse((pos.x, pos.y)))).unwrap_or(*start);
+			runs.push(DangerousRun {worst: worst, cells: cells.len(), accidents: cells.iter().map(accidents).sum()});
+		}
+		runs.sort_by_key(|run| (std::cmp::Reverse(run.accidents), run.worst.x, run.worst.y));
+		runs.truncate(count);
+		runs
+	}
+}
+
+/// Connected piste cells where accidents happened.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DangerousRun {
+	/// The cell with the most accidents.
+	pub worst: Hex,
+	pub cells: usize,
+	pub accidents: u32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// A guest fell or collided with someone at the end of a run.
+pub struct Accident {
+	pub guest: Entity,
+	pub position: Hex,
+}
+fn injure_guest(
+	trigger: Trigger<Accident>,
+	staffing: Res<Staffing>,
+	mut incidents: ResMut<Incidents>,
+	mut guests: Query<&mut Guest>,
+) {
+	let Accident {guest: entity, position} = *trigger;
+	*incidents.heatmap.entry(position).or_default() += 1;
+	incidents.today.accidents += 1;
+	let severity = random::<f32>() * staffing.injury_severity_factor(&position);
+	if severity < INJURY_SEVERITY {return}
+	let mut guest = match guests.get_mut(entity) {Ok(guest) => guest, Err(_) => {warn!("Accident happened to {:?}, who isn't a guest.", entity); return}};
+	guest.activity = Activity::Injured;
+	guest.run = None;
+	guest.experience.injured = true;
+	incidents.today.injuries += 1;
+	incidents.active.push(Incident {guest: entity, position: position, severity: severity, minutes: 0., responder: None, stage: RescueStage::Waiting});
+}
+
+/// Sends the nearest available ski patrol to injured guests, and has them carry the guests to the nearest first-aid hut.
+/// Guests are treated and go home once they get there.
+fn respond_to_incidents(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	mut incidents: ResMut<Incidents>,
+	mut guests: Query<&mut Guest>,
+	mut staff: Query<(Entity, &mut StaffMember)>,
+) {
+	if incidents.active.is_empty() {return}
+	let minutes = clock.delta_minutes;
+	let huts: Vec<Hex> = objects.iter().filter_map(|(_, object)| {
+		let ObjectInstance::Structure(instance) = object else {return None};
+		match structure_types.0.get(&instance.type_id)?.role {
+			StructureRole::FirstAidHut => Some(instance.position),
+			_ => None,
+		}
+	}).collect();
+	let nearest_hut = |pos: Hex| huts.iter().min_by_key(|hut| pos.unsigned_distance_to(**hut)).copied();
+
+	let Incidents {active, today, ..} = &mut *incidents;
+	active.retain_mut(|incident| {
+		incident.minutes += minutes;
+		let Ok(mut guest) = guests.get_mut(incident.guest) else {
+			// The guest went home when the resort closed.
+			if let Some(mut member) = incident.responder.and_then(|responder| staff.get_mut(responder).ok()).map(|(_, member)| member) {
+				member.errand = None;
+			}
+			today.unresolved += 1;
+			return false
+		};
+		if incident.responder.is_some_and(|responder| !staff.contains(responder)) {
+			// The patrol was fired on the way, and someone else has to go.
+			incident.responder = None;
+			incident.stage = RescueStage::Waiting;
+		}
+
+		match incident.stage {
+			RescueStage::Waiting => {
+				if huts.is_empty() {return true}
+				let available = staff.iter_mut()
+				.filter(|(_, member)| member.role == StaffRole::SkiPatrol && member.on_duty && member.errand.is_none())
+				.min_by_key(|(_, member)| member.position.unsigned_distance_to(incident.position));
+				if let Some((entity, mut member)) = available {
+					member.errand = Some(incident.position);
+					incident.responder = Some(entity);
+					incident.stage = RescueStage::Reaching;
+				}
+			},
+			RescueStage::Reaching => {
+				let Some((_, mut member)) = incident.responder.and_then(|responder| staff.get_mut(responder).ok()) else {return true};
+				if member.position != incident.position {return true}
+				guest.experience.rescue_minutes = Some(incident.minutes);
+				incident.stage = RescueStage::Transporting;
+				member.errand = nearest_hut(member.position);
+			},
+			RescueStage::Transporting => {
+				let Some((_, mut member)) = incident.responder.and_then(|responder| staff.get_mut(responder).ok()) else {return true};
+				incident.position = member.position;
+				guest.position = member.position;
+				if !member.errand.is_some_and(|errand| huts.contains(&errand)) {
+					// The hut was removed, so they head for another one.
+					member.errand = nearest_hut(member.position);
+				}
+				// Treated on the spot if there's nowhere left to go.
+				if member.errand.is_none_or(|hut| hut == member.position) {
+					member.errand = None;
+					today.rescued += 1;
+					commands.trigger(GuestLeft(guest.clone()));
+					commands.entity(incident.guest).despawn();
+					return false
+				}
+			},
+		}
+		true
+	});
+}
+
+fn close_day(
+	_trigger: Trigger<DayStarted>,
+	mut incidents: ResMut<Incidents>,
+) {
+	incidents.yesterday = incidents.today;
+	incidents.today = IncidentStats::default();
+}
+
+/// Incidents hold guest and staff entities, which are new after loading, so injured guests start waiting for the ski patrol again.
+fn reopen_incidents(
+	_trigger: Trigger<ResortLoaded>,
+	mut incidents: ResMut<Incidents>,
+	guests: Query<(Entity, &Guest)>,
+	mut staff: Query<&mut StaffMember>,
+) {
+	for mut member in staff.iter_mut() {
+		if member.errand.is_some() {
+			member.errand = None;
+		}
+	}
+	incidents.active = guests.iter()
+	.filter(|(_, guest)| guest.activity == Activity::Injured)
+	.map(|(entity, guest)| Incident {guest: entity, position: guest.position, severity: INJURY_SEVERITY, minutes: 0., responder: None, stage: RescueStage::Waiting})
+	.collect();
+}
+
+fn draw_incidents(
+	mut gizmos: Gizmos,
+	incidents: Res<Incidents>,
+	heights: Res<CellHeights>,
+) {
+	for incident in incidents.active.iter() {
+		cell_outline(&mut *gizmos, &heights.0, &incident.position, tailwind::RED_500);
+	}
+}
+
+fn draw_heatmap(
+	mut gizmos: Gizmos,
+	incidents: Res<Incidents>,
+	heights: Res<CellHeights>,
+) {
+	let Some(max) = incidents.heatmap.values().max().copied() else {return};
+	let low = Color::from(tailwind::YELLOW_300);
+	let high = Color::from(tailwind::RED_600);
+	for (pos, accidents) in incidents.heatmap.iter() {
+		cell_outline(&mut *gizmos, &heights.0, pos, low.mix(&high, *accidents as f32 / max as f32));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn accidents_are_likelier_on_hard_runs_bad_snow_and_crowds() {
+		let base = accident_chance(PisteGrade::Green, 0.5, SnowQuality::Groomed, 0);
+		assert!(base > 0.);
+		assert!(accident_chance(PisteGrade::Black, 0.5, SnowQuality::Groomed, 0) > base);
+		assert!(accident_chance(PisteGrade::Green, 0.5, SnowQuality::Icy, 0) > base);
+		assert!(accident_chance(PisteGrade::Green, 0.5, SnowQuality::Groomed, 3) > base);
+		assert!(accident_chance(PisteGrade::Green, 1., SnowQuality::Groomed, 0) < base, "skilled guests fall less");
+		assert!(accident_chance(PisteGrade::Black, 0., SnowQuality::Icy, 1_000_000) <= 1.);
+	}
+
+	#[test]
+	fn accidents_are_grouped_by_run() {
+		let mut surfaces: HashMap<Hex, Surface> = HashMap::new();
+		// Two pistes down the x axis, kept apart by a row of grass.
+		for x in 0..5 {
+			surfaces.insert(Hex::new(x, 0), Surface::Piste);
+			surfaces.insert(Hex::new(x, 1), Surface::None);
+			surfaces.insert(Hex::new(x, 2), Surface::Piste);
+		}
+		let incidents = Incidents {
+			heatmap: HashMap::from([(Hex::new(0, 0), 2), (Hex::new(4, 0), 2), (Hex::new(2, 2), 3), (Hex::new(2, 1), 1)]),
+			..default()
+		};
+		let runs = incidents.most_dangerous(&surfaces, 5);
+		assert_eq!(runs, vec![
+			DangerousRun {worst: Hex::new(0, 0), cells: 5, accidents: 4},
+			DangerousRun {worst: Hex::new(2, 2), cells: 5, accidents: 3},
+			DangerousRun {worst: Hex::new(2, 1), cells: 1, accidents: 1},
+		]);
+		assert_eq!(incidents.most_dangerous(&surfaces, 1).len(), 1);
+	}
+}
@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use bevy::{prelude::*, color::{Mix, palettes::tailwind}};
+use hexx::Hex;
+use serde::{Serialize, Deserialize};
+
+use crate::util::{hex::{axial_to_xz, cell_slope, flow_direction}, rotation::Rotation};
+use crate::clock::{GameClock, DayStarted};
+use crate::weather::Weather;
+use crate::economy::{Economy, Money, ledger::Category};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::Surface,
+	snow::{CellSnow, SnowCell, SnowQuality},
+	geometry::{gizmo::cell_outline, overlay::{AddOverlay, CellColors, scale_color}},
+	object::{ObjectInstance, RemoveObject, structure::{StructureTypes, StructureRole}},
+};
+
+/// Builds up avalanche risk on steep snowy slopes, and releases slides when it gets too high.
+/// The simulation itself is made of plain functions on cell maps, so it can be run without an app.
+pub struct AvalanchePlugin;
+impl Plugin for AvalanchePlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Avalanches>();
+		app.add_systems(Update, (
+			load_and_release,
+			draw_risk.run_if(|avalanches: Res<Avalanches>| avalanches.show_risk),
+		));
+		app.add_overlay("Avalanche risk", "Green is safe, red is about to slide, purple is closed.", risk_overlay);
+		app.add_observer(control_avalanches);
+		app.add_observer(reopen_pistes);
+	}
+}
+
+/// Cells flatter than this never release avalanches, and slides start depositing their snow on them.
+pub const AVALANCHE_MIN_SLOPE: u16 = 3;
+/// Slopes need at least this much snow (cm) to build up risk.
+pub const MIN_SLIDE_DEPTH: f32 = 30.;
+/// Risk added per cm of new snow on the flattest avalanche slopes.
+pub const NEW_SNOW_LOADING: f32 = 0.02;
+/// Wind slower than this (m/s) doesn't move snow around.
+pub const WIND_LOADING_MIN_SPEED: f32 = 8.;
+/// Risk added to lee slopes per m/s above `WIND_LOADING_MIN_SPEED` per in-game minute.
+pub const WIND_LOADING: f32 = 0.00002;
+/// Fraction of the risk that goes away per in-game minute, as the snow settles.
+pub const SETTLING_RATE: f32 = 0.0005;
+/// Slopes release on their own at this risk.
+pub const RELEASE_RISK: f32 = 1.;
+/// Explosives are only used on slopes with at least this much risk.
+pub const CONTROL_MIN_RISK: f32 = 0.4;
+/// Cost of blasting one slope.
+pub const EXPLOSIVE_COST: Money = 300;
+/// Fraction of the snow on the starting cell that breaks loose.
+pub const RELEASE_FRACTION: f32 = 0.5;
+/// Fraction of the snow that a slide picks up from each steep cell it passes.
+pub const ENTRAIN_FRACTION: f32 = 0.2;
+/// Fraction of the remaining snow that a slide drops on each flat cell.
+pub const DEPOSIT_FRACTION: f32 = 0.5;
+/// Snow fences protect cells within this distance from wind loading.
+pub const FENCE_RADIUS: u32 = 2;
+/// Days that pistes stay closed after being hit by an uncontrolled slide.
+pub const CLOSURE_DAYS: u32 = 2;
+/// Number of slides kept in the log.
+const REPORTED_SLIDES: usize = 10;
+/// Slides are stopped after this many cells, in case the terrain is very long.
+const MAX_SLIDE_LENGTH: usize = 200;
+
+/// Cells where structures protect against avalanches.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Protection {
+	/// Slides stop before reaching these cells.
+	pub barriers: HashSet<Hex>,
+	/// Cells where the wind doesn't load any snow.
+	pub fenced: HashSet<Hex>,
+}
+impl Protection {
+	pub fn from_objects(objects: &PlacedObjects, structure_types: &StructureTypes) -> Self {
+		let mut protection = Self::default();
+		for (_, object) in objects.iter() {
+			let ObjectInstance::Structure(instance) = object else {continue};
+			match structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.role) {
+				Some(StructureRole::AvalancheBarrier) => {protection.barriers.insert(instance.position);},
+				Some(StructureRole::SnowFence) => protection.fenced.extend(instance.position.range(FENCE_RADIUS)),
+				_ => (),
+			}
+		}
+		protection
+	}
+}
+
+/// Weather that loads snow onto the slopes.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Loading {
+	/// New snow per in-game minute (cm).
+	pub snowfall: f32,
+	/// Wind speed (m/s).
+	pub wind: f32,
+	/// The wind blows towards the `edge_last` of this rotation.
+	pub wind_direction: Rotation,
+}
+
+/// How much a slope counts towards the risk, from 0 for slopes flatter than `AVALANCHE_MIN_SLOPE`.
+pub fn steepness(heights: &HashMap<Hex, u16>, pos: &Hex) -> f32 {
+	let slope = cell_slope(heights, pos);
+	if slope < AVALANCHE_MIN_SLOPE {0.} else {(slope - AVALANCHE_MIN_SLOPE + 1) as f32 / 3.}
+}
+
+/// Whether the slope faces away from the wind, so that blown snow collects on it.
+pub fn is_lee_slope(heights: &HashMap<Hex, u16>, pos: &Hex, wind_direction: Rotation) -> bool {
+	let Some(flow) = flow_direction(heights, pos) else {return false};
+	let [flow_x, flow_z] = axial_to_xz(&(flow - *pos));
+	let [wind_x, wind_z] = axial_to_xz(&wind_direction.edge_last());
+	Vec2::new(flow_x, flow_z).normalize().dot(Vec2::new(wind_x, wind_z).normalize()) > 0.4
+}
+
+/// Adds risk from new snow and wind, and lets older snow settle.
+/// Panics if a snow cell is not in heights.
+pub fn load_risk(
+	risk: &mut HashMap<Hex, f32>,
+	heights: &HashMap<Hex, u16>,
+	snow: &HashMap<Hex, SnowCell>,
+	protection: &Protection,
+	loading: &Loading,
+	minutes: f32,
+) {
+	let wind_loading = (loading.wind - WIND_LOADING_MIN_SPEED).max(0.) * WIND_LOADING;
+	for (pos, cell) in snow.iter() {
+		let steepness = steepness(heights, pos);
+		let cell_risk = risk.entry(*pos).or_default();
+		*cell_risk *= (1. - SETTLING_RATE).powf(minutes);
+		if steepness <= 0. || cell.depth < MIN_SLIDE_DEPTH {continue}
+		let mut added = loading.snowfall * NEW_SNOW_LOADING;
+		if wind_loading > 0. && !protection.fenced.contains(pos) && is_lee_slope(heights, pos, loading.wind_direction) {
+			added += wind_loading;
+		}
+		// Barriers also hold the snow around them in place.
+		if pos.ring(1).chain([*pos]).any(|neighbor| protection.barriers.contains(&neighbor)) {
+			added /= 2.;
+		}
+		*cell_risk += added * steepness * minutes;
+	}
+	risk.retain(|_, cell_risk| *cell_risk > 0.001);
+}
+
+/// An avalanche that has run its course.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Slide {
+	pub start: Hex,
+	/// Every cell that the slide passed, starting with `start`.
+	pub path: Vec<Hex>,
+	/// Cells where snow was dropped, with the depth added (cm).
+	pub buried: Vec<(Hex, f32)>,
+	/// Released on purpose with explosives.
+	pub controlled: bool,
+}
+impl Slide {
+	/// All cells touched by the slide.
+	pub fn cells(&self) -> impl Iterator<Item = Hex> {
+		self.path.iter().copied().chain(self.buried.iter().map(|(pos, _)| *pos))
+	}
+}
+
+/// Releases the snow on a cell and lets it run down the flow direction, picking up snow on steep cells and dropping it on flat ones.
+pub fn run_slide(
+	start: Hex,
+	heights: &HashMap<Hex, u16>,
+	snow: &mut HashMap<Hex, SnowCell>,
+	protection: &Protection,
+	controlled: bool,
+) -> Slide {
+	let mut slide = Slide {start: start, path: vec![start], buried: Vec::new(), controlled: controlled};
+	let mut mass = match snow.get_mut(&start) {
+		Some(cell) => {
+			let released = cell.depth * RELEASE_FRACTION;
+			cell.depth -= released;
+			released
+		},
+		None => return slide,
+	};
+	let mut current = start;
+	while mass >= 1. && slide.path.len() < MAX_SLIDE_LENGTH {
+		let next = match flow_direction(heights, &current) {Some(next) if !protection.barriers.contains(&next) => next, _ => break};
+		slide.path.push(next);
+		current = next;
+		let Some(cell) = snow.get_mut(&next) else {break};
+		if steepness(heights, &next) > 0. {
+			let entrained = cell.depth * ENTRAIN_FRACTION;
+			cell.depth -= entrained;
+			mass += entrained;
+		} else {
+			let deposited = mass * DEPOSIT_FRACTION;
+			bury(cell, deposited);
+			slide.buried.push((next, deposited));
+			mass -= deposited;
+		}
+	}
+	if mass > 0. && let Some(cell) = snow.get_mut(&current) {
+		bury(cell, mass);
+		slide.buried.push((current, mass));
+	}
+	slide
+}
+
+fn bury(cell: &mut SnowCell, depth: f32) {
+	cell.depth += depth;
+	// Avalanche debris sets hard as it stops.
+	cell.quality = SnowQuality::Icy;
+	cell.traffic = 0.;
+}
+
+/// Releases every cell that has reached `RELEASE_RISK`, highest risk first.
+/// The risk of every cell touched by a slide is reset.
+pub fn release_slides(
+	risk: &mut HashMap<Hex, f32>,
+	heights: &HashMap<Hex, u16>,
+	snow: &mut HashMap<Hex, SnowCell>,
+	protection: &Protection,
+) -> Vec<Slide> {
+	let mut starts: Vec<(Hex, f32)> = risk.iter().filter(|(_, cell_risk)| **cell_risk >= RELEASE_RISK).map(|(pos, cell_risk)| (*pos, *cell_risk)).collect();
+	starts.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+	let mut slides = Vec::new();
+	for (start, _) in starts {
+		// An earlier slide may already have taken this snow with it.
+		if risk.get(&start).is_none_or(|cell_risk| *cell_risk < RELEASE_RISK) {continue}
+		let slide = run_slide(start, heights, snow, protection, false);
+		for pos in slide.cells() {
+			risk.remove(&pos);
+		}
+		slides.push(slide);
+	}
+	slides
+}
+
+/// A slide, and what it did to the resort.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct SlideReport {
+	pub day: u32,
+	pub slide: Slide,
+	pub pistes_closed: usize,
+	pub structures_destroyed: Vec<&'static str>,
+}
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Avalanches {
+	pub risk: HashMap<Hex, f32>,
+	/// Piste cells that are closed after a slide, with the number of days left.
+	pub closed: HashMap<Hex, u32>,
+	/// The latest slides, newest first.
+	/// Not saved, since they name the structures they destroyed by their type's name.
+	#[serde(skip)]
+	pub reports: VecDeque<SlideReport>,
+	pub show_risk: bool,
+}
+impl Avalanches {
+	pub fn is_closed(&self, pos: &Hex) -> bool {self.closed.contains_key(pos)}
+
+	pub fn risk(&self, pos: &Hex) -> f32 {self.risk.get(pos).copied().unwrap_or_default()}
+}
+
+/// Closes the pistes and destroys the structures in the way of the slides, and logs them.
+fn apply_slides(
+	commands: &mut Commands,
+	avalanches: &mut Avalanches,
+	slides: Vec<Slide>,
+	day: u32,
+	surfaces: &CellSurfaces,
+	objects: &PlacedObjects,
+	structure_types: &StructureTypes,
+) {
+	let mut destroyed = HashSet::new();
+	for slide in slides {
+		let cells: HashSet<Hex> = slide.cells().collect();
+		let mut report = SlideReport {day: day, pistes_closed: 0, structures_destroyed: Vec::new(), slide: Slide::default()};
+		if !slide.controlled {
+			for pos in cells.iter().filter(|pos| surfaces.0.get(*pos) == Some(&Surface::Piste)) {
+				avalanches.closed.insert(*pos, CLOSURE_DAYS);
+				report.pistes_closed += 1;
+			}
+		}
+		for (instance_id, object) in objects.iter() {
+			let ObjectInstance::Structure(instance) = object else {continue};
+			if !cells.contains(&instance.position) || destroyed.contains(instance_id) {continue}
+			let Some(structure_type) = structure_types.0.get(&instance.type_id) else {continue};
+			if structure_type.role == StructureRole::AvalancheBarrier {continue}
+			report.structures_destroyed.push(structure_type.name);
+			destroyed.insert(*instance_id);
+			commands.trigger(RemoveObject(*instance_id));
+		}
+		if !slide.controlled || !report.structures_destroyed.is_empty() {
+			info!("Avalanche from {:?} ran {} cells, closing {} piste cells and destroying {:?}.", slide.start, slide.path.len(), report.pistes_closed, report.structures_destroyed);
+		}
+		report.slide = slide;
+		avalanches.reports.push_front(report);
+		avalanches.reports.truncate(REPORTED_SLIDES);
+	}
+}
+
+fn load_and_release(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	weather: Res<Weather>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	mut snow: ResMut<CellSnow>,
+	mut avalanches: ResMut<Avalanches>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	let protection = Protection::from_objects(&objects, &structure_types);
+	let loading = Loading {snowfall: weather.snowfall_per_minute(), wind: weather.today.wind, wind_direction: weather.today.wind_direction};
+	load_risk(&mut avalanches.risk, &heights.0, &snow.0, &protection, &loading, minutes);
+	if !avalanches.risk.values().any(|cell_risk| *cell_risk >= RELEASE_RISK) {return}
+	let slides = release_slides(&mut avalanches.risk, &heights.0, &mut snow.0, &protection);
+	apply_slides(&mut commands, &mut avalanches, slides, clock.day, &surfaces, &objects, &structure_types);
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Blasts every slope with at least `CONTROL_MIN_RISK`, riskiest first, for as long as the explosives can be afforded.
+/// Controlled slides don't close any pistes, but can still destroy structures.
+pub struct ControlAvalanches;
+fn control_avalanches(
+	_trigger: Trigger<ControlAvalanches>,
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	mut snow: ResMut<CellSnow>,
+	mut avalanches: ResMut<Avalanches>,
+	mut economy: ResMut<Economy>,
+) {
+	let protection = Protection::from_objects(&objects, &structure_types);
+	let mut targets: Vec<(Hex, f32)> = avalanches.risk.iter().filter(|(_, cell_risk)| **cell_risk >= CONTROL_MIN_RISK).map(|(pos, cell_risk)| (*pos, *cell_risk)).collect();
+	targets.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+	let mut slides = Vec::new();
+	for (target, _) in targets {
+		if avalanches.risk(&target) < CONTROL_MIN_RISK {continue}
+		if !economy.try_spend(Category::Maintenance, EXPLOSIVE_COST) {
+			warn!("Can't afford more explosives for avalanche control.");
+			break
+		}
+		let slide = run_slide(target, &heights.0, &mut snow.0, &protection, true);
+		for pos in slide.cells() {
+			avalanches.risk.remove(&pos);
+		}
+		slides.push(slide);
+	}
+	apply_slides(&mut commands, &mut avalanches, slides, clock.day, &surfaces, &objects, &structure_types);
+}
+
+fn reopen_pistes(
+	_trigger: Trigger<DayStarted>,
+	mut avalanches: ResMut<Avalanches>,
+) {
+	if avalanches.closed.is_empty() {return}
+	avalanches.closed.retain(|_, days| {
+		*days -= 1;
+		*days > 0
+	});
+}
+
+/// Outlines risky cells from yellow to red, and closed pistes in purple.
+fn risk_overlay(
+	avalanches: Res<Avalanches>,
+	snow: Res<CellSnow>,
+) -> CellColors {
+	let mut colors: CellColors = snow.0.keys().map(|pos| (*pos, scale_color(avalanches.risk(pos) / RELEASE_RISK))).collect();
+	for pos in avalanches.closed.keys() {
+		colors.insert(*pos, Color::from(tailwind::PURPLE_500).to_linear());
+	}
+	colors
+}
+
+fn draw_risk(
+	mut gizmos: Gizmos,
+	avalanches: Res<Avalanches>,
+	heights: Res<CellHeights>,
+) {
+	let low = Color::from(tailwind::YELLOW_300);
+	let high = Color::from(tailwind::RED_600);
+	for (pos, cell_risk) in avalanches.risk.iter() {
+		if *cell_risk < 0.2 {continue}
+		cell_outline(&mut *gizmos, &heights.0, pos, low.mix(&high, (*cell_risk / RELEASE_RISK).min(1.)));
+	}
+	for pos in avalanches.closed.keys() {
+		cell_outline(&mut *gizmos, &heights.0, pos, tailwind::PURPLE_500);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grid::object::structure::StructureTypes;
+
+	/// Snow deep enough to slide on every cell.
+	fn deep_snow(heights: &HashMap<Hex, u16>) -> HashMap<Hex, SnowCell> {
+		heights.keys().map(|pos| (*pos, SnowCell {depth: 100., ..default()})).collect()
+	}
+
+	#[test]
+	fn risk_rises_with_steepness() {
+		let loading = Loading {snowfall: 1., ..default()};
+		let risk_on_ramp = |step: u16| {
+			let heights: HashMap<Hex, u16> = Hex::ZERO.range(5).map(|pos| (pos, (100 - step as i32 * pos.x) as u16)).collect();
+			let mut risk = HashMap::new();
+			load_risk(&mut risk, &heights, &deep_snow(&heights), &Protection::default(), &loading, 10.);
+			risk.get(&Hex::ZERO).copied().unwrap_or_default()
+		};
+		let (flat, gentle, steep) = (risk_on_ramp(1), risk_on_ramp(2), risk_on_ramp(4));
+		assert_eq!(flat, 0.);
+		assert!(0. < gentle && gentle < steep, "{} should be more than {}", steep, gentle);
+	}
+
+	#[test]
+	fn slides_stop_on_flat_ground() {
+		// A steep slope down to x = 0, then a gentle runout down to a flat valley.
+		let heights: HashMap<Hex, u16> = Hex::ZERO.range(12).map(|pos| {
+			let height = if pos.x < 0 {10 + 4 * -pos.x} else {(10 - pos.x).max(6)};
+			(pos, height as u16)
+		}).collect();
+		let mut snow = deep_snow(&heights);
+		let slide = run_slide(Hex::new(-6, 0), &heights, &mut snow, &Protection::default(), false);
+		let end = *slide.path.last().unwrap();
+		assert!(slide.path.len() < MAX_SLIDE_LENGTH);
+		assert_eq!(steepness(&heights, &end), 0., "the slide should end on flat ground, not at {:?}", end);
+		assert!(!slide.buried.is_empty());
+		for (pos, depth) in slide.buried.iter() {
+			assert_eq!(steepness(&heights, pos), 0., "{:?} is too steep to bury", pos);
+			assert!(*depth > 0. && snow[pos].depth > 100.);
+		}
+	}
+
+	#[test]
+	fn closed_pistes_are_reported() {
+		let (a, b, c, d, e) = (Hex::new(0, 0), Hex::new(1, 0), Hex::new(2, 0), Hex::new(3, 0), Hex::new(0, 3));
+		let surfaces = CellSurfaces([(a, Surface::None), (b, Surface::Piste), (c, Surface::None), (d, Surface::Piste), (e, Surface::Piste)].into());
+		let slide = Slide {start: a, path: vec![a, b, c], buried: vec![(d, 5.)], controlled: false};
+		let controlled = Slide {start: e, path: vec![e], buried: Vec::new(), controlled: true};
+		let mut world = World::new();
+		let mut avalanches = Avalanches::default();
+		apply_slides(&mut world.commands(), &mut avalanches, vec![slide, controlled], 4, &surfaces, &PlacedObjects::default(), &StructureTypes(HashMap::new()));
+		assert_eq!(avalanches.closed, [(b, CLOSURE_DAYS), (d, CLOSURE_DAYS)].into());
+		assert!(avalanches.is_closed(&b) && !avalanches.is_closed(&e));
+		let pistes_closed: Vec<usize> = avalanches.reports.iter().map(|report| report.pistes_closed).collect();
+		// Newest first.
+		assert_eq!(pistes_closed, vec![0, 2]);
+		assert!(avalanches.reports.iter().all(|report| report.day == 4));
+	}
+}
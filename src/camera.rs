@@ -1,34 +1,207 @@
 use bevy::{
 	prelude::*,
-	input::mouse::{MouseButton, AccumulatedMouseMotion},
+	color::palettes::tailwind,
+	input::mouse::{MouseButton, MouseScrollUnit, AccumulatedMouseMotion, AccumulatedMouseScroll},
 	window::{CursorGrabMode, PrimaryWindow}
 };
-use std::f32::consts::PI;
+use bevy_egui::EguiContexts;
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::{PI, FRAC_PI_2};
+use hexx::Hex;
+
+pub mod bookmark;
+pub mod flythrough;
+
+use crate::util::hex::xz_to_axial;
+use crate::tool::pick::Target;
+use crate::guest::Guest;
+use crate::staff::StaffMember;
+use crate::grooming::Snowcat;
+use crate::grid::{CellHeights, PlacedObjects, geometry::gizmo::cell_top, object::{structure::StructureTypes, lift::LiftTypes}};
+
+/// Distance from the camera to what it follows.
+pub const FOLLOW_DISTANCE: f32 = 30.;
+/// How quickly the camera catches up with what it follows, per second.
+pub const FOLLOW_SMOOTHING: f32 = 4.;
+/// The camera is kept at least this far above the terrain.
+pub const GROUND_CLEARANCE: f32 = 2.;
+/// Closest and furthest the orbit camera can zoom to its focus.
+pub const MIN_ORBIT_DISTANCE: f32 = 8.;
+pub const MAX_ORBIT_DISTANCE: f32 = 300.;
+/// Share of the distance zoomed per line scrolled.
+pub const ZOOM_STEP: f32 = 0.1;
+/// Pitch of the orbit camera, between almost straight down and almost level.
+pub const MIN_ORBIT_PITCH: f32 = -FRAC_PI_2 + 0.05;
+pub const MAX_ORBIT_PITCH: f32 = -0.1;
+/// Turning speed of the orbit camera with Q and E, in radians per second.
+pub const ORBIT_TURN_SPEED: f32 = 1.5;
+/// Pixels from the edge of the window where the cursor pans the orbit camera.
+pub const EDGE_SCROLL_MARGIN: f32 = 8.;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum CameraMode {
+	/// Flies around freely, looking around with the right mouse button.
+	#[default]
+	Free,
+	/// Looks down at a point on the terrain, panning over it and turning around it.
+	Orbit,
+}
+impl CameraMode {
+	pub const ALL: [Self; 2] = [Self::Free, Self::Orbit];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Free => "Free",
+			Self::Orbit => "Orbit",
+		}
+	}
+}
 
 #[derive(Debug, Clone, Copy, Resource)]
 pub struct CameraSettings {
 	/// Vertical field of view in degrees.
 	pub fov: f32,
 	pub movement_speed: f32,
-	pub sensitivity: f32
+	pub sensitivity: f32,
+	pub mode: CameraMode,
+	/// Point on the terrain that the orbit camera looks at.
+	pub focus: Vec3,
+	/// Distance from the orbit camera to its focus.
+	pub distance: f32,
+	/// Direction the orbit camera looks at its focus from, in radians.
+	pub yaw: f32,
+	pub pitch: f32,
+	/// Pans the orbit camera when the cursor is at the edge of the window.
+	pub edge_scrolling: bool,
 }
 impl Default for CameraSettings {
 	fn default() -> Self {
 		Self {
 			fov: 70.,
 			movement_speed: 40.,
-			sensitivity: 0.001
+			sensitivity: 0.001,
+			mode: CameraMode::Free,
+			focus: Vec3::ZERO,
+			distance: 60.,
+			yaw: 1.25 * PI,
+			pitch: PI / -4.,
+			edge_scrolling: true,
 		}
 	}
 }
+impl CameraSettings {
+	/// Switches modes. The orbit camera starts out looking at the terrain the camera was looking at.
+	pub fn switch_mode(&mut self, mode: CameraMode, camera: &Transform, heights: &CellHeights) {
+		if mode == CameraMode::Orbit && self.mode != CameraMode::Orbit {
+			let (yaw, pitch, _) = camera.rotation.to_euler(EulerRot::YXZ);
+			self.yaw = yaw;
+			self.pitch = pitch.clamp(MIN_ORBIT_PITCH, MAX_ORBIT_PITCH);
+			self.focus = look_target(&heights.0, camera).unwrap_or(camera.translation + camera.forward().as_vec3() * self.distance);
+			self.distance = camera.translation.distance(self.focus).clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+		}
+		self.mode = mode;
+	}
+
+	/// Horizontal directions the orbit camera pans in, forward and right.
+	pub fn pan_directions(&self) -> (Vec3, Vec3) {
+		(Vec3::new(-self.yaw.sin(), 0., -self.yaw.cos()), Vec3::new(self.yaw.cos(), 0., -self.yaw.sin()))
+	}
+}
+
+/// Camera looking at the focus from the distance, yaw and pitch.
+pub fn orbit_transform(focus: Vec3, yaw: f32, pitch: f32, distance: f32) -> Transform {
+	let rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.);
+	Transform {translation: focus + rotation * Vec3::Z * distance, rotation: rotation, ..default()}
+}
+
+/// Highest terrain around a point, since the terrain between cell centers can be as high as any neighbor.
+pub fn ground_height(heights: &HashMap<Hex, u16>, point: Vec3) -> Option<f32> {
+	let pos = xz_to_axial(point.x, point.z);
+	pos.all_neighbors().into_iter().chain([pos]).filter_map(|pos| heights.get(&pos)).max().map(|height| *height as f32)
+}
+
+/// Keys that move the camera by hand, which stop it from following, flying to a bookmark or flying through.
+pub const MOVEMENT_KEYS: [KeyCode; 6] = [KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyQ];
+
+/// Where the camera's line of sight first goes below the terrain, if it does nearby.
+fn look_target(heights: &HashMap<Hex, u16>, camera: &Transform) -> Option<Vec3> {
+	let forward = camera.forward().as_vec3();
+	(0..MAX_ORBIT_DISTANCE as u32).map(|step| camera.translation + forward * step as f32).find(|point| {
+		let pos = xz_to_axial(point.x, point.z);
+		heights.get(&pos).is_some_and(|height| point.y <= *height as f32)
+	})
+}
+
+/// Something the camera can follow.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Followed {
+	Target(Target),
+	/// A guest, staff member or snowcat.
+	Agent(Entity),
+}
+
+/// What the camera keeps in the middle of the view, if anything.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Resource)]
+pub struct CameraFollow(pub Option<Followed>);
+
+/// Everything that moves around the resort on its own.
+pub type Agents<'w, 's> = Query<'w, 's, AnyOf<(&'static Guest, &'static StaffMember, &'static Snowcat)>>;
+
+/// Where a guest, staff member or snowcat is, partway to the next cell on their path.
+pub fn agent_center(entity: Entity, agents: &Agents, heights: &CellHeights) -> Option<Vec3> {
+	let between = |position: &Hex, path: &VecDeque<Hex>, progress: f32| {
+		let here = cell_top(&heights.0, position)?;
+		Some(path.front().and_then(|next| cell_top(&heights.0, next)).map_or(here, |next| here.lerp(next, progress)))
+	};
+	match agents.get(entity).ok()? {
+		(Some(guest), _, _) => cell_top(&heights.0, &guest.position),
+		(_, Some(member), _) => between(&member.position, &member.path, member.progress),
+		(_, _, Some(snowcat)) => between(&snowcat.position, &snowcat.path, snowcat.progress),
+		(None, None, None) => None,
+	}
+}
+
+/// Describes what the camera follows, for the camera window.
+pub fn describe_followed(followed: Followed, agents: &Agents, objects: &PlacedObjects, structure_types: &StructureTypes, lift_types: &LiftTypes) -> String {
+	match followed {
+		Followed::Target(target) => target.describe(objects, structure_types, lift_types),
+		Followed::Agent(entity) => match agents.get(entity) {
+			Ok((Some(guest), _, _)) => format!("Guest at {}, {}", guest.position.x, guest.position.y),
+			Ok((_, Some(member), _)) => format!("{} at {}, {}", member.role.name(), member.position.x, member.position.y),
+			Ok((_, _, Some(snowcat))) => format!("Snowcat at {}, {}", snowcat.position.x, snowcat.position.y),
+			_ => "Nothing".to_string(),
+		},
+	}
+}
 
 /// Adds a camera that moves around on user input.
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
 	fn build(&self, app: &mut App) {
 		app.insert_resource(CameraSettings::default());
+		app.init_resource::<CameraFollow>();
+		app.init_resource::<bookmark::CameraBookmarks>();
+		app.init_resource::<bookmark::CameraTransition>();
+		app.init_resource::<flythrough::Flythrough>();
+		app.init_resource::<flythrough::FlythroughPlayer>();
 		app.add_systems(Startup, setup);
-		app.add_systems(Update, (movement_system, rotation_system, fov_system));
+		app.add_systems(Update, (
+			(
+				mode_system,
+				follow_system,
+				movement_system,
+				rotation_system,
+				zoom_system,
+				bookmark::bookmark_shortcuts,
+				bookmark::fly_to_bookmark,
+				flythrough::play_flythrough,
+				orbit_system,
+				clearance_system,
+			).chain(),
+			fov_system,
+			draw_followed,
+		));
+		app.add_observer(flythrough::stop_flythrough);
 	}
 }
 
@@ -60,13 +233,83 @@ fn fov_system(
 	}
 }
 
+/// V switches between the free and orbit camera.
+fn mode_system(
+	input: Res<ButtonInput<KeyCode>>,
+	mut settings: ResMut<CameraSettings>,
+	heights: Res<CellHeights>,
+	camera: Single<&Transform, With<Camera3d>>
+) {
+	if !input.just_pressed(KeyCode::KeyV) {return}
+	let mode = match settings.mode {CameraMode::Free => CameraMode::Orbit, CameraMode::Orbit => CameraMode::Free};
+	settings.switch_mode(mode, &camera, &heights);
+}
+
+/// Glides the camera towards what it follows, looking at it from the same direction.
+/// The orbit camera glides its focus instead.
+/// Moving the camera by hand, or the target going away, stops following.
+fn follow_system(
+	input: Res<ButtonInput<KeyCode>>,
+	time: Res<Time<Real>>,
+	mut follow: ResMut<CameraFollow>,
+	mut settings: ResMut<CameraSettings>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+	agents: Agents,
+	mut camera: Single<&mut Transform, With<Camera3d>>
+) {
+	let Some(followed) = follow.0 else {return};
+	let center = match followed {
+		Followed::Target(target) => target.center(&objects, &structure_types, &heights),
+		Followed::Agent(entity) => agent_center(entity, &agents, &heights),
+	};
+	let Some(center) = center.filter(|_| !input.any_pressed(MOVEMENT_KEYS)) else {
+		follow.0 = None;
+		return
+	};
+	let smoothing = 1. - (-FOLLOW_SMOOTHING * time.delta_secs()).exp();
+	match settings.mode {
+		CameraMode::Free => {
+			let goal = center - camera.forward().as_vec3() * FOLLOW_DISTANCE;
+			camera.translation = camera.translation.lerp(goal, smoothing);
+		},
+		CameraMode::Orbit => settings.focus = settings.focus.lerp(center, smoothing),
+	}
+}
+
 /// WASD (+QE) moves the camera around.
+/// The orbit camera pans its focus over the terrain with WASD and the edges of the window, and turns around it with QE.
+/// Uses real time, so the camera can still move while the game is paused.
 fn movement_system(
 	input: Res<ButtonInput<KeyCode>>,
-	time: Res<Time>,
-	settings: Res<CameraSettings>,
+	time: Res<Time<Real>>,
+	mut settings: ResMut<CameraSettings>,
+	window: Single<&Window, With<PrimaryWindow>>,
 	mut camera: Single<&mut Transform, With<Camera3d>>
 ) {
+	if settings.mode == CameraMode::Orbit {
+		let (forward, right) = settings.pan_directions();
+		let mut vec = Vec3::ZERO;
+		if input.pressed(KeyCode::KeyW) {vec += forward}
+		if input.pressed(KeyCode::KeyA) {vec -= right}
+		if input.pressed(KeyCode::KeyS) {vec -= forward}
+		if input.pressed(KeyCode::KeyD) {vec += right}
+		if settings.edge_scrolling && let Some(cursor) = window.cursor_position() {
+			if cursor.x < EDGE_SCROLL_MARGIN {vec -= right}
+			if cursor.x > window.width() - EDGE_SCROLL_MARGIN {vec += right}
+			if cursor.y < EDGE_SCROLL_MARGIN {vec += forward}
+			if cursor.y > window.height() - EDGE_SCROLL_MARGIN {vec -= forward}
+		}
+		// Panning is faster when zoomed out, so it covers about the same share of the view.
+		let speed = settings.movement_speed * settings.distance / CameraSettings::default().distance;
+		if let Some(vec_norm) = vec.try_normalize() {
+			settings.focus += vec_norm * time.delta_secs() * speed;
+		}
+		if input.pressed(KeyCode::KeyQ) {settings.yaw -= ORBIT_TURN_SPEED * time.delta_secs()}
+		if input.pressed(KeyCode::KeyE) {settings.yaw += ORBIT_TURN_SPEED * time.delta_secs()}
+		return
+	}
 	let mut vec = Vec3::ZERO;
 	if input.pressed(KeyCode::KeyW) {vec += camera.forward().as_vec3()}
 	if input.pressed(KeyCode::KeyA) {vec += camera.left().as_vec3()}
@@ -75,38 +318,120 @@ fn movement_system(
 	if input.pressed(KeyCode::KeyE) {vec += camera.up().as_vec3()}
 	if input.pressed(KeyCode::KeyQ) {vec += camera.down().as_vec3()}
 
-	match vec.try_normalize() {
-		Some(vec_norm) => camera.translation += vec_norm * time.delta_secs() * settings.movement_speed,
-		None => {}
+	if let Some(vec_norm) = vec.try_normalize() {
+		camera.translation += vec_norm * time.delta_secs() * settings.movement_speed;
 	}
 }
 
 /// Right click hides and locks the cursor, and rotates the camera with mouse movement.
+/// The orbit camera turns around its focus with the middle mouse button instead.
 fn rotation_system(
-	settings: Res<CameraSettings>,
+	mut settings: ResMut<CameraSettings>,
 	mut camera: Single<&mut Transform, With<Camera3d>>,
 	mouse_button: Res<ButtonInput<MouseButton>>,
 	mouse_motion: Res<AccumulatedMouseMotion>,
 	mut window: Single<&mut Window, With<PrimaryWindow>>
 ) {
-	if mouse_button.just_pressed(MouseButton::Right) {
+	let button = match settings.mode {CameraMode::Free => MouseButton::Right, CameraMode::Orbit => MouseButton::Middle};
+	if mouse_button.just_pressed(button) {
 		window.cursor_options.grab_mode = CursorGrabMode::Locked;
 		window.cursor_options.visible = false;
 	}
-	if mouse_button.just_released(MouseButton::Right) {
+	if mouse_button.any_just_released([MouseButton::Right, MouseButton::Middle]) {
 		window.cursor_options.grab_mode = CursorGrabMode::None;
 		window.cursor_options.visible = true;
 	}
 
-	if mouse_button.pressed(MouseButton::Right) &&  mouse_motion.delta != Vec2::ZERO {
+	if mouse_button.pressed(button) && mouse_motion.delta != Vec2::ZERO {
 		let delta = -mouse_motion.delta * settings.sensitivity;
-		let (yaw, pitch, roll) = camera.rotation.to_euler(EulerRot::YXZ);
-
 		// If the window is more flat rather than tall, the vertical mouse sensitivity is decreased.
 		let aspect_ratio = window.width() / window.height(); // TODO: Find a way to avoid this calculation on every single frame.
+		if settings.mode == CameraMode::Orbit {
+			settings.yaw += delta.x;
+			settings.pitch = (settings.pitch + delta.y / aspect_ratio).clamp(MIN_ORBIT_PITCH, MAX_ORBIT_PITCH);
+			return
+		}
+		let (yaw, pitch, roll) = camera.rotation.to_euler(EulerRot::YXZ);
 
 		/// A pitch of +- pi/2 radians means looking straight up or down, which causes issues. This value clamps it 0.01 to radians before that.
 		const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
 		camera.rotation = Quat::from_euler(EulerRot::YXZ, yaw + delta.x, (pitch + delta.y / aspect_ratio).clamp(-PITCH_LIMIT, PITCH_LIMIT), roll)
 	}
 }
+
+/// The scroll wheel zooms the orbit camera in and out, unless the cursor is over a window.
+fn zoom_system(
+	mut contexts: EguiContexts,
+	mut settings: ResMut<CameraSettings>,
+	scroll: Res<AccumulatedMouseScroll>
+) {
+	if settings.mode != CameraMode::Orbit || scroll.delta.y == 0. {return}
+	if contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area()) {return}
+	let lines = match scroll.unit {
+		MouseScrollUnit::Line => scroll.delta.y,
+		MouseScrollUnit::Pixel => scroll.delta.y / 100.,
+	};
+	settings.distance = (settings.distance * (1. - ZOOM_STEP).powf(lines)).clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+}
+
+/// Keeps the orbit camera's focus on the terrain, and places the camera around it.
+fn orbit_system(
+	mut settings: ResMut<CameraSettings>,
+	heights: Res<CellHeights>,
+	mut camera: Single<&mut Transform, With<Camera3d>>
+) {
+	if settings.mode != CameraMode::Orbit {return}
+	let focus = settings.focus;
+	if let Some(height) = heights.0.get(&xz_to_axial(focus.x, focus.z)) {
+		// Only write when it moves, so the settings aren't marked as changed every frame.
+		if focus.y != *height as f32 {settings.focus.y = *height as f32}
+	}
+	**camera = orbit_transform(settings.focus, settings.yaw, settings.pitch, settings.distance);
+}
+
+/// Marks the guest, staff member or snowcat being followed, since they have no model of their own.
+fn draw_followed(
+	mut gizmos: Gizmos,
+	follow: Res<CameraFollow>,
+	heights: Res<CellHeights>,
+	agents: Agents,
+) {
+	let Some(Followed::Agent(entity)) = follow.0 else {return};
+	let Some(center) = agent_center(entity, &agents, &heights) else {return};
+	gizmos.sphere(Isometry3d::from_translation(center + Vec3::Y), 0.5, tailwind::AMBER_400);
+}
+
+/// Lifts the camera out of the terrain. The orbit camera keeps looking at its focus.
+fn clearance_system(
+	settings: Res<CameraSettings>,
+	heights: Res<CellHeights>,
+	mut camera: Single<&mut Transform, With<Camera3d>>
+) {
+	let Some(ground) = ground_height(&heights.0, camera.translation) else {return};
+	if camera.translation.y >= ground + GROUND_CLEARANCE {return}
+	camera.translation.y = ground + GROUND_CLEARANCE;
+	if settings.mode == CameraMode::Orbit {
+		camera.look_at(settings.focus, Vec3::Y);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn orbit_camera_looks_at_focus() {
+		let focus = Vec3::new(10., 5., -3.);
+		let camera = orbit_transform(focus, 0.7, -0.6, 40.);
+		assert!((camera.translation.distance(focus) - 40.).abs() < 1e-3);
+		assert!(camera.forward().as_vec3().dot((focus - camera.translation).normalize()) > 0.999);
+		assert!(camera.translation.y > focus.y);
+	}
+
+	#[test]
+	fn ground_includes_neighbors() {
+		let heights: HashMap<Hex, u16> = [(Hex::ZERO, 5), (Hex::new(1, 0), 20)].into();
+		assert_eq!(ground_height(&heights, Vec3::ZERO), Some(20.));
+		assert_eq!(ground_height(&heights, Vec3::new(100., 0., 100.)), None);
+	}
+}
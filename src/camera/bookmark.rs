@@ -0,0 +1,151 @@
+use std::f32::consts::{PI, TAU};
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+
+use crate::grid::CellHeights;
+use super::{CameraSettings, CameraMode, CameraFollow, MOVEMENT_KEYS, flythrough::FlythroughPlayer};
+
+/// Seconds it takes to fly to a bookmark.
+pub const BOOKMARK_FLIGHT_SECONDS: f32 = 1.;
+/// Keys of the bookmarks, which recall them, or save them while holding control.
+pub const BOOKMARK_KEYS: [KeyCode; 9] = [
+	KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3,
+	KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6,
+	KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+];
+
+/// Where the camera is and where it looks, in either mode.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CameraView {
+	pub mode: CameraMode,
+	pub translation: Vec3,
+	pub rotation: Quat,
+	pub focus: Vec3,
+	pub distance: f32,
+	pub yaw: f32,
+	pub pitch: f32,
+}
+impl CameraView {
+	pub fn capture(settings: &CameraSettings, camera: &Transform) -> Self {
+		Self {
+			mode: settings.mode,
+			translation: camera.translation,
+			rotation: camera.rotation,
+			focus: settings.focus,
+			distance: settings.distance,
+			yaw: settings.yaw,
+			pitch: settings.pitch,
+		}
+	}
+
+	/// Partway from this view to the other, turning the shortest way around.
+	pub fn lerp(&self, other: &Self, t: f32) -> Self {
+		Self {
+			mode: other.mode,
+			translation: self.translation.lerp(other.translation, t),
+			rotation: self.rotation.slerp(other.rotation, t),
+			focus: self.focus.lerp(other.focus, t),
+			distance: self.distance + (other.distance - self.distance) * t,
+			yaw: self.yaw + angle_difference(self.yaw, other.yaw) * t,
+			pitch: self.pitch + (other.pitch - self.pitch) * t,
+		}
+	}
+
+	/// Moves the camera to the view. The orbit camera is placed around its focus later in the frame.
+	pub fn apply(&self, settings: &mut CameraSettings, camera: &mut Transform) {
+		settings.mode = self.mode;
+		settings.focus = self.focus;
+		settings.distance = self.distance;
+		settings.yaw = self.yaw;
+		settings.pitch = self.pitch;
+		if self.mode == CameraMode::Free {
+			camera.translation = self.translation;
+			camera.rotation = self.rotation;
+		}
+	}
+}
+
+/// Angle to turn from one angle to the other the shortest way, between -π and π.
+pub fn angle_difference(from: f32, to: f32) -> f32 {
+	(to - from + PI).rem_euclid(TAU) - PI
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct CameraBookmarks(pub [Option<CameraView>; 9]);
+
+/// A flight from one view to another.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Flight {
+	pub from: CameraView,
+	pub to: CameraView,
+	pub elapsed: f32,
+}
+impl Flight {
+	/// Flying into orbit turns the camera into an orbit camera first, so it can glide around its focus.
+	pub fn new(to: CameraView, settings: &CameraSettings, camera: &Transform, heights: &CellHeights) -> Self {
+		let mut start = *settings;
+		start.switch_mode(to.mode, camera, heights);
+		Self {from: CameraView::capture(&start, camera), to: to, elapsed: 0.}
+	}
+}
+
+/// The flight to a bookmark, if the camera is on its way to one.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct CameraTransition(pub Option<Flight>);
+
+/// Control and a number saves the view as a bookmark, and the number alone flies back to it.
+pub fn bookmark_shortcuts(
+	mut contexts: EguiContexts,
+	input: Res<ButtonInput<KeyCode>>,
+	mut bookmarks: ResMut<CameraBookmarks>,
+	mut transition: ResMut<CameraTransition>,
+	mut follow: ResMut<CameraFollow>,
+	mut player: ResMut<FlythroughPlayer>,
+	settings: Res<CameraSettings>,
+	heights: Res<CellHeights>,
+	camera: Single<&Transform, With<Camera3d>>
+) {
+	if contexts.try_ctx_mut().is_some_and(|ctx| ctx.wants_keyboard_input()) {return}
+	let Some(slot) = BOOKMARK_KEYS.iter().position(|key| input.just_pressed(*key)) else {return};
+	if input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+		bookmarks.0[slot] = Some(CameraView::capture(&settings, &camera));
+	} else if let Some(view) = bookmarks.0[slot] {
+		transition.0 = Some(Flight::new(view, &settings, &camera, &heights));
+		follow.0 = None;
+		player.elapsed = None;
+	}
+}
+
+/// Glides the camera to the bookmark, slowing down at both ends. Moving the camera by hand stops it.
+pub fn fly_to_bookmark(
+	input: Res<ButtonInput<KeyCode>>,
+	time: Res<Time<Real>>,
+	mut transition: ResMut<CameraTransition>,
+	mut settings: ResMut<CameraSettings>,
+	mut camera: Single<&mut Transform, With<Camera3d>>
+) {
+	let Some(mut flight) = transition.0 else {return};
+	if input.any_pressed(MOVEMENT_KEYS) {
+		transition.0 = None;
+		return
+	}
+	flight.elapsed += time.delta_secs();
+	let t = (flight.elapsed / BOOKMARK_FLIGHT_SECONDS).min(1.);
+	flight.from.lerp(&flight.to, t * t * (3. - 2. * t)).apply(&mut settings, &mut camera);
+	transition.0 = if t < 1. {Some(flight)} else {None};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn views_turn_the_short_way() {
+		assert!((angle_difference(0.1, TAU - 0.1) + 0.2).abs() < 1e-5);
+		assert!((angle_difference(-3., 3.) - (6. - TAU)).abs() < 1e-5);
+		let view = CameraView::capture(&CameraSettings::default(), &Transform::default());
+		let turned = CameraView {yaw: view.yaw + PI / 2., ..view};
+		assert_eq!(view.lerp(&turned, 1.).yaw, turned.yaw);
+		assert!((view.lerp(&turned, 0.5).yaw - (view.yaw + PI / 4.)).abs() < 1e-5);
+	}
+}
@@ -0,0 +1,102 @@
+use bevy::{prelude::*, math::cubic_splines::{CubicCardinalSpline, CubicGenerator}};
+use serde::{Serialize, Deserialize};
+
+use crate::save::ResortLoaded;
+use super::{CameraSettings, CameraMode, CameraFollow, MOVEMENT_KEYS};
+
+/// Seconds to fly from one keyframe to the next in a new flythrough.
+pub const DEFAULT_SEGMENT_SECONDS: f32 = 4.;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Keyframe {
+	pub translation: Vec3,
+	pub rotation: Quat,
+}
+impl From<&Transform> for Keyframe {
+	fn from(transform: &Transform) -> Self {Self {translation: transform.translation, rotation: transform.rotation}}
+}
+
+/// A path for the camera to fly along, through views recorded by the player.
+/// Saved with the resort, so it can be shown off.
+#[derive(Resource, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Flythrough {
+	pub keyframes: Vec<Keyframe>,
+	/// Seconds to fly from one keyframe to the next.
+	pub segment_seconds: f32,
+}
+impl Default for Flythrough {
+	fn default() -> Self {Self {keyframes: Vec::new(), segment_seconds: DEFAULT_SEGMENT_SECONDS}}
+}
+impl Flythrough {
+	/// Seconds from the first keyframe to the last.
+	pub fn duration(&self) -> f32 {self.keyframes.len().saturating_sub(1) as f32 * self.segment_seconds}
+
+	/// Where the camera is after flying for the seconds, or None with fewer than two keyframes.
+	/// The camera moves along a Catmull-Rom spline through the keyframes, and turns smoothly between them.
+	pub fn sample(&self, seconds: f32) -> Option<Transform> {
+		let curve = CubicCardinalSpline::new_catmull_rom(self.keyframes.iter().map(|keyframe| keyframe.translation)).to_curve().ok()?;
+		let last = self.keyframes.len() - 1;
+		let t = (seconds / self.segment_seconds).clamp(0., last as f32);
+		let index = (t as usize).min(last - 1);
+		let part = t - index as f32;
+		let rotation = self.keyframes[index].rotation.slerp(self.keyframes[index + 1].rotation, part * part * (3. - 2. * part));
+		Some(Transform::from_translation(curve.position(t)).with_rotation(rotation))
+	}
+}
+
+/// Seconds into the flythrough, while it's playing.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct FlythroughPlayer {
+	pub elapsed: Option<f32>,
+}
+
+/// Flies the free camera along the flythrough. Moving the camera by hand or escape stops it.
+pub fn play_flythrough(
+	input: Res<ButtonInput<KeyCode>>,
+	time: Res<Time<Real>>,
+	flythrough: Res<Flythrough>,
+	mut player: ResMut<FlythroughPlayer>,
+	mut settings: ResMut<CameraSettings>,
+	mut follow: ResMut<CameraFollow>,
+	mut camera: Single<&mut Transform, With<Camera3d>>
+) {
+	let Some(elapsed) = player.elapsed else {return};
+	let stopped = input.any_pressed(MOVEMENT_KEYS) || input.just_pressed(KeyCode::Escape) || elapsed > flythrough.duration();
+	let Some(transform) = flythrough.sample(elapsed).filter(|_| !stopped) else {
+		player.elapsed = None;
+		return
+	};
+	if settings.mode != CameraMode::Free {settings.mode = CameraMode::Free}
+	if follow.0.is_some() {follow.0 = None}
+	**camera = transform;
+	player.elapsed = Some(elapsed + time.delta_secs());
+}
+
+/// The loaded resort comes with its own flythrough.
+pub fn stop_flythrough(
+	_trigger: Trigger<ResortLoaded>,
+	mut player: ResMut<FlythroughPlayer>,
+) {
+	player.elapsed = None;
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn flythrough_passes_through_keyframes() {
+		let keyframes: Vec<Keyframe> = [Vec3::ZERO, Vec3::new(10., 5., 0.), Vec3::new(20., 0., 10.)].iter()
+		.map(|translation| Keyframe {translation: *translation, rotation: Quat::from_rotation_y(translation.x / 10.)})
+		.collect();
+		let flythrough = Flythrough {keyframes: keyframes.clone(), segment_seconds: 2.};
+		assert_eq!(flythrough.duration(), 4.);
+		for (i, keyframe) in keyframes.iter().enumerate() {
+			let transform = flythrough.sample(i as f32 * 2.).unwrap();
+			assert!(transform.translation.distance(keyframe.translation) < 1e-3, "{:?} should be at {:?}", transform.translation, keyframe.translation);
+			assert!(transform.rotation.angle_between(keyframe.rotation) < 1e-3);
+		}
+		assert_eq!(flythrough.sample(100.), flythrough.sample(4.));
+		assert!(Flythrough {keyframes: keyframes[..1].to_vec(), segment_seconds: 2.}.sample(0.).is_none());
+	}
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+/// Keeps track of the in-game date and time.
+/// The game speed scales `Time<Virtual>`, so systems using `Time` (the default in `Update` and `FixedUpdate`) run on game time.
+pub struct ClockPlugin;
+impl Plugin for ClockPlugin {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(GameClock::default());
+		app.add_systems(Update, (speed_shortcuts, apply_speed, advance_clock).chain());
+	}
+}
+
+/// In-game minutes that pass every second at normal speed.
+pub const MINUTES_PER_SECOND: f32 = 4.;
+pub const MINUTES_PER_DAY: f32 = 24. * 60.;
+pub const DAYS_PER_YEAR: u32 = 365;
+/// Minute of the day when lifts open.
+pub const OPENING_TIME: f32 = 9. * 60.;
+/// Minute of the day when lifts close.
+pub const CLOSING_TIME: f32 = 16. * 60.;
+
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum GameSpeed {
+	Paused,
+	#[default]
+	Normal,
+	Fast,
+	Fastest,
+}
+impl GameSpeed {
+	pub const ALL: [Self; 4] = [Self::Paused, Self::Normal, Self::Fast, Self::Fastest];
+
+	pub const fn multiplier(&self) -> f32 {
+		match self {
+			Self::Paused => 0.,
+			Self::Normal => 1.,
+			Self::Fast => 2.,
+			Self::Fastest => 4.,
+		}
+	}
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Paused => "Pause",
+			Self::Normal => "1×",
+			Self::Fast => "2×",
+			Self::Fastest => "4×",
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum Weekday {
+	Monday,
+	Tuesday,
+	Wednesday,
+	Thursday,
+	Friday,
+	Saturday,
+	Sunday,
+}
+impl Weekday {
+	pub const ALL: [Self; 7] = [Self::Monday, Self::Tuesday, Self::Wednesday, Self::Thursday, Self::Friday, Self::Saturday, Self::Sunday];
+
+	pub const fn is_weekend(&self) -> bool {matches!(self, Self::Saturday | Self::Sunday)}
+}
+
+/// Part of the year, which decides how many guests come and whether the resort is open at all.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum SeasonPhase {
+	PreSeason,
+	Peak,
+	Holidays,
+	Spring,
+	Closed,
+}
+impl SeasonPhase {
+	/// Phase for a day of the year, where day 0 is the first of November.
+	pub const fn from_day_of_year(day: u32) -> Self {
+		match day {
+			0..44 => Self::PreSeason, // November 1st - December 14th.
+			44..52 => Self::Peak,
+			52..67 => Self::Holidays, // Christmas and New Year.
+			67..98 => Self::Peak,
+			98..126 => Self::Holidays, // Winter sports holidays.
+			126..181 => Self::Spring, // Until April 30th.
+			_ => Self::Closed,
+		}
+	}
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::PreSeason => "Pre-season",
+			Self::Peak => "Peak season",
+			Self::Holidays => "Holidays",
+			Self::Spring => "Spring",
+			Self::Closed => "Closed",
+		}
+	}
+
+	/// Guest demand relative to a normal peak season day.
+	pub const fn demand_factor(&self) -> f32 {
+		match self {
+			Self::PreSeason => 0.4,
+			Self::Peak => 1.,
+			Self::Holidays => 1.6,
+			Self::Spring => 0.7,
+			Self::Closed => 0.,
+		}
+	}
+}
+
+/// A minute of the day as hours and minutes, like 09:30.
+pub fn minute_string(minute: f32) -> String {
+	format!("{:02}:{:02}", minute as u32 / 60, minute as u32 % 60)
+}
+
+#[derive(Resource, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct GameClock {
+	/// Days since the game started.
+	pub day: u32,
+	/// Minute of the current day.
+	pub minute: f32,
+	pub speed: GameSpeed,
+	/// Speed to go back to when unpausing.
+	pub unpaused_speed: GameSpeed,
+	/// In-game minutes that passed during the last frame.
+	pub delta_minutes: f32,
+}
+impl Default for GameClock {
+	fn default() -> Self {
+		Self {day: 0, minute: 8. * 60., speed: GameSpeed::Normal, unpaused_speed: GameSpeed::Normal, delta_minutes: 0.}
+	}
+}
+impl GameClock {
+	/// The game starts on the first of November.
+	pub const fn day_of_year(&self) -> u32 {self.day % DAYS_PER_YEAR}
+	pub const fn year(&self) -> u32 {self.day / DAYS_PER_YEAR}
+	/// The game starts on a Monday.
+	pub const fn weekday(&self) -> Weekday {Weekday::ALL[self.day as usize % 7]}
+	pub const fn season_phase(&self) -> SeasonPhase {SeasonPhase::from_day_of_year(self.day_of_year())}
+	pub fn hour(&self) -> f32 {self.minute / 60.}
+
+	/// Whether lifts are running and guests are on the mountain.
+	pub fn is_open(&self) -> bool {
+		self.season_phase() != SeasonPhase::Closed && (OPENING_TIME..CLOSING_TIME).contains(&self.minute)
+	}
+
+	pub fn time_string(&self) -> String {minute_string(self.minute)}
+
+	pub fn set_speed(&mut self, speed: GameSpeed) {
+		if self.speed != GameSpeed::Paused {self.unpaused_speed = self.speed}
+		self.speed = speed;
+	}
+
+	pub fn toggle_pause(&mut self) {
+		if self.speed == GameSpeed::Paused {self.speed = self.unpaused_speed}
+		else {self.set_speed(GameSpeed::Paused)}
+	}
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// A new in-game day started at midnight.
+pub struct DayStarted(pub u32);
+
+/// Space pauses and unpauses, plus and minus change the speed.
+fn speed_shortcuts(
+	input: Res<ButtonInput<KeyCode>>,
+	mut clock: ResMut<GameClock>,
+) {
+	if input.just_pressed(KeyCode::Space) {clock.toggle_pause()}
+	let index = GameSpeed::ALL.iter().position(|speed| *speed == clock.speed).unwrap_or(1);
+	if input.just_pressed(KeyCode::Equal) || input.just_pressed(KeyCode::NumpadAdd) {
+		clock.set_speed(GameSpeed::ALL[(index + 1).min(GameSpeed::ALL.len() - 1)]);
+	}
+	if input.just_pressed(KeyCode::Minus) || input.just_pressed(KeyCode::NumpadSubtract) {
+		clock.set_speed(GameSpeed::ALL[index.saturating_sub(1)]);
+	}
+}
+
+/// Scales virtual time to match the game speed.
+fn apply_speed(
+	clock: Res<GameClock>,
+	mut time: ResMut<Time<Virtual>>,
+) {
+	match clock.speed {
+		GameSpeed::Paused => if !time.is_paused() {time.pause()},
+		speed => {
+			if time.is_paused() {time.unpause()}
+			if time.relative_speed() != speed.multiplier() {time.set_relative_speed(speed.multiplier())}
+		}
+	}
+}
+
+fn advance_clock(
+	mut commands: Commands,
+	time: Res<Time<Virtual>>,
+	mut clock: ResMut<GameClock>,
+) {
+	clock.delta_minutes = time.delta_secs() * MINUTES_PER_SECOND;
+	clock.minute += clock.delta_minutes;
+	while clock.minute >= MINUTES_PER_DAY {
+		clock.minute -= MINUTES_PER_DAY;
+		clock.day += 1;
+		commands.trigger(DayStarted(clock.day));
+	}
+}
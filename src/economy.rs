@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+pub mod ledger;
+pub mod loan;
+pub mod pricing;
+
+use ledger::{Ledger, Category};
+use crate::clock::DayStarted;
+use crate::grid::{
+	PlacedObjects,
+	object::{structure::StructureTypes, lift::LiftTypes},
+};
+
+/// Money is counted in whole currency units.
+pub type Money = i64;
+
+pub struct EconomyPlugin;
+impl Plugin for EconomyPlugin {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(Economy::default());
+		app.add_plugins((loan::LoanPlugin, pricing::PricingPlugin));
+		app.add_observer(pay_running_costs);
+	}
+}
+
+#[derive(Resource, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Economy {
+	pub balance: Money,
+	/// Every transaction made, oldest first.
+	pub ledger: Ledger,
+}
+impl Default for Economy {
+	fn default() -> Self {Self {balance: Self::STARTING_BALANCE, ledger: Ledger::default()}}
+}
+impl Economy {
+	pub const STARTING_BALANCE: Money = 250_000;
+
+	pub const fn can_afford(&self, amount: Money) -> bool {self.balance >= amount}
+
+	/// Adds income to the balance.
+	pub fn earn(&mut self, category: Category, amount: Money) {
+		self.balance += amount;
+		self.ledger.record(category, amount);
+	}
+
+	/// Pays an expense that can't be refused (like wages and upkeep), even if it brings the balance below zero.
+	pub fn charge(&mut self, category: Category, amount: Money) {
+		self.balance -= amount;
+		self.ledger.record(category, -amount);
+	}
+
+	/// Pays an optional expense (like construction) only if it can be afforded.
+	/// Returns false and leaves the balance unchanged if funds are short.
+	pub fn try_spend(&mut self, category: Category, amount: Money) -> bool {
+		if !self.can_afford(amount) {return false}
+		self.charge(category, amount);
+		true
+	}
+}
+
+/// Pays the daily upkeep of all placed objects.
+fn pay_running_costs(
+	_trigger: Trigger<DayStarted>,
+	mut economy: ResMut<Economy>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	lift_types: Res<LiftTypes>,
+) {
+	let upkeep = objects.daily_upkeep(&structure_types, &lift_types);
+	if upkeep > 0 {
+		economy.charge(Category::Maintenance, upkeep);
+	}
+}
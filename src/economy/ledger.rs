@@ -0,0 +1,101 @@
+use serde::{Serialize, Deserialize};
+
+use crate::economy::Money;
+
+/// What a transaction was for.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Category {
+	LiftTickets,
+	SeasonPasses,
+	Food,
+	Rentals,
+	Lessons,
+	Parking,
+	Construction,
+	Staff,
+	Maintenance,
+	SnowmakingEnergy,
+	Fuel,
+	/// Money borrowed or paid back.
+	Loan,
+	Interest,
+}
+impl Category {
+	pub const ALL: [Self; 13] = [
+		Self::LiftTickets, Self::SeasonPasses, Self::Food, Self::Rentals, Self::Lessons, Self::Parking,
+		Self::Construction, Self::Staff, Self::Maintenance, Self::SnowmakingEnergy, Self::Fuel,
+		Self::Loan, Self::Interest,
+	];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::LiftTickets => "Lift tickets",
+			Self::SeasonPasses => "Season passes",
+			Self::Food => "Food",
+			Self::Rentals => "Rentals",
+			Self::Lessons => "Lessons",
+			Self::Parking => "Parking",
+			Self::Construction => "Construction",
+			Self::Staff => "Staff",
+			Self::Maintenance => "Maintenance",
+			Self::SnowmakingEnergy => "Snowmaking energy",
+			Self::Fuel => "Fuel",
+			Self::Loan => "Loans",
+			Self::Interest => "Interest",
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Transaction {
+	pub category: Category,
+	/// Positive for income, negative for expenses.
+	pub amount: Money,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Ledger(Vec<Transaction>);
+impl Ledger {
+	pub fn record(&mut self, category: Category, amount: Money) {
+		self.0.push(Transaction {category: category, amount: amount});
+	}
+
+	pub fn transactions(&self) -> &[Transaction] {&self.0}
+
+	/// Sum of all transactions in a category (negative if it's mostly expenses).
+	pub fn total(&self, category: Category) -> Money {
+		self.0.iter().filter(|transaction| transaction.category == category).map(|transaction| transaction.amount).sum()
+	}
+
+	pub fn income(&self) -> Money {
+		self.0.iter().map(|transaction| transaction.amount).filter(|amount| *amount > 0).sum()
+	}
+
+	/// Sum of all expenses, as a positive number.
+	pub fn expenses(&self) -> Money {
+		-self.0.iter().map(|transaction| transaction.amount).filter(|amount| *amount < 0).sum::<Money>()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn totals_add_up() {
+		let mut ledger = Ledger::default();
+		ledger.record(Category::LiftTickets, 500);
+		ledger.record(Category::Staff, -300);
+		ledger.record(Category::LiftTickets, 200);
+		ledger.record(Category::Loan, 1_000);
+		ledger.record(Category::Loan, -400);
+		assert_eq!(ledger.transactions().len(), 5);
+		assert_eq!(ledger.total(Category::LiftTickets), 700);
+		assert_eq!(ledger.total(Category::Loan), 600);
+		assert_eq!(ledger.total(Category::Food), 0);
+		assert_eq!(ledger.income(), 1_700);
+		assert_eq!(ledger.expenses(), 700);
+		let by_category: Money = Category::ALL.iter().map(|category| ledger.total(*category)).sum();
+		assert_eq!(by_category, ledger.income() - ledger.expenses());
+	}
+}
@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::economy::{Economy, Money, ledger::Category};
+use crate::clock::DayStarted;
+
+pub struct LoanPlugin;
+impl Plugin for LoanPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Loans>();
+		app.add_observer(take_loan);
+		app.add_observer(repay_loan);
+		app.add_observer(pay_interest);
+	}
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Loan {
+	/// Amount that is still owed.
+	pub principal: Money,
+	/// Fraction of the principal that is paid in interest every day.
+	pub daily_interest: f64,
+}
+impl Loan {
+	pub fn daily_interest_cost(&self) -> Money {
+		(self.principal as f64 * self.daily_interest).ceil() as Money
+	}
+}
+
+/// All loans that have not been fully paid back.
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Loans(pub Vec<Loan>);
+impl Loans {
+	/// The most that can be owed at once, across all loans.
+	pub const CREDIT_LIMIT: Money = 500_000;
+	pub const DAILY_INTEREST: f64 = 0.0005;
+
+	pub fn total_owed(&self) -> Money {
+		self.0.iter().map(|loan| loan.principal).sum()
+	}
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Borrows the specified amount, if it fits within the credit limit.
+pub struct TakeLoan(pub Money);
+fn take_loan(
+	trigger: Trigger<TakeLoan>,
+	mut economy: ResMut<Economy>,
+	mut loans: ResMut<Loans>,
+) {
+	let amount = trigger.0;
+	if amount <= 0 {warn!("Can't take a loan of {}.", amount); return}
+	if loans.total_owed() + amount > Loans::CREDIT_LIMIT {
+		warn!("Can't take a loan of {} because it would exceed the credit limit of {} (currently owing {}).", amount, Loans::CREDIT_LIMIT, loans.total_owed());
+		return
+	}
+	loans.0.push(Loan {principal: amount, daily_interest: Loans::DAILY_INTEREST});
+	economy.earn(Category::Loan, amount);
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Pays back part of the loan with the specified index.
+/// The amount is limited to what is owed, and the loan is removed once fully paid back.
+pub struct RepayLoan {
+	pub index: usize,
+	pub amount: Money,
+}
+fn repay_loan(
+	trigger: Trigger<RepayLoan>,
+	mut economy: ResMut<Economy>,
+	mut loans: ResMut<Loans>,
+) {
+	let RepayLoan {index, amount} = *trigger;
+	if amount <= 0 {warn!("Can't repay {} of a loan.", amount); return}
+	let loan = match loans.0.get_mut(index) {Some(loan) => loan, None => {error!("Attempted to repay unknown loan with index {}.", index); return}};
+	let amount = amount.min(loan.principal);
+	if !economy.try_spend(Category::Loan, amount) {
+		warn!("Can't repay {} of loan because the balance is only {}.", amount, economy.balance);
+		return
+	}
+	loan.principal -= amount;
+	if loan.principal <= 0 {
+		loans.0.remove(index);
+	}
+}
+
+fn pay_interest(
+	_trigger: Trigger<DayStarted>,
+	mut economy: ResMut<Economy>,
+	loans: Res<Loans>,
+) {
+	let interest: Money = loans.0.iter().map(|loan| loan.daily_interest_cost()).sum();
+	if interest > 0 {
+		economy.charge(Category::Interest, interest);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn world() -> World {
+		let mut world = World::new();
+		world.init_resource::<Economy>();
+		world.init_resource::<Loans>();
+		world.add_observer(take_loan);
+		world.add_observer(repay_loan);
+		world.add_observer(pay_interest);
+		world
+	}
+
+	#[test]
+	fn loans_stay_within_the_credit_limit() {
+		let mut world = world();
+		world.trigger(TakeLoan(400_000));
+		world.trigger(TakeLoan(200_000));
+		world.trigger(TakeLoan(-1));
+		assert_eq!(world.resource::<Loans>().total_owed(), 400_000);
+		assert_eq!(world.resource::<Economy>().balance, Economy::STARTING_BALANCE + 400_000);
+		assert_eq!(world.resource::<Economy>().ledger.total(Category::Loan), 400_000);
+	}
+
+	#[test]
+	fn interest_is_paid_every_day() {
+		let mut world = world();
+		world.resource_mut::<Loans>().0 = vec![
+			Loan {principal: 100_000, daily_interest: 0.0005},
+			Loan {principal: 1_001, daily_interest: 0.01},
+		];
+		world.trigger(DayStarted(1));
+		// 50 on the first loan, and 10.01 rounded up on the second.
+		assert_eq!(world.resource::<Economy>().balance, Economy::STARTING_BALANCE - 61);
+		assert_eq!(world.resource::<Economy>().ledger.total(Category::Interest), -61);
+		assert_eq!(world.resource::<Loans>().total_owed(), 101_001, "interest doesn't add to what is owed");
+	}
+
+	#[test]
+	fn repaying_only_pays_what_is_owed() {
+		let mut world = world();
+		world.trigger(TakeLoan(10_000));
+		world.trigger(RepayLoan {index: 0, amount: 0});
+		world.trigger(RepayLoan {index: 0, amount: -5_000});
+		assert_eq!(world.resource::<Loans>().total_owed(), 10_000);
+		world.trigger(RepayLoan {index: 0, amount: 4_000});
+		assert_eq!(world.resource::<Loans>().total_owed(), 6_000);
+		world.trigger(RepayLoan {index: 0, amount: 50_000});
+		assert!(world.resource::<Loans>().0.is_empty());
+		assert_eq!(world.resource::<Economy>().balance, Economy::STARTING_BALANCE);
+	}
+}
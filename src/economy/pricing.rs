@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::util::hex::cell_slope;
+use crate::economy::{Economy, Money, ledger::Category};
+use crate::clock::{DayStarted, SeasonPhase, DAYS_PER_YEAR};
+use crate::satisfaction::DEFAULT_RATING;
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::{Surface, PisteGrade},
+	object::ObjectInstance,
+};
+
+pub struct PricingPlugin;
+impl Plugin for PricingPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<TicketPrices>();
+		app.init_resource::<ResortValue>();
+		app.init_resource::<SeasonPassHolders>();
+		app.add_systems(Update, update_resort_value.run_if(
+			resource_changed::<CellHeights>
+			.or(resource_changed::<CellSurfaces>)
+			.or(resource_changed::<PlacedObjects>)
+		));
+		app.add_observer(sell_season_passes);
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Ticket {
+	DayPass,
+	HalfDay,
+	SeasonPass,
+}
+impl Ticket {
+	pub const ALL: [Self; 3] = [Self::DayPass, Self::HalfDay, Self::SeasonPass];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::DayPass => "Day pass",
+			Self::HalfDay => "Half-day pass",
+			Self::SeasonPass => "Season pass",
+		}
+	}
+
+	/// What guests are willing to pay at a resort with a value score of 1.
+	pub const fn base_price(&self) -> f32 {
+		match self {
+			Self::DayPass => 50.,
+			Self::HalfDay => 35.,
+			Self::SeasonPass => 600.,
+		}
+	}
+}
+
+/// Prices set by the player.
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct TicketPrices {
+	pub day_pass: Money,
+	pub half_day: Money,
+	pub season_pass: Money,
+}
+impl Default for TicketPrices {
+	fn default() -> Self {Self {day_pass: 50, half_day: 35, season_pass: 600}}
+}
+impl TicketPrices {
+	pub const fn get(&self, ticket: Ticket) -> Money {
+		match ticket {
+			Ticket::DayPass => self.day_pass,
+			Ticket::HalfDay => self.half_day,
+			Ticket::SeasonPass => self.season_pass,
+		}
+	}
+	pub const fn get_mut(&mut self, ticket: Ticket) -> &mut Money {
+		match ticket {
+			Ticket::DayPass => &mut self.day_pass,
+			Ticket::HalfDay => &mut self.half_day,
+			Ticket::SeasonPass => &mut self.season_pass,
+		}
+	}
+}
+
+/// What the resort has to offer, as perceived by guests deciding whether the prices are worth it.
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct ResortValue {
+	pub lift_count: usize,
+	/// Height difference between the highest and lowest piste cell.
+	pub vertical_drop: u16,
+	/// Number of different piste grades.
+	pub piste_variety: usize,
+	/// 0-1, where 1 is perfect conditions.
+	pub snow_quality: f32,
+	/// Average stars (1-5) from guest reviews.
+	pub rating: f32,
+}
+impl Default for ResortValue {
+	fn default() -> Self {Self {lift_count: 0, vertical_drop: 0, piste_variety: 0, snow_quality: 1., rating: DEFAULT_RATING}}
+}
+impl ResortValue {
+	/// Combined score, where 1 is a small but complete resort.
+	pub fn score(&self) -> f32 {
+		let lifts = (self.lift_count as f32 / 3.).sqrt();
+		let drop = (self.vertical_drop as f32 / 20.).sqrt();
+		let variety = self.piste_variety as f32 / PisteGrade::ALL.len() as f32;
+		(0.4 * lifts + 0.3 * drop + 0.3 * variety) * self.snow_quality
+	}
+
+	/// How word of mouth affects demand, where 1 is an average rating.
+	pub fn reputation(&self) -> f32 {
+		(1. + (self.rating - DEFAULT_RATING) * REPUTATION_PER_STAR).max(0.)
+	}
+
+	/// The price guests consider fair for a ticket at this resort.
+	pub fn fair_price(&self, ticket: Ticket) -> f32 {
+		ticket.base_price() * self.score()
+	}
+}
+
+/// Nobody buys a ticket that costs this many times the fair price or more.
+pub const PRICE_CEILING: f32 = 3.;
+
+/// How many guests are interested in a ticket, relative to how many would come if it was priced fairly.
+/// Free tickets double the demand, it drops off quickly once the price goes above the fair price, and it's 0 at `PRICE_CEILING`.
+pub fn demand(price: Money, fair_price: f32) -> f32 {
+	if fair_price <= 0. {return 0.}
+	let ratio = price.max(0) as f32 / fair_price;
+	if ratio >= PRICE_CEILING {return 0.}
+	2. / (1. + ratio.powi(3))
+}
+
+/// How happy guests are with what they paid, from 0 (ripped off at twice the fair price) to 1 (free), where the fair price is 0.5.
+pub fn price_satisfaction(price: Money, fair_price: f32) -> f32 {
+	if fair_price <= 0. {return 0.}
+	(1. - 0.5 * price.max(0) as f32 / fair_price).clamp(0., 1.)
+}
+
+/// Change in demand per star above or below the default rating.
+pub const REPUTATION_PER_STAR: f32 = 0.15;
+/// Guests arriving per day at a fairly priced resort with a value score of 1.
+pub const BASE_ARRIVALS: f32 = 200.;
+/// Share of guests that would rather buy a half-day pass.
+pub const HALF_DAY_SHARE: f32 = 0.3;
+/// Season pass holders wanted at a fairly priced resort with a value score of 1.
+pub const BASE_SEASON_PASS_MARKET: f32 = 150.;
+
+/// Expected guests per day buying the specified ticket, not counting visiting season pass holders.
+pub fn daily_arrivals(value: &ResortValue, prices: &TicketPrices, ticket: Ticket) -> f32 {
+	let share = match ticket {
+		Ticket::DayPass => 1. - HALF_DAY_SHARE,
+		Ticket::HalfDay => HALF_DAY_SHARE,
+		Ticket::SeasonPass => return 0.,
+	};
+	BASE_ARRIVALS * value.score() * value.reputation() * share * demand(prices.get(ticket), value.fair_price(ticket))
+}
+
+/// How many guests want to own a season pass at the current price.
+pub fn season_pass_market(value: &ResortValue, prices: &TicketPrices) -> f32 {
+	BASE_SEASON_PASS_MARKET * value.score() * value.reputation() * demand(prices.season_pass, value.fair_price(Ticket::SeasonPass))
+}
+
+#[derive(Resource, Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct SeasonPassHolders(pub u32);
+
+fn update_resort_value(
+	mut value: ResMut<ResortValue>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+) {
+	let mut min_height = u16::MAX;
+	let mut max_height = u16::MIN;
+	let mut grades = HashSet::new();
+	for (pos, _) in surfaces.0.iter().filter(|(_, surface)| **surface == Surface::Piste) {
+		let height = match heights.0.get(pos) {Some(height) => *height, None => {error!("Piste cell {:?} is missing a height.", pos); continue}};
+		min_height = min_height.min(height);
+		max_height = max_height.max(height);
+		grades.insert(PisteGrade::from_slope(cell_slope(&heights.0, pos)));
+	}
+	value.lift_count = objects.iter().filter(|(_, object)| matches!(object, ObjectInstance::Lift(_))).count();
+	value.vertical_drop = max_height.saturating_sub(min_height);
+	value.piste_variety = grades.len();
+}
+
+/// Sells new season passes until the number of holders matches the demand.
+/// Holders don't return passes when the demand drops, but all passes expire when a new season starts.
+fn sell_season_passes(
+	trigger: Trigger<DayStarted>,
+	mut economy: ResMut<Economy>,
+	mut holders: ResMut<SeasonPassHolders>,
+	value: Res<ResortValue>,
+	prices: Res<TicketPrices>,
+) {
+	let day_of_year = trigger.0 % DAYS_PER_YEAR;
+	if day_of_year == 0 {holders.0 = 0}
+	if SeasonPhase::from_day_of_year(day_of_year) == SeasonPhase::Closed {return}
+	let market = season_pass_market(&value, &prices) as u32;
+	let sold = market.saturating_sub(holders.0);
+	if sold > 0 {
+		holders.0 += sold;
+		economy.earn(Category::SeasonPasses, sold as Money * prices.season_pass);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn demand_falls_as_price_rises() {
+		let fair_price = 40.;
+		let demands: Vec<f32> = (0..12).map(|i| demand(i * 10, fair_price)).collect();
+		assert!(demands.windows(2).all(|pair| pair[1] < pair[0]), "{:?} should fall", demands);
+		assert_eq!(demand(0, fair_price), 2.);
+		assert_eq!(demand(40, fair_price), 1.);
+	}
+
+	#[test]
+	fn nobody_pays_the_ceiling() {
+		let fair_price = 40.;
+		assert_eq!(demand((fair_price * PRICE_CEILING) as Money, fair_price), 0.);
+		assert_eq!(demand(1_000, fair_price), 0.);
+		assert!(demand((fair_price * PRICE_CEILING) as Money - 1, fair_price) > 0.);
+		assert_eq!(demand(10, 0.), 0.);
+	}
+
+	#[test]
+	fn reputation_raises_demand() {
+		let prices = TicketPrices::default();
+		let value = ResortValue {lift_count: 3, vertical_drop: 20, piste_variety: 2, ..default()};
+		let loved = ResortValue {rating: 5., ..value};
+		let hated = ResortValue {rating: 1., ..value};
+		assert!(loved.reputation() > value.reputation() && value.reputation() > hated.reputation());
+		assert!(daily_arrivals(&loved, &prices, Ticket::DayPass) > daily_arrivals(&value, &prices, Ticket::DayPass));
+		assert!(daily_arrivals(&hated, &prices, Ticket::DayPass) < daily_arrivals(&value, &prices, Ticket::DayPass));
+		assert!(season_pass_market(&loved, &prices) > season_pass_market(&value, &prices));
+	}
+
+	#[test]
+	fn satisfaction_stays_between_0_and_1() {
+		let fair_price = 40.;
+		assert_eq!(price_satisfaction(0, fair_price), 1.);
+		assert_eq!(price_satisfaction(40, fair_price), 0.5);
+		assert_eq!(price_satisfaction(1_000, fair_price), 0.);
+		assert_eq!(price_satisfaction(-10, fair_price), 1.);
+		assert_eq!(price_satisfaction(10, 0.), 0.);
+	}
+}
@@ -0,0 +1,240 @@
+use std::collections::{HashMap, VecDeque};
+use bevy::prelude::*;
+use hexx::Hex;
+use serde::{Serialize, Deserialize};
+
+use crate::clock::{GameClock, DayStarted};
+use crate::economy::{Economy, Money};
+use crate::guest::{Guest, Need, Activity, NEED_THRESHOLD, GUEST_WALKING_SPEED};
+use crate::staff::{Staffing, LESSON_SIZE, SKILL_PER_LESSON_MINUTE};
+use crate::save::ResortLoaded;
+use crate::grid::{
+	PlacedObjects,
+	object::{ObjectInstanceId, ObjectInstance, structure::{StructureTypes, StructureRole}},
+};
+
+/// Structures that guests visit to satisfy their needs, for a price.
+pub struct FacilityPlugin;
+impl Plugin for FacilityPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Facilities>();
+		app.init_resource::<FacilityPrices>();
+		app.add_systems(Update, (seek_facilities, visit_facilities).chain());
+		app.add_observer(close_books);
+		app.add_observer(requeue_guests);
+	}
+}
+
+/// Stats for a type of facility.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Facility {
+	pub need: Need,
+	/// Number of guests that can be served at the same time.
+	pub capacity: u32,
+	/// How long it takes to serve a guest, in in-game minutes.
+	pub service_minutes: f32,
+	pub price: Money,
+	/// Guests further away than this (cells) don't know about the facility.
+	pub catchment: u32,
+}
+
+/// What is going on at a placed facility.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FacilityState {
+	/// Guests waiting to be served, first in line first.
+	/// Not saved, since the guests are new entities after loading.
+	#[serde(skip)]
+	pub queue: VecDeque<Entity>,
+	/// Guests being served right now.
+	pub serving: u32,
+	pub served_today: u32,
+	pub revenue_today: Money,
+	pub revenue_yesterday: Money,
+	pub revenue_total: Money,
+}
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Facilities(pub HashMap<ObjectInstanceId, FacilityState>);
+
+/// Prices set for single facilities, instead of the price of their type.
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FacilityPrices(pub HashMap<ObjectInstanceId, Money>);
+impl FacilityPrices {
+	/// What the facility charges each guest.
+	pub fn get(&self, instance_id: &ObjectInstanceId, facility: &Facility) -> Money {
+		self.0.get(instance_id).copied().unwrap_or(facility.price)
+	}
+}
+
+/// All placed facilities, with their instance ID and position.
+pub fn placed_facilities<'a>(objects: &'a PlacedObjects, structure_types: &'a StructureTypes) -> impl Iterator<Item = (ObjectInstanceId, Hex, Facility)> + 'a {
+	objects.iter().filter_map(|(instance_id, object)| {
+		let ObjectInstance::Structure(instance) = object else {return None};
+		match structure_types.0.get(&instance.type_id)?.role {
+			StructureRole::Facility(facility) => Some((*instance_id, instance.position, facility)),
+			_ => None,
+		}
+	})
+}
+
+/// How many guests the facility can serve at once.
+/// Ski schools can only take as many students as there are instructors to teach them.
+pub fn capacity(instance_id: &ObjectInstanceId, facility: &Facility, staffing: &Staffing) -> u32 {
+	match facility.need {
+		Need::Lessons => facility.capacity.min((staffing.instructors.get(instance_id).copied().unwrap_or_default() * LESSON_SIZE) as u32),
+		_ => facility.capacity,
+	}
+}
+
+/// Sends skiing guests with a pressing need to the nearest facility that satisfies it, taking the queue into account.
+/// Guests go for the most pressing need that a facility in reach can serve, skipping facilities that can't serve anyone.
+fn seek_facilities(
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	facilities: Res<Facilities>,
+	staffing: Res<Staffing>,
+	mut guests: Query<&mut Guest>,
+) {
+	let placed: Vec<_> = placed_facilities(&objects, &structure_types)
+	.filter(|(instance_id, _, facility)| facility.need.is_visited() && capacity(instance_id, facility, &staffing) > 0)
+	.collect();
+	if placed.is_empty() {return}
+	for mut guest in guests.iter_mut() {
+		if guest.activity != Activity::Skiing {continue}
+		// Guests in a lift queue, on a lift, or skiing down from one finish their run first.
+		if guest.run_minutes_left > 0. || guest.run.as_ref().is_some_and(|run| run.lift.is_none()) {continue}
+		let nearest = guest.needs.pressing(NEED_THRESHOLD).into_iter().find_map(|need| placed.iter()
+		.filter(|(_, position, facility)| facility.need == need && guest.position.unsigned_distance_to(*position) <= facility.catchment)
+		.min_by_key(|(instance_id, position, _)| {
+			let queue = facilities.0.get(instance_id).map_or(0, |state| state.queue.len() as u32);
+			guest.position.unsigned_distance_to(*position) + queue
+		}));
+		let Some((instance_id, position, _)) = nearest else {continue};
+		let minutes = guest.position.unsigned_distance_to(*position) as f32 / GUEST_WALKING_SPEED;
+		guest.activity = Activity::Walking {facility: *instance_id, minutes_left: minutes};
+		guest.run = None;
+	}
+}
+
+/// Moves guests through the queues of the facilities, charges them, and satisfies their needs once they're served.
+fn visit_facilities(
+	clock: Res<GameClock>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	staffing: Res<Staffing>,
+	prices: Res<FacilityPrices>,
+	mut facilities: ResMut<Facilities>,
+	mut economy: ResMut<Economy>,
+	mut guests: Query<(Entity, &mut Guest)>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	let placed: HashMap<ObjectInstanceId, (Hex, Facility)> = placed_facilities(&objects, &structure_types).map(|(instance_id, position, facility)| (instance_id, (position, facility))).collect();
+	facilities.0.retain(|instance_id, _| placed.contains_key(instance_id));
+	for state in facilities.0.values_mut() {
+		state.serving = 0;
+	}
+
+	for (entity, mut guest) in guests.iter_mut() {
+		let facility_id = match guest.activity {
+			Activity::Walking {facility, ..} | Activity::Queuing(facility) | Activity::Using {facility, ..} => facility,
+			_ => continue,
+		};
+		let Some((position, facility)) = placed.get(&facility_id).copied() else {
+			// The facility was removed while they were on their way.
+			guest.activity = Activity::Skiing;
+			continue
+		};
+		match guest.activity {
+			Activity::Walking {facility, minutes_left} => {
+				if minutes_left > minutes {
+					guest.activity = Activity::Walking {facility: facility, minutes_left: minutes_left - minutes};
+				} else {
+					guest.position = position;
+					guest.activity = Activity::Queuing(facility);
+					facilities.0.entry(facility).or_default().queue.push_back(entity);
+				}
+			},
+			Activity::Using {facility: facility_id, minutes_left} => {
+				if facility.need == Need::Lessons {
+					guest.skill = (guest.skill + SKILL_PER_LESSON_MINUTE * minutes.min(minutes_left)).min(1.);
+				}
+				if minutes_left > minutes {
+					guest.activity = Activity::Using {facility: facility_id, minutes_left: minutes_left - minutes};
+					facilities.0.entry(facility_id).or_default().serving += 1;
+				} else {
+					*guest.needs.get_mut(facility.need) = 0.;
+					guest.activity = Activity::Skiing;
+				}
+			},
+			Activity::Queuing(_) => guest.experience.queue_minutes += minutes,
+			_ => (),
+		}
+	}
+
+	for (instance_id, state) in facilities.0.iter_mut() {
+		let Some((_, facility)) = placed.get(instance_id) else {continue};
+		// Guests that went home are no longer queuing.
+		state.queue.retain(|entity| guests.get(*entity).is_ok_and(|(_, guest)| guest.activity == Activity::Queuing(*instance_id)));
+		let capacity = capacity(instance_id, facility, &staffing);
+		while state.serving < capacity {
+			let Some(entity) = state.queue.pop_front() else {break};
+			let Ok((_, mut guest)) = guests.get_mut(entity) else {continue};
+			let price = prices.get(instance_id, facility);
+			if price > 0 {
+				economy.earn(facility.need.revenue_category(), price);
+				state.revenue_today += price;
+				state.revenue_total += price;
+			}
+			guest.activity = Activity::Using {facility: *instance_id, minutes_left: facility.service_minutes};
+			state.serving += 1;
+			state.served_today += 1;
+		}
+	}
+}
+
+fn close_books(
+	_trigger: Trigger<DayStarted>,
+	mut facilities: ResMut<Facilities>,
+) {
+	for state in facilities.0.values_mut() {
+		state.revenue_yesterday = state.revenue_today;
+		state.revenue_today = 0;
+		state.served_today = 0;
+	}
+}
+
+/// Queues hold guest entities, which are new after loading, so queuing guests line up again.
+fn requeue_guests(
+	_trigger: Trigger<ResortLoaded>,
+	mut facilities: ResMut<Facilities>,
+	guests: Query<(Entity, &Guest)>,
+) {
+	for state in facilities.0.values_mut() {
+		state.queue.clear();
+	}
+	for (entity, guest) in guests.iter() {
+		if let Activity::Queuing(facility) = guest.activity {
+			facilities.0.entry(facility).or_default().queue.push_back(entity);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ski_schools_need_instructors() {
+		let school = Facility {need: Need::Lessons, capacity: 10, service_minutes: 60., price: 50, catchment: 20};
+		let cafe = Facility {need: Need::Hunger, ..school};
+		let instance_id = ObjectInstanceId(1);
+		let mut staffing = Staffing::default();
+		assert_eq!(capacity(&instance_id, &school, &staffing), 0);
+		assert_eq!(capacity(&instance_id, &cafe, &staffing), 10);
+		staffing.instructors.insert(instance_id, 1);
+		assert_eq!(capacity(&instance_id, &school, &staffing), LESSON_SIZE as u32);
+		staffing.instructors.insert(instance_id, 5);
+		assert_eq!(capacity(&instance_id, &school, &staffing), 10);
+	}
+}
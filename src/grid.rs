@@ -1,24 +1,78 @@
 use std::collections::HashMap;
 use bevy::prelude::*;
 use hexx::Hex;
+use serde::{Serialize, Deserialize};
 
-mod surface;
-mod object;
+use crate::economy::Money;
+use object::{ObjectInstanceId, ObjectInstance, structure::StructureTypes, lift::LiftTypes};
+
+pub mod surface;
+pub mod object;
+pub mod snow;
 mod worldgen;
-mod geometry;
+pub mod geometry;
+pub mod edit;
 
 pub struct GridPlugin;
 impl Plugin for GridPlugin {
 	fn build(&self, app: &mut App) {
-		
+		app.init_resource::<CellHeights>();
+		app.init_resource::<CellSurfaces>();
+		app.init_resource::<PlacedObjects>();
+		app.init_resource::<worldgen::WorldGenSettings>();
+		app.add_systems(PreStartup, worldgen::setup);
+		app.add_plugins((
+			object::ObjectPlugin,
+			snow::SnowPlugin,
+			geometry::GeometryPlugin,
+			edit::EditPlugin,
+		));
 	}
 }
 
-#[derive(Resource)]
-pub struct CellHeights(HashMap<Hex, u16>);
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CellHeights(pub HashMap<Hex, u16>);
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CellSurfaces(pub HashMap<Hex, surface::Surface>);
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PlacedObjects {
+	objects: HashMap<ObjectInstanceId, ObjectInstance>,
+	/// The instance id that the next pushed object will get.
+	/// Only ever goes up, so an id isn't handed out again after its object is removed, and undoing the removal can put it back.
+	next_id: u32,
+}
+impl PlacedObjects {
+	/// Adds the specified ObjectInstance and returns its new instance id.
+	pub fn push(&mut self, object: ObjectInstance) -> ObjectInstanceId {
+		let instance_id = ObjectInstanceId(self.next_id);
+		self.next_id += 1;
+		self.objects.insert(instance_id, object);
+		instance_id
+	}
+
+	/// Adds the ObjectInstance under a specific instance id, like when undoing its removal.
+	pub fn insert(&mut self, instance_id: ObjectInstanceId, object: ObjectInstance) -> Option<ObjectInstance> {
+		self.next_id = self.next_id.max(instance_id.0 + 1);
+		self.objects.insert(instance_id, object)
+	}
+
+	pub fn remove(&mut self, instance_id: &ObjectInstanceId) -> Option<ObjectInstance> {self.objects.remove(instance_id)}
+
+	pub fn get(&self, instance_id: &ObjectInstanceId) -> Option<&ObjectInstance> {self.objects.get(instance_id)}
 
-#[derive(Resource)]
-pub struct CellSurfaces(HashMap<Hex, surface::Surface>);
+	pub fn get_mut(&mut self, instance_id: &ObjectInstanceId) -> Option<&mut ObjectInstance> {self.objects.get_mut(instance_id)}
 
-#[derive(Resource)]
-pub struct PlacedObjects(HashMap<object::ObjectInstanceId, object::ObjectInstance>);
+	pub fn iter(&self) -> impl Iterator<Item = (&ObjectInstanceId, &ObjectInstance)> {self.objects.iter()}
+
+	/// The object on each cell that has one.
+	pub fn occupied(&self, structure_types: &StructureTypes) -> HashMap<Hex, ObjectInstanceId> {
+		self.objects.iter().flat_map(|(instance_id, object)| object.cells(structure_types).into_iter().map(|pos| (pos, *instance_id))).collect()
+	}
+
+	/// Total upkeep per day of all placed objects.
+	pub fn daily_upkeep(&self, structure_types: &StructureTypes, lift_types: &LiftTypes) -> Money {
+		self.objects.values().filter_map(|object| object.upkeep(structure_types, lift_types)).sum()
+	}
+}
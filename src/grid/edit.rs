@@ -0,0 +1,393 @@
+use std::collections::{HashSet, VecDeque};
+use bevy::prelude::*;
+use hexx::Hex;
+
+use crate::save::ResortLoaded;
+use crate::economy::{Economy, Money, ledger::Category};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::Surface,
+	geometry::TerrainChanged,
+	object::{ObjectInstanceId, ObjectInstance, ObjectPlaced, ObjectRemoved},
+};
+
+/// Every change the player makes to the grid is a reversible edit, so it can be undone and redone.
+pub struct EditPlugin;
+impl Plugin for EditPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<EditHistory>();
+		app.add_systems(Update, undo_shortcuts);
+		app.add_observer(edit_grid);
+		app.add_observer(begin_stroke);
+		app.add_observer(end_stroke);
+		app.add_observer(undo);
+		app.add_observer(redo);
+		app.add_observer(forget_history);
+	}
+}
+
+/// Number of steps that can be undone.
+pub const HISTORY_LENGTH: usize = 100;
+
+/// A single change to the grid, which knows how to reverse itself.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Edit {
+	Height {pos: Hex, before: u16, after: u16},
+	Surface {pos: Hex, before: Surface, after: Surface},
+	Place {instance_id: ObjectInstanceId, object: ObjectInstance},
+	Remove {instance_id: ObjectInstanceId, object: ObjectInstance},
+}
+impl Edit {
+	/// The edit that reverses this one.
+	pub fn inverse(&self) -> Self {
+		match self.clone() {
+			Self::Height {pos, before, after} => Self::Height {pos: pos, before: after, after: before},
+			Self::Surface {pos, before, after} => Self::Surface {pos: pos, before: after, after: before},
+			Self::Place {instance_id, object} => Self::Remove {instance_id: instance_id, object: object},
+			Self::Remove {instance_id, object} => Self::Place {instance_id: instance_id, object: object},
+		}
+	}
+
+	/// Applies the edit to the grid.
+	/// Returns false and leaves the grid unchanged if the grid isn't in the state the edit expects, like when an avalanche destroyed the object since.
+	pub fn apply(&self, heights: &mut CellHeights, surfaces: &mut CellSurfaces, objects: &mut PlacedObjects) -> bool {
+		match self {
+			Self::Height {pos, before, after} => {
+				let Some(height) = heights.0.get_mut(pos).filter(|height| **height == *before) else {return false};
+				*height = *after;
+			},
+			Self::Surface {pos, before, after} => {
+				if surfaces.0.get(pos).copied().unwrap_or_default() != *before || !heights.0.contains_key(pos) {return false}
+				surfaces.0.insert(*pos, *after);
+			},
+			Self::Place {instance_id, object} => {
+				if objects.get(instance_id).is_some() {return false}
+				objects.insert(*instance_id, object.clone());
+			},
+			Self::Remove {instance_id, object} => {
+				if objects.get(instance_id) != Some(object) {return false}
+				objects.remove(instance_id);
+			},
+		}
+		true
+	}
+
+	/// The cell whose terrain is changed by the edit, if any.
+	pub const fn cell(&self) -> Option<Hex> {
+		match self {
+			Self::Height {pos, ..} | Self::Surface {pos, ..} => Some(*pos),
+			Self::Place {..} | Self::Remove {..} => None,
+		}
+	}
+}
+
+/// Applies the edits in order, or none of them if any of them doesn't fit the grid.
+pub fn apply_all<'a>(edits: impl IntoIterator<Item = &'a Edit>, heights: &mut CellHeights, surfaces: &mut CellSurfaces, objects: &mut PlacedObjects) -> bool {
+	let mut applied: Vec<&Edit> = Vec::new();
+	for edit in edits {
+		if !edit.apply(heights, surfaces, objects) {
+			for edit in applied.iter().rev() {
+				let reverted = edit.inverse().apply(heights, surfaces, objects);
+				debug_assert!(reverted, "Failed to revert {:?}.", edit);
+			}
+			return false
+		}
+		applied.push(edit);
+	}
+	true
+}
+
+/// Edits that are undone and redone together, along with what they cost.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct EditStep {
+	pub edits: Vec<Edit>,
+	/// Paid when the step is done or redone, and refunded when it's undone.
+	pub cost: Money,
+}
+impl EditStep {
+	pub fn new(edits: Vec<Edit>, cost: Money) -> Self {Self {edits: edits, cost: cost}}
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct EditHistory {
+	/// Newest last.
+	undo: VecDeque<EditStep>,
+	/// Most recently undone last.
+	redo: Vec<EditStep>,
+	/// Edits made since the current stroke began, which become a single step when it ends.
+	stroke: Option<EditStep>,
+}
+impl EditHistory {
+	pub fn can_undo(&self) -> bool {!self.undo.is_empty() || self.stroke.as_ref().is_some_and(|stroke| !stroke.edits.is_empty())}
+
+	pub fn can_redo(&self) -> bool {!self.redo.is_empty()}
+
+	/// Adds a step that was just done, merging it into the current stroke if there is one.
+	/// Anything that was undone can't be redone anymore.
+	pub fn record(&mut self, step: EditStep) {
+		self.redo.clear();
+		match &mut self.stroke {
+			Some(stroke) => {
+				stroke.edits.extend(step.edits);
+				stroke.cost += step.cost;
+			},
+			None => self.push(step),
+		}
+	}
+
+	/// Ends the current stroke, if any, and adds it as a single step.
+	pub fn end_stroke(&mut self) {
+		if let Some(stroke) = self.stroke.take().filter(|stroke| !stroke.edits.is_empty()) {
+			self.push(stroke);
+		}
+	}
+
+	fn push(&mut self, step: EditStep) {
+		if step.edits.is_empty() {return}
+		self.undo.push_back(step);
+		while self.undo.len() > HISTORY_LENGTH {
+			self.undo.pop_front();
+		}
+	}
+}
+
+/// Tells everything built from the grid about the edits.
+fn announce<'a>(commands: &mut Commands, edits: impl IntoIterator<Item = &'a Edit>) {
+	let mut cells: HashSet<Hex> = HashSet::new();
+	for edit in edits {
+		match edit {
+			Edit::Place {instance_id, ..} => commands.trigger(ObjectPlaced(*instance_id)),
+			Edit::Remove {instance_id, ..} => commands.trigger(ObjectRemoved(*instance_id)),
+			_ => {cells.extend(edit.cell());},
+		}
+	}
+	if !cells.is_empty() {
+		commands.trigger(TerrainChanged(cells));
+	}
+}
+
+#[derive(Event, Debug, Clone)]
+/// Pays for and applies a step of edits, and adds it to the history.
+/// Nothing is changed if the cost can't be afforded or the edits don't fit the grid.
+pub struct EditGrid(pub EditStep);
+fn edit_grid(
+	trigger: Trigger<EditGrid>,
+	mut commands: Commands,
+	mut economy: ResMut<Economy>,
+	mut history: ResMut<EditHistory>,
+	mut heights: ResMut<CellHeights>,
+	mut surfaces: ResMut<CellSurfaces>,
+	mut objects: ResMut<PlacedObjects>,
+) {
+	let step = trigger.0.clone();
+	if step.edits.is_empty() {return}
+	if !economy.can_afford(step.cost) {
+		warn!("Can't afford edits costing {} with a balance of {}.", step.cost, economy.balance);
+		return
+	}
+	if !apply_all(&step.edits, &mut heights, &mut surfaces, &mut objects) {
+		warn!("Edits {:?} don't fit the grid.", step.edits);
+		return
+	}
+	if step.cost != 0 {
+		economy.charge(Category::Construction, step.cost);
+	}
+	announce(&mut commands, &step.edits);
+	history.record(step);
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Starts merging edits into a single step, like while dragging a brush.
+pub struct BeginStroke;
+fn begin_stroke(
+	_trigger: Trigger<BeginStroke>,
+	mut history: ResMut<EditHistory>,
+) {
+	history.end_stroke();
+	history.stroke = Some(EditStep::default());
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Stops merging edits, adding everything since `BeginStroke` as a single step.
+pub struct EndStroke;
+fn end_stroke(
+	_trigger: Trigger<EndStroke>,
+	mut history: ResMut<EditHistory>,
+) {
+	history.end_stroke();
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Reverses the latest step and refunds its cost.
+pub struct Undo;
+fn undo(
+	_trigger: Trigger<Undo>,
+	mut commands: Commands,
+	mut economy: ResMut<Economy>,
+	mut history: ResMut<EditHistory>,
+	mut heights: ResMut<CellHeights>,
+	mut surfaces: ResMut<CellSurfaces>,
+	mut objects: ResMut<PlacedObjects>,
+) {
+	history.end_stroke();
+	let Some(step) = history.undo.pop_back() else {return};
+	let inverse: Vec<Edit> = step.edits.iter().rev().map(Edit::inverse).collect();
+	if !apply_all(&inverse, &mut heights, &mut surfaces, &mut objects) {
+		// Something else changed the grid since, so the step is forgotten.
+		warn!("Can't undo edits {:?} because the grid has changed since.", step.edits);
+		return
+	}
+	if step.cost != 0 {
+		economy.earn(Category::Construction, step.cost);
+	}
+	announce(&mut commands, &inverse);
+	history.redo.push(step);
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Applies the most recently undone step again, paying for it again.
+pub struct Redo;
+fn redo(
+	_trigger: Trigger<Redo>,
+	mut commands: Commands,
+	mut economy: ResMut<Economy>,
+	mut history: ResMut<EditHistory>,
+	mut heights: ResMut<CellHeights>,
+	mut surfaces: ResMut<CellSurfaces>,
+	mut objects: ResMut<PlacedObjects>,
+) {
+	history.end_stroke();
+	let Some(step) = history.redo.pop() else {return};
+	if !economy.can_afford(step.cost) {
+		warn!("Can't afford to redo edits costing {} with a balance of {}.", step.cost, economy.balance);
+		history.redo.push(step);
+		return
+	}
+	if !apply_all(&step.edits, &mut heights, &mut surfaces, &mut objects) {
+		warn!("Can't redo edits {:?} because the grid has changed since.", step.edits);
+		return
+	}
+	if step.cost != 0 {
+		economy.charge(Category::Construction, step.cost);
+	}
+	announce(&mut commands, &step.edits);
+	history.push(step);
+}
+
+/// Edits of the old resort don't apply to the loaded one.
+fn forget_history(
+	_trigger: Trigger<ResortLoaded>,
+	mut history: ResMut<EditHistory>,
+) {
+	*history = EditHistory::default();
+}
+
+/// Ctrl+Z undoes, and Ctrl+Y or Ctrl+Shift+Z redoes.
+fn undo_shortcuts(
+	mut commands: Commands,
+	input: Res<ButtonInput<KeyCode>>,
+) {
+	if !input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {return}
+	let shift = input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+	if input.just_pressed(KeyCode::KeyZ) {
+		if shift {commands.trigger(Redo)} else {commands.trigger(Undo)}
+	}
+	if input.just_pressed(KeyCode::KeyY) {commands.trigger(Redo)}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grid::object::structure::{StructureInstance, StructureTypeId};
+
+	fn grid() -> (CellHeights, CellSurfaces, PlacedObjects) {
+		let heights = CellHeights(Hex::ZERO.range(2).map(|pos| (pos, 5)).collect());
+		let surfaces = CellSurfaces(Hex::ZERO.range(2).map(|pos| (pos, Surface::None)).collect());
+		(heights, surfaces, PlacedObjects::default())
+	}
+
+	fn tree(position: Hex) -> ObjectInstance {
+		ObjectInstance::Structure(StructureInstance {type_id: StructureTypeId(1), position: position, rotation: None})
+	}
+
+	#[test]
+	fn inverse_undoes_the_edit() {
+		let (mut heights, mut surfaces, mut objects) = grid();
+		let original = (heights.clone(), surfaces.clone());
+		let edits = [
+			Edit::Height {pos: Hex::ZERO, before: 5, after: 7},
+			Edit::Surface {pos: Hex::ZERO, before: Surface::None, after: Surface::Piste},
+			Edit::Place {instance_id: ObjectInstanceId(3), object: tree(Hex::ZERO)},
+		];
+		for edit in edits.iter() {
+			assert!(edit.apply(&mut heights, &mut surfaces, &mut objects));
+		}
+		assert_eq!(heights.0[&Hex::ZERO], 7);
+		assert_eq!(surfaces.0[&Hex::ZERO], Surface::Piste);
+		assert_eq!(objects.get(&ObjectInstanceId(3)), Some(&tree(Hex::ZERO)));
+		for edit in edits.iter().rev() {
+			assert!(edit.inverse().apply(&mut heights, &mut surfaces, &mut objects));
+		}
+		assert_eq!((heights, surfaces), original);
+		assert_eq!(objects.iter().count(), 0);
+	}
+
+	#[test]
+	fn ids_are_never_reused() {
+		let (mut heights, mut surfaces, mut objects) = grid();
+		let sold = objects.push(tree(Hex::ZERO));
+		let sale = Edit::Remove {instance_id: sold, object: tree(Hex::ZERO)};
+		assert!(sale.apply(&mut heights, &mut surfaces, &mut objects));
+		let placed = objects.push(tree(Hex::new(1, 0)));
+		assert_ne!(placed, sold, "The sold object's id is still in the history.");
+		assert!(sale.inverse().apply(&mut heights, &mut surfaces, &mut objects), "Undoing the sale fails.");
+		assert!(objects.push(tree(Hex::new(0, 1))) > placed);
+		let mut loaded: PlacedObjects = ron::from_str(&ron::to_string(&objects).unwrap()).unwrap();
+		assert!(!objects.iter().any(|(instance_id, _)| *instance_id == loaded.push(tree(Hex::ZERO))), "The counter isn't saved.");
+	}
+
+	#[test]
+	fn stale_edit_is_refused() {
+		let (mut heights, mut surfaces, mut objects) = grid();
+		assert!(!Edit::Height {pos: Hex::ZERO, before: 4, after: 6}.apply(&mut heights, &mut surfaces, &mut objects));
+		assert!(!Edit::Remove {instance_id: ObjectInstanceId(0), object: tree(Hex::ZERO)}.apply(&mut heights, &mut surfaces, &mut objects));
+		assert_eq!(heights.0[&Hex::ZERO], 5);
+	}
+
+	#[test]
+	fn apply_all_changes_nothing_if_an_edit_fails() {
+		let (mut heights, mut surfaces, mut objects) = grid();
+		let original = heights.clone();
+		let edits = [
+			Edit::Height {pos: Hex::ZERO, before: 5, after: 6},
+			Edit::Height {pos: Hex::new(1, 0), before: 5, after: 6},
+			Edit::Height {pos: Hex::new(9, 9), before: 5, after: 6},
+		];
+		assert!(!apply_all(&edits, &mut heights, &mut surfaces, &mut objects));
+		assert_eq!(heights, original);
+	}
+
+	#[test]
+	fn stroke_is_one_step() {
+		let mut history = EditHistory {stroke: Some(EditStep::default()), ..default()};
+		for pos in [Hex::ZERO, Hex::new(1, 0), Hex::new(0, 1)] {
+			history.record(EditStep::new(vec![Edit::Height {pos: pos, before: 5, after: 6}], 10));
+		}
+		history.end_stroke();
+		assert_eq!(history.undo.len(), 1);
+		assert_eq!(history.undo[0].edits.len(), 3);
+		assert_eq!(history.undo[0].cost, 30);
+	}
+
+	#[test]
+	fn history_is_limited_and_new_edits_clear_redo() {
+		let mut history = EditHistory::default();
+		history.redo.push(EditStep::new(vec![Edit::Height {pos: Hex::ZERO, before: 1, after: 2}], 0));
+		for height in 0..HISTORY_LENGTH as u16 + 10 {
+			history.record(EditStep::new(vec![Edit::Height {pos: Hex::ZERO, before: height, after: height + 1}], 0));
+		}
+		assert!(!history.can_redo());
+		assert_eq!(history.undo.len(), HISTORY_LENGTH);
+		assert_eq!(history.undo.back().map(|step| step.edits[0].clone()), Some(Edit::Height {pos: Hex::ZERO, before: HISTORY_LENGTH as u16 + 9, after: HISTORY_LENGTH as u16 + 10}));
+	}
+}
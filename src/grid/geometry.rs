@@ -1,2 +1,154 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use bevy::{
+	prelude::*,
+	render::render_asset::RenderAssetUsages,
+	time::common_conditions::on_real_timer,
+};
+use hexx::Hex;
+
 pub mod gizmo;
-pub mod mesh;
\ No newline at end of file
+pub mod mesh;
+pub mod material;
+pub mod overlay;
+
+use crate::save::ResortLoaded;
+use crate::grid::{CellHeights, CellSurfaces, snow::{self, CellSnow}};
+use material::{TerrainMaterial, cell_color};
+use mesh::{chunk_mesh, chunk_colors, cell_chunk};
+use overlay::{Overlays, OverlayColors, AddOverlay};
+
+pub struct GeometryPlugin;
+impl Plugin for GeometryPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<TerrainMaterial>();
+		app.init_resource::<Overlays>();
+		app.init_resource::<OverlayColors>();
+		app.add_overlay("Slope", "Green is flat, red is steep.", overlay::slope_overlay);
+		app.add_overlay("Altitude", "Bands get lighter higher up.", overlay::altitude_overlay);
+		app.add_overlay("Snow depth", "Green is deep, red is thin.", overlay::snow_depth_overlay);
+		app.add_overlay("Snow quality", "Blue is powder, green groomed, yellow moguls, cyan icy, orange slush, brown bare.", overlay::snow_quality_overlay);
+		app.add_overlay("Piste difficulty", "Piste cells in the color of their grade.", overlay::piste_grade_overlay);
+		app.add_overlay("Land value", "Green is valuable, near lifts and pistes on flat ground.", overlay::land_value_overlay);
+		app.add_systems(PreStartup, material::load_assets);
+		app.add_systems(Startup, spawn_terrain.after(snow::setup));
+		// Snow changes all the time while the game runs, so the terrain is recolored for it at most twice a second.
+		app.add_systems(Update, (
+			overlay::run_overlay.run_if(resource_changed::<Overlays>.or(on_real_timer(Duration::from_millis(500)).and(overlay::inputs_changed))),
+			update_terrain_colors.run_if(resource_changed::<OverlayColors>.or(on_real_timer(Duration::from_millis(500)).and(resource_changed::<CellSnow>))),
+		).chain());
+		app.add_observer(respawn_terrain);
+		app.add_observer(rebuild_terrain);
+	}
+}
+
+#[derive(Component, Debug, PartialEq, Eq, Clone)]
+/// A piece of the terrain mesh.
+/// The cells are stored in the same order as in the mesh.
+pub struct TerrainChunk {
+	pub key: IVec2,
+	pub cells: Vec<Hex>,
+}
+
+fn spawn_terrain(
+	mut commands: Commands,
+	mut mesh_assets: ResMut<Assets<Mesh>>,
+	material: Res<TerrainMaterial>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	snow: Res<CellSnow>,
+	overlay: Res<OverlayColors>,
+) {
+	spawn_chunks(&mut commands, &mut mesh_assets, &material, &heights, &surfaces, &snow, &overlay);
+}
+
+/// Replaces the terrain of the old resort with the loaded one.
+fn respawn_terrain(
+	_trigger: Trigger<ResortLoaded>,
+	mut commands: Commands,
+	mut mesh_assets: ResMut<Assets<Mesh>>,
+	material: Res<TerrainMaterial>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	snow: Res<CellSnow>,
+	overlay: Res<OverlayColors>,
+	chunks: Query<Entity, With<TerrainChunk>>,
+) {
+	for entity in chunks.iter() {
+		commands.entity(entity).despawn();
+	}
+	spawn_chunks(&mut commands, &mut mesh_assets, &material, &heights, &surfaces, &snow, &overlay);
+}
+
+/// Vertex colors of the cells, where the overlay's colors replace the terrain's.
+fn terrain_colors(cells: &[Hex], heights: &CellHeights, surfaces: &CellSurfaces, snow: &CellSnow, overlay: &OverlayColors) -> Vec<[f32; 4]> {
+	chunk_colors(cells.iter().map(|pos| match overlay.0.get(pos) {
+		Some(color) => *color,
+		None => cell_color(&heights.0, pos, surfaces.0.get(pos).copied().unwrap_or_default(), snow.0.get(pos)),
+	}))
+}
+
+fn spawn_chunks(
+	commands: &mut Commands,
+	mesh_assets: &mut Assets<Mesh>,
+	material: &TerrainMaterial,
+	heights: &CellHeights,
+	surfaces: &CellSurfaces,
+	snow: &CellSnow,
+	overlay: &OverlayColors,
+) {
+	let mut chunks: HashMap<IVec2, Vec<Hex>> = HashMap::new();
+	for pos in heights.0.keys() {
+		chunks.entry(cell_chunk(pos)).or_default().push(*pos);
+	}
+	for (key, mut cells) in chunks {
+		cells.sort_by_key(|pos| (pos.x, pos.y));
+		let colors = terrain_colors(&cells, heights, surfaces, snow, overlay);
+		commands.spawn((
+			TerrainChunk {key: key, cells: cells.clone()},
+			Mesh3d(mesh_assets.add(chunk_mesh(&heights.0, &cells, colors, RenderAssetUsages::all()))),
+			MeshMaterial3d(material.0.clone()),
+			Pickable::default(),
+			Transform::default(),
+		));
+	}
+}
+
+#[derive(Event, Debug, Clone)]
+/// The height or surface of these cells changed, so the terrain around them has to be rebuilt.
+pub struct TerrainChanged(pub HashSet<Hex>);
+
+/// Rebuilds the meshes of the chunks around the changed cells.
+/// Neighbours are included because the corners and colors of a cell depend on the cells around it.
+fn rebuild_terrain(
+	trigger: Trigger<TerrainChanged>,
+	mut mesh_assets: ResMut<Assets<Mesh>>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	snow: Res<CellSnow>,
+	overlay: Res<OverlayColors>,
+	chunks: Query<(&TerrainChunk, &Mesh3d)>,
+) {
+	let keys: HashSet<IVec2> = trigger.0.iter().flat_map(|pos| pos.all_neighbors().into_iter().chain([*pos])).map(|pos| cell_chunk(&pos)).collect();
+	for (chunk, mesh) in chunks.iter().filter(|(chunk, _)| keys.contains(&chunk.key)) {
+		let mesh = match mesh_assets.get_mut(&mesh.0) {Some(mesh) => mesh, None => {error!("Terrain chunk {:?} is missing its mesh.", chunk.key); continue}};
+		let colors = terrain_colors(&chunk.cells, &heights, &surfaces, &snow, &overlay);
+		*mesh = chunk_mesh(&heights.0, &chunk.cells, colors, RenderAssetUsages::all());
+	}
+}
+
+/// Recolors the terrain when the snow or the overlay colors changed, without rebuilding the meshes.
+fn update_terrain_colors(
+	mut mesh_assets: ResMut<Assets<Mesh>>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	snow: Res<CellSnow>,
+	overlay: Res<OverlayColors>,
+	chunks: Query<(&TerrainChunk, &Mesh3d)>,
+) {
+	for (chunk, mesh) in chunks.iter() {
+		let mesh = match mesh_assets.get_mut(&mesh.0) {Some(mesh) => mesh, None => {error!("Terrain chunk {:?} is missing its mesh.", chunk.key); continue}};
+		let colors = terrain_colors(&chunk.cells, &heights, &surfaces, &snow, &overlay);
+		mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+	}
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use bevy::{prelude::*, gizmos::{config::GizmoConfigGroup, gizmos::GizmoBuffer}};
+use hexx::Hex;
+
+use crate::util::{
+	hex::{axial_to_xz, corner_height},
+	rotation::Rotation,
+};
+
+/// Gizmos are lifted slightly above the terrain so they don't flicker.
+pub const GIZMO_OFFSET: f32 = 0.05;
+
+/// Draws the outline of a cell, following the terrain.
+/// Does nothing if pos is not in heights.
+pub fn cell_outline<Config, Clear>(
+	gizmos: &mut GizmoBuffer<Config, Clear>,
+	heights: &HashMap<Hex, u16>,
+	pos: &Hex,
+	color: impl Into<Color>,
+) where
+	Config: GizmoConfigGroup,
+	Clear: 'static + Send + Sync,
+{
+	if !heights.contains_key(pos) {return}
+	let [center_x, center_z] = axial_to_xz(pos);
+	let corners = Rotation::ALL.map(|corner| {
+		let [x, z] = corner.corner_xz();
+		Vec3::new(center_x + x, corner_height(heights, pos, corner) + GIZMO_OFFSET, center_z + z)
+	});
+	gizmos.linestrip(corners.iter().chain([&corners[0]]).copied(), color);
+}
+
+/// Center of a cell on the terrain, lifted by `GIZMO_OFFSET`.
+/// Returns None if pos is not in heights.
+pub fn cell_top(heights: &HashMap<Hex, u16>, pos: &Hex) -> Option<Vec3> {
+	let height = *heights.get(pos)? as f32;
+	let [x, z] = axial_to_xz(pos);
+	Some(Vec3::new(x, height + GIZMO_OFFSET, z))
+}
+
+/// Draws a line through the centers of the cells, skipping cells without a height.
+pub fn path_line<Config, Clear>(
+	gizmos: &mut GizmoBuffer<Config, Clear>,
+	heights: &HashMap<Hex, u16>,
+	cells: impl IntoIterator<Item = Hex>,
+	color: impl Into<Color>,
+) where
+	Config: GizmoConfigGroup,
+	Clear: 'static + Send + Sync,
+{
+	gizmos.linestrip(cells.into_iter().filter_map(|pos| cell_top(heights, &pos)), color);
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use bevy::{prelude::*, color::{Mix, palettes::tailwind}};
+use hexx::Hex;
+
+use crate::util::hex::cell_slope;
+use crate::grid::{
+	surface::Surface,
+	snow::{SnowCell, SnowQuality},
+};
+
+pub const DIRT_MAX_SLOPE: u16 = 4;
+
+/// Single material for all terrain, which gets its colors from the mesh vertices.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct TerrainMaterial(pub Handle<StandardMaterial>);
+
+pub fn load_assets(
+	mut material_assets: ResMut<Assets<StandardMaterial>>,
+	mut material: ResMut<TerrainMaterial>,
+) {
+	material.0 = material_assets.add(StandardMaterial {
+		base_color: Color::WHITE,
+		perceptual_roughness: 0.9,
+		reflectance: 0.2,
+		..default()
+	});
+}
+
+/// Color of the ground under the snow.
+pub fn ground_color(heights: &HashMap<Hex, u16>, pos: &Hex, surface: Surface) -> LinearRgba {
+	match surface {
+		Surface::Water => Color::from(tailwind::SKY_800),
+		Surface::None | Surface::Piste => {
+			if cell_slope(heights, pos) > DIRT_MAX_SLOPE {Color::from(tailwind::SLATE_800)}
+			else {Color::from(tailwind::YELLOW_950)}
+		}
+	}.to_linear()
+}
+
+pub fn snow_color(quality: SnowQuality, surface: Surface) -> LinearRgba {
+	let color = match quality {
+		SnowQuality::Powder => Color::WHITE,
+		SnowQuality::Groomed => Color::from(tailwind::SLATE_50),
+		SnowQuality::Packed => Color::from(tailwind::SLATE_100),
+		SnowQuality::Moguls => Color::from(tailwind::SLATE_300),
+		SnowQuality::Icy => Color::from(tailwind::SKY_100),
+		SnowQuality::Slush => Color::from(tailwind::STONE_300),
+		SnowQuality::Bare => Color::from(tailwind::STONE_500),
+	}.to_linear();
+	// Pistes get a slight tint to stand out from the off-piste snow.
+	if surface == Surface::Piste {color.mix(&Color::from(tailwind::BLUE_200).to_linear(), 0.3)} else {color}
+}
+
+/// Final color of a cell, where the ground is blended with the snow depending on how deep it is.
+pub fn cell_color(heights: &HashMap<Hex, u16>, pos: &Hex, surface: Surface, snow: Option<&SnowCell>) -> LinearRgba {
+	let ground = ground_color(heights, pos, surface);
+	match snow {
+		Some(snow) if surface != Surface::Water => ground.mix(&snow_color(snow.quality, surface), snow.coverage()),
+		_ => ground,
+	}
+}
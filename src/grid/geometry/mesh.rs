@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use bevy::{
+	prelude::*,
+	render::{render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+use hexx::Hex;
+
+use crate::util::{
+	hex::{axial_to_xz, corner_height},
+	rotation::Rotation,
+};
+
+/// Width and length of a terrain chunk, in cells.
+pub const CHUNK_SIZE: i32 = 16;
+/// Every cell is made of six triangles.
+pub const VERTICES_PER_CELL: usize = 18;
+
+/// The chunk that a cell belongs to.
+pub fn cell_chunk(pos: &Hex) -> IVec2 {
+	IVec2::new(pos.x.div_euclid(CHUNK_SIZE), pos.y.div_euclid(CHUNK_SIZE))
+}
+
+/// Vertices of a cell in world coordinates, with sharp edges between cells.
+/// Panics if pos is not in heights.
+fn cell_sharp(heights: &HashMap<Hex, u16>, pos: &Hex) -> [Vec3; VERTICES_PER_CELL] {
+	let center_y = *heights.get(pos).unwrap() as f32;
+	let [center_x, center_z] = axial_to_xz(pos);
+	let center_vertex = Vec3::new(center_x, center_y, center_z);
+	let corner_vertices = Rotation::ALL.map(|corner| {
+		let [x, z] = corner.corner_xz();
+		Vec3::new(center_x + x, corner_height(heights, pos, corner), center_z + z)
+	});
+	let mut vertices = [Vec3::ZERO; VERTICES_PER_CELL];
+	for i in 0..6 {
+		vertices[i * 3] = center_vertex;
+		vertices[i * 3 + 1] = corner_vertices[(i + 1) % 6];
+		vertices[i * 3 + 2] = corner_vertices[i];
+	}
+	vertices
+}
+
+/// Mesh for all cells in a chunk, in the same order as `cells`.
+/// Every cell has `VERTICES_PER_CELL` vertices, so the colors can be changed per cell without rebuilding the mesh.
+pub fn chunk_mesh(heights: &HashMap<Hex, u16>, cells: &[Hex], colors: Vec<[f32; 4]>, asset_usage: RenderAssetUsages) -> Mesh {
+	let vertices: Vec<Vec3> = cells.iter().flat_map(|pos| cell_sharp(heights, pos)).collect();
+	Mesh::new(PrimitiveTopology::TriangleList, asset_usage)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertices)
+	.with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+	.with_computed_flat_normals()
+}
+
+/// Vertex colors for all cells in a chunk, with one color per cell.
+pub fn chunk_colors(cell_colors: impl Iterator<Item = LinearRgba>) -> Vec<[f32; 4]> {
+	cell_colors.flat_map(|color| [color.to_f32_array(); VERTICES_PER_CELL]).collect()
+}
@@ -0,0 +1,223 @@
+use std::collections::{HashMap, VecDeque};
+use bevy::{prelude::*, color::{Mix, palettes::tailwind}, ecs::system::SystemId};
+use hexx::Hex;
+
+use crate::util::hex::cell_slope;
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::{Surface, PisteGrade},
+	snow::{CellSnow, SnowQuality, MIN_SKIABLE_DEPTH},
+	object::ObjectInstance,
+};
+
+/// Height covered by each altitude band.
+pub const ALTITUDE_BAND: u16 = 10;
+/// Snow depth (cm) shown as the deepest on the snow depth overlay.
+pub const DEEP_SNOW: f32 = 4. * MIN_SKIABLE_DEPTH;
+/// How far (cells) lift stations and pistes add to the value of land.
+pub const LAND_VALUE_REACH: u32 = 12;
+
+/// Colors for some cells, which replace the terrain colors of those cells while the overlay is shown.
+pub type CellColors = HashMap<Hex, LinearRgba>;
+
+/// A way of coloring the terrain, worked out by a system that returns the color of each cell.
+#[derive(Debug, Clone)]
+pub struct OverlayProvider {
+	pub name: &'static str,
+	/// What the colors mean.
+	pub legend: &'static str,
+	system: SystemId<(), CellColors>,
+}
+
+/// Every overlay that can be shown, in the order they were added, and which one is shown.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct Overlays {
+	pub providers: Vec<OverlayProvider>,
+	/// Index into `providers`, or None to show the terrain as usual.
+	pub active: Option<usize>,
+}
+impl Overlays {
+	pub fn active_provider(&self) -> Option<&OverlayProvider> {self.providers.get(self.active?)}
+}
+
+/// Colors of the shown overlay, as of the last time the terrain was recolored.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct OverlayColors(pub CellColors);
+
+/// Lets any plugin add an overlay.
+pub trait AddOverlay {
+	fn add_overlay<M>(&mut self, name: &'static str, legend: &'static str, system: impl IntoSystem<(), CellColors, M> + 'static) -> &mut Self;
+}
+impl AddOverlay for App {
+	fn add_overlay<M>(&mut self, name: &'static str, legend: &'static str, system: impl IntoSystem<(), CellColors, M> + 'static) -> &mut Self {
+		let world = self.world_mut();
+		let system = world.register_system(system);
+		world.get_resource_or_init::<Overlays>().providers.push(OverlayProvider {name: name, legend: legend, system: system});
+		self
+	}
+}
+
+/// Whether anything that an overlay is worked out from changed since the last check.
+pub fn inputs_changed(
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	snow: Res<CellSnow>,
+	objects: Res<PlacedObjects>,
+) -> bool {
+	heights.is_changed() || surfaces.is_changed() || snow.is_changed() || objects.is_changed()
+}
+
+/// Runs the provider of the shown overlay, so the terrain can be recolored with its colors.
+/// The colors are only replaced if they're different, so the terrain isn't recolored for nothing.
+pub fn run_overlay(world: &mut World) {
+	let Some(provider) = world.resource::<Overlays>().active_provider().cloned() else {
+		if !world.resource::<OverlayColors>().0.is_empty() {world.resource_mut::<OverlayColors>().0.clear()}
+		return
+	};
+	match world.run_system(provider.system) {
+		Ok(colors) => if world.resource::<OverlayColors>().0 != colors {world.resource_mut::<OverlayColors>().0 = colors},
+		Err(error) => error!("Failed to work out the {} overlay: {}", provider.name, error),
+	}
+}
+
+/// Color on a scale from green (0) through yellow to red (1).
+pub fn scale_color(t: f32) -> LinearRgba {
+	let (low, middle, high) = (Color::from(tailwind::GREEN_500), Color::from(tailwind::YELLOW_400), Color::from(tailwind::RED_600));
+	let t = t.clamp(0., 1.);
+	if t < 0.5 {low.mix(&middle, t * 2.)} else {middle.mix(&high, t * 2. - 1.)}.to_linear()
+}
+
+pub const fn grade_color(grade: PisteGrade) -> Srgba {
+	match grade {
+		PisteGrade::Green => tailwind::GREEN_500,
+		PisteGrade::Blue => tailwind::BLUE_500,
+		PisteGrade::Red => tailwind::RED_600,
+		PisteGrade::Black => tailwind::NEUTRAL_900,
+	}
+}
+
+pub const fn quality_color(quality: SnowQuality) -> Srgba {
+	match quality {
+		SnowQuality::Powder => tailwind::SKY_200,
+		SnowQuality::Groomed => tailwind::GREEN_400,
+		SnowQuality::Packed => tailwind::LIME_300,
+		SnowQuality::Moguls => tailwind::YELLOW_400,
+		SnowQuality::Icy => tailwind::CYAN_600,
+		SnowQuality::Slush => tailwind::ORANGE_500,
+		SnowQuality::Bare => tailwind::STONE_600,
+	}
+}
+
+pub fn slope_overlay(heights: Res<CellHeights>) -> CellColors {
+	heights.0.keys().map(|pos| (*pos, scale_color(cell_slope(&heights.0, pos) as f32 / 6.))).collect()
+}
+
+/// Alternates light and dark within each band, so the bands can be told apart where they meet.
+pub fn altitude_overlay(heights: Res<CellHeights>) -> CellColors {
+	let (Some(lowest), Some(highest)) = (heights.0.values().min(), heights.0.values().max()) else {return CellColors::new()};
+	let bands = ((highest - lowest) / ALTITUDE_BAND).max(1);
+	heights.0.iter().map(|(pos, height)| {
+		let band = (height - lowest) / ALTITUDE_BAND;
+		let color = Color::from(tailwind::EMERALD_700).mix(&Color::WHITE, band as f32 / bands as f32);
+		let shade = if band.is_multiple_of(2) {1.} else {0.85};
+		(*pos, (color.to_linear() * shade).with_alpha(1.))
+	}).collect()
+}
+
+pub fn snow_depth_overlay(snow: Res<CellSnow>) -> CellColors {
+	snow.0.iter().map(|(pos, cell)| (*pos, scale_color(1. - cell.depth / DEEP_SNOW))).collect()
+}
+
+pub fn snow_quality_overlay(snow: Res<CellSnow>) -> CellColors {
+	snow.0.iter().map(|(pos, cell)| (*pos, Color::from(quality_color(cell.quality)).to_linear())).collect()
+}
+
+pub fn piste_grade_overlay(heights: Res<CellHeights>, surfaces: Res<CellSurfaces>) -> CellColors {
+	surfaces.0.iter()
+	.filter(|(_, surface)| **surface == Surface::Piste)
+	.map(|(pos, _)| (*pos, Color::from(grade_color(PisteGrade::from_slope(cell_slope(&heights.0, pos)))).to_linear()))
+	.collect()
+}
+
+/// Distance (cells) from each cell to the nearest source, up to `reach`, found by searching outwards from all sources at once.
+/// Cells further away are left out.
+pub fn distances(sources: impl IntoIterator<Item = Hex>, heights: &CellHeights, reach: u32) -> HashMap<Hex, u32> {
+	let mut distances: HashMap<Hex, u32> = HashMap::new();
+	let mut queue: VecDeque<Hex> = VecDeque::new();
+	for pos in sources {
+		if heights.0.contains_key(&pos) && distances.insert(pos, 0).is_none() {queue.push_back(pos)}
+	}
+	while let Some(pos) = queue.pop_front() {
+		let distance = distances[&pos] + 1;
+		if distance > reach {continue}
+		for next in pos.all_neighbors() {
+			if !heights.0.contains_key(&next) || distances.contains_key(&next) {continue}
+			distances.insert(next, distance);
+			queue.push_back(next);
+		}
+	}
+	distances
+}
+
+/// How much a cell is worth to build on: more close to lift stations and pistes, and less on steep ground.
+/// Takes the distances to the nearest station and piste, if they're in reach. Water can't be built on at all.
+pub fn land_value(pos: &Hex, heights: &CellHeights, surfaces: &CellSurfaces, station: Option<u32>, piste: Option<u32>) -> f32 {
+	if surfaces.0.get(pos) == Some(&Surface::Water) {return 0.}
+	let closeness = |distance: Option<u32>| distance.map_or(0., |distance| 1. - (distance as f32 / LAND_VALUE_REACH as f32).min(1.));
+	let flatness = 1. - (cell_slope(&heights.0, pos) as f32 / 6.).min(1.);
+	(0.5 * closeness(station) + 0.3 * closeness(piste) + 0.2 * flatness).clamp(0., 1.)
+}
+
+/// Only worked out again when the terrain or the objects change, since snow doesn't change the value of land.
+pub fn land_value_overlay(
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	mut colors: Local<CellColors>,
+) -> CellColors {
+	if heights.is_changed() || surfaces.is_changed() || objects.is_changed() {
+		let stations = objects.iter().flat_map(|(_, object)| match object {
+			ObjectInstance::Lift(lift) => vec![lift.bottom, lift.top],
+			ObjectInstance::Structure(_) => Vec::new(),
+		});
+		let stations = distances(stations, &heights, LAND_VALUE_REACH);
+		let pistes = distances(surfaces.0.iter().filter(|(_, surface)| **surface == Surface::Piste).map(|(pos, _)| *pos), &heights, LAND_VALUE_REACH);
+		*colors = heights.0.keys().map(|pos| (*pos, scale_color(1. - land_value(pos, &heights, &surfaces, stations.get(pos).copied(), pistes.get(pos).copied())))).collect();
+	}
+	colors.clone()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn land_is_worth_more_near_lifts() {
+		let heights = CellHeights(Hex::ZERO.range(20).map(|pos| (pos, 10)).collect());
+		let mut surfaces = CellSurfaces(heights.0.keys().map(|pos| (*pos, Surface::None)).collect());
+		surfaces.0.insert(Hex::new(1, 0), Surface::Water);
+		let stations = distances([Hex::ZERO], &heights, LAND_VALUE_REACH);
+		let value = |pos: Hex| land_value(&pos, &heights, &surfaces, stations.get(&pos).copied(), None);
+		let (near, far) = (value(Hex::new(0, 1)), value(Hex::new(15, 0)));
+		assert!(near > far, "{} should be more than {}", near, far);
+		assert_eq!(value(Hex::new(1, 0)), 0.);
+	}
+
+	#[test]
+	fn distances_are_to_the_nearest_source() {
+		let heights = CellHeights(Hex::ZERO.range(10).map(|pos| (pos, 10)).collect());
+		let sources = [Hex::new(-3, 0), Hex::new(4, -1), Hex::new(20, 0)];
+		let found = distances(sources, &heights, 5);
+		for pos in heights.0.keys() {
+			let nearest = sources.iter().map(|source| source.unsigned_distance_to(*pos)).min().unwrap();
+			assert_eq!(found.get(pos).copied(), Some(nearest).filter(|distance| *distance <= 5), "Wrong distance for {:?}.", pos);
+		}
+	}
+
+	#[test]
+	fn scale_goes_from_green_to_red() {
+		assert_eq!(scale_color(0.), Color::from(tailwind::GREEN_500).to_linear());
+		assert_eq!(scale_color(1.), Color::from(tailwind::RED_600).to_linear());
+		assert_eq!(scale_color(2.), scale_color(1.));
+	}
+}
@@ -1,23 +1,227 @@
-mod structure;
-mod lift;
+use bevy::prelude::*;
+use hexx::Hex;
+use serde::{Serialize, Deserialize};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
-pub struct ObjectInstanceId(u32);
+pub mod structure;
+pub mod lift;
+
+use crate::save::ResortLoaded;
+use crate::grid::{PlacedObjects, edit::{Edit, EditStep, EditHistory, EditGrid}};
+use crate::economy::{Economy, Money, ledger::Category};
+use structure::StructureTypes;
+use lift::LiftTypes;
+
+pub struct ObjectPlugin;
+impl Plugin for ObjectPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<LiftTypes>();
+		app.init_resource::<lift::LiftQueues>();
+		app.add_systems(PreStartup, structure::load_assets);
+		app.add_systems(Update, (lift::run_lifts, lift::draw_lifts));
+		app.add_observer(place_object);
+		app.add_observer(remove_object);
+		app.add_observer(sell_object);
+		app.add_observer(despawn_object);
+		app.add_observer(structure::spawn_structure);
+		app.add_observer(structure::follow_terrain);
+		app.add_observer(respawn_objects);
+		app.add_observer(lift::count_rides);
+		app.add_observer(lift::forget_queues);
+	}
+}
+
+/// Share of the construction cost that selling an object gives back.
+pub const SELL_REFUND: f32 = 0.5;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub struct ObjectInstanceId(pub u32);
+
+#[derive(Component, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+/// The associated instance ID of this entity.
+pub struct ObjectEntity(pub ObjectInstanceId);
+
+/// Section of the build menu.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum BuildCategory {
+	#[default]
+	Lifts,
+	/// Structures that guests visit.
+	Facilities,
+	Decoration,
+	Nature,
+	/// Structures that keep the resort running.
+	Infrastructure,
+}
+impl BuildCategory {
+	pub const ALL: [Self; 5] = [Self::Lifts, Self::Facilities, Self::Decoration, Self::Nature, Self::Infrastructure];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Lifts => "Lifts",
+			Self::Facilities => "Facilities",
+			Self::Decoration => "Decoration",
+			Self::Nature => "Nature",
+			Self::Infrastructure => "Infrastructure",
+		}
+	}
+}
 
 /// A placed object.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum ObjectInstance {
 	Structure(structure::StructureInstance),
 	Lift(lift::LiftInstance),
 }
 impl ObjectInstance {
-	pub fn object_type() {
-		
+	/// Name of the object's type in the catalogue.
+	pub fn name(&self, structure_types: &StructureTypes, lift_types: &LiftTypes) -> &'static str {
+		match self {
+			Self::Structure(instance) => structure_types.0.get(&instance.type_id).map_or("Unknown structure", |structure_type| structure_type.name),
+			Self::Lift(instance) => lift_types.0.get(&instance.type_id).map_or("Unknown lift", |lift_type| lift_type.name),
+		}
+	}
+
+	/// Cells that the object stands on, going by the catalogue.
+	pub fn cells(&self, structure_types: &StructureTypes) -> Vec<Hex> {
+		match self {
+			Self::Structure(instance) => match structure_types.0.get(&instance.type_id) {
+				Some(structure_type) => structure_type.footprint.cells(instance.position, instance.rotation).collect(),
+				None => vec![instance.position],
+			},
+			Self::Lift(instance) => instance.supports(),
+		}
+	}
+
+	/// The object turned clockwise by `turns` sixths around `pivot`, and moved so that `pivot` ends up on `target`.
+	/// Everything else about it stays the same.
+	pub fn moved(&self, pivot: Hex, target: Hex, turns: u32) -> Self {
+		let move_cell = |pos: Hex| (pos - pivot).rotate_cw(turns) + target;
+		match self {
+			Self::Structure(instance) => Self::Structure(structure::StructureInstance {
+				position: move_cell(instance.position),
+				rotation: instance.rotation.map(|rotation| rotation + turns as usize),
+				..*instance
+			}),
+			Self::Lift(instance) => Self::Lift(lift::LiftInstance {
+				bottom: move_cell(instance.bottom),
+				top: move_cell(instance.top),
+				towers: instance.towers.iter().map(|tower| move_cell(*tower)).collect(),
+				..instance.clone()
+			}),
+		}
+	}
+
+	/// Construction cost according to the catalogue, or None if the type is unknown.
+	pub fn cost(&self, structure_types: &StructureTypes, lift_types: &LiftTypes) -> Option<Money> {
+		match self {
+			Self::Structure(instance) => structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.cost),
+			Self::Lift(instance) => lift_types.0.get(&instance.type_id).map(|lift_type| lift_type.cost),
+		}
+	}
+
+	/// What selling the object gives back, or None if the type is unknown.
+	pub fn refund(&self, structure_types: &StructureTypes, lift_types: &LiftTypes) -> Option<Money> {
+		self.cost(structure_types, lift_types).map(|cost| (cost as f32 * SELL_REFUND) as Money)
+	}
+
+	/// Running cost per day according to the catalogue, or None if the type is unknown.
+	pub fn upkeep(&self, structure_types: &StructureTypes, lift_types: &LiftTypes) -> Option<Money> {
+		match self {
+			Self::Structure(instance) => structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.upkeep),
+			Self::Lift(instance) => lift_types.0.get(&instance.type_id).map(|lift_type| lift_type.upkeep),
+		}
+	}
+}
+
+#[derive(Event, Debug, Clone)]
+/// Pays for and adds a new object instance, as a step that can be undone.
+/// Nothing is placed if the construction cost can't be afforded.
+pub struct PlaceObject(pub ObjectInstance);
+fn place_object(
+	trigger: Trigger<PlaceObject>,
+	mut commands: Commands,
+	mut economy: ResMut<Economy>,
+	mut objects: ResMut<PlacedObjects>,
+	mut history: ResMut<EditHistory>,
+	structure_types: Res<StructureTypes>,
+	lift_types: Res<LiftTypes>,
+) {
+	let instance = trigger.0.clone();
+	let cost = match instance.cost(&structure_types, &lift_types) {Some(cost) => cost, None => {error!("Failed to place object {:?} of unknown type.", instance); return}};
+	if !economy.try_spend(Category::Construction, cost) {
+		warn!("Can't afford to place object {:?} costing {} with a balance of {}.", instance, cost, economy.balance);
+		return
 	}
-	pub const fn name(&self)/* -> &str */ {
-		/*match self {
-			Self::Structure(instance) => instance.name,
-			Self::Lift(instance) => instance.name
-		}*/
+	// The id is handed out here rather than by whoever triggered the event, so objects placed in the same frame don't share one.
+	let instance_id = objects.push(instance.clone());
+	history.record(EditStep::new(vec![Edit::Place {instance_id: instance_id, object: instance}], cost));
+	commands.trigger(ObjectPlaced(instance_id));
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// An object instance was added to the placed objects.
+pub struct ObjectPlaced(pub ObjectInstanceId);
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Removes an object instance without any refund, like when it's destroyed.
+/// Unlike edits, this can't be undone.
+pub struct RemoveObject(pub ObjectInstanceId);
+fn remove_object(
+	trigger: Trigger<RemoveObject>,
+	mut commands: Commands,
+	mut objects: ResMut<PlacedObjects>,
+) {
+	let instance_id = trigger.0;
+	if objects.remove(&instance_id).is_none() {
+		warn!("Attempted to remove object with unknown instance ID {:?}.", instance_id);
+		return
 	}
-}
\ No newline at end of file
+	commands.trigger(ObjectRemoved(instance_id));
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Removes an object instance and refunds part of its cost, as a step that can be undone.
+pub struct SellObject(pub ObjectInstanceId);
+fn sell_object(
+	trigger: Trigger<SellObject>,
+	mut commands: Commands,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	lift_types: Res<LiftTypes>,
+) {
+	let instance_id = trigger.0;
+	let Some(object) = objects.get(&instance_id) else {warn!("Attempted to sell object with unknown instance ID {:?}.", instance_id); return};
+	let refund = object.refund(&structure_types, &lift_types).unwrap_or(0);
+	// A negative cost pays the refund out, and undoing the sale takes it back.
+	commands.trigger(EditGrid(EditStep::new(vec![Edit::Remove {instance_id: instance_id, object: object.clone()}], -refund)));
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// An object instance was taken out of the placed objects.
+pub struct ObjectRemoved(pub ObjectInstanceId);
+fn despawn_object(
+	trigger: Trigger<ObjectRemoved>,
+	mut commands: Commands,
+	entities: Query<(Entity, &ObjectEntity)>,
+) {
+	for (entity, object_entity) in entities.iter() {
+		if object_entity.0 == trigger.0 {
+			commands.entity(entity).despawn();
+		}
+	}
+}
+
+/// Replaces the entities of the old resort's objects with the loaded ones.
+fn respawn_objects(
+	_trigger: Trigger<ResortLoaded>,
+	mut commands: Commands,
+	objects: Res<PlacedObjects>,
+	entities: Query<Entity, With<ObjectEntity>>,
+) {
+	for entity in entities.iter() {
+		commands.entity(entity).despawn();
+	}
+	for (instance_id, _) in objects.iter() {
+		commands.trigger(ObjectPlaced(*instance_id));
+	}
+}
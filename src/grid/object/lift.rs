@@ -1,15 +1,311 @@
 use std::collections::HashMap;
-use bevy::prelude::*;
+use bevy::{prelude::*, color::palettes::tailwind};
+use hexx::Hex;
+use serde::{Serialize, Deserialize};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
-pub struct LiftTypeId(u32);
-#[derive(Debug, PartialEq, Eq, Clone)]
+use crate::clock::{GameClock, DayStarted, OPENING_TIME, CLOSING_TIME};
+use crate::economy::Money;
+use crate::save::ResortLoaded;
+use crate::grid::{CellHeights, PlacedObjects, object::{ObjectInstance, ObjectInstanceId}, geometry::gizmo::cell_top};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub struct LiftTypeId(pub u32);
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct LiftInstance {
 	pub type_id: LiftTypeId,
+	/// Cell of the bottom station, where guests get on.
+	pub bottom: Hex,
+	/// Cell of the top station.
+	pub top: Hex,
+	/// Cells of the towers holding up the cable, from bottom to top.
+	pub towers: Vec<Hex>,
+	/// Stopped because the wind is too strong for this lift type.
+	pub wind_hold: bool,
+	/// Stopped because there is no operator at the bottom station.
+	pub unstaffed: bool,
+	/// Closed by the player.
+	pub closed: bool,
+	/// Minute of the day when the lift starts carrying guests.
+	pub opens_at: f32,
+	/// Minute of the day when the lift stops carrying guests.
+	pub closes_at: f32,
+}
+impl LiftInstance {
+	/// A lift that is open whenever the resort is.
+	pub fn new(type_id: LiftTypeId, bottom: Hex, top: Hex, towers: Vec<Hex>) -> Self {
+		Self {type_id: type_id, bottom: bottom, top: top, towers: towers, wind_hold: false, unstaffed: false, closed: false, opens_at: OPENING_TIME, closes_at: CLOSING_TIME}
+	}
+
+	pub const fn is_running(&self) -> bool {!self.wind_hold && !self.unstaffed && !self.closed}
+
+	/// Whether the lift carries guests at this minute of the day.
+	pub fn is_open_at(&self, minute: f32) -> bool {
+		self.is_running() && (self.opens_at..self.closes_at).contains(&minute)
+	}
+
+	pub fn status(&self, minute: f32) -> &'static str {
+		if self.closed {"Closed"}
+		else if self.wind_hold {"Stopped by wind"}
+		else if self.unstaffed {"No operator"}
+		else if !self.is_open_at(minute) {"Outside operating hours"}
+		else {"Running"}
+	}
+
+	/// Stations and towers that hold up the cable, from bottom to top.
+	pub fn supports(&self) -> Vec<Hex> {
+		[self.bottom].into_iter().chain(self.towers.iter().copied()).chain([self.top]).collect()
+	}
 }
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct LiftType {
 	pub name: &'static str,
+	/// Construction cost.
+	pub cost: Money,
+	/// Running cost per day.
+	pub upkeep: Money,
+	/// Wind speed (m/s) above which the lift has to stop, or None if it isn't exposed to wind.
+	pub max_wind: Option<f32>,
+	/// Longest distance (cells) between two stations or towers.
+	pub max_span: u32,
+	/// Steepest rise of the cable, in height per cell.
+	pub max_gradient: f32,
+	/// Lowest the cable may come above the terrain between two supports.
+	pub min_clearance: f32,
+	/// Height of the cable above the ground at stations and towers.
+	pub tower_height: f32,
+	/// Guests carried per hour.
+	pub capacity: u32,
 }
 #[derive(Resource)]
-pub struct LiftTypes(HashMap<LiftTypeId, LiftType>);
\ No newline at end of file
+pub struct LiftTypes(pub HashMap<LiftTypeId, LiftType>);
+impl Default for LiftTypes {
+	fn default() -> Self {
+		Self(HashMap::from([
+			(LiftTypeId(1), LiftType {name: "Drag Lift", cost: 40_000, upkeep: 150, max_wind: None, max_span: 4, max_gradient: 1., min_clearance: 1., tower_height: 3., capacity: 720}),
+			(LiftTypeId(2), LiftType {name: "Chairlift", cost: 120_000, upkeep: 400, max_wind: Some(15.), max_span: 8, max_gradient: 1.5, min_clearance: 2., tower_height: 5., capacity: 1800}),
+			(LiftTypeId(3), LiftType {name: "Gondola", cost: 300_000, upkeep: 900, max_wind: Some(18.), max_span: 15, max_gradient: 2.5, min_clearance: 3., tower_height: 8., capacity: 2400}),
+		]))
+	}
+}
+
+/// Guests lining up at the bottom station of a lift, and how many it carried.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct LiftQueue {
+	/// Guests in line. The lift carries them continuously, so this isn't a whole number.
+	pub waiting: f32,
+	pub rides_today: u32,
+	pub rides_yesterday: u32,
+}
+impl LiftQueue {
+	/// Minutes that a guest joining the line now waits before getting on.
+	pub fn wait_minutes(&self, lift_type: &LiftType) -> f32 {
+		self.waiting / lift_type.capacity.max(1) as f32 * 60.
+	}
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct LiftQueues(pub HashMap<ObjectInstanceId, LiftQueue>);
+
+/// Carries the guests in line up the lifts, at the capacity of each lift.
+/// Lines at lifts that stopped break up, since the guests waiting already went elsewhere.
+pub fn run_lifts(
+	clock: Res<GameClock>,
+	objects: Res<PlacedObjects>,
+	lift_types: Res<LiftTypes>,
+	mut queues: ResMut<LiftQueues>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	queues.0.retain(|instance_id, _| matches!(objects.get(instance_id), Some(ObjectInstance::Lift(_))));
+	for (instance_id, queue) in queues.0.iter_mut() {
+		let Some(ObjectInstance::Lift(lift)) = objects.get(instance_id) else {continue};
+		let carried = match lift_types.0.get(&lift.type_id) {
+			Some(lift_type) if lift.is_open_at(clock.minute) => lift_type.capacity as f32 / 60. * minutes,
+			_ => queue.waiting,
+		};
+		queue.waiting = (queue.waiting - carried).max(0.);
+	}
+}
+
+pub fn count_rides(
+	_trigger: Trigger<DayStarted>,
+	mut queues: ResMut<LiftQueues>,
+) {
+	for queue in queues.0.values_mut() {
+		queue.rides_yesterday = queue.rides_today;
+		queue.rides_today = 0;
+	}
+}
+
+/// Lines and ride counts belong to the old resort.
+pub fn forget_queues(
+	_trigger: Trigger<ResortLoaded>,
+	mut queues: ResMut<LiftQueues>,
+) {
+	queues.0.clear();
+}
+
+/// Draws the towers and cable of every placed lift.
+pub fn draw_lifts(
+	mut gizmos: Gizmos,
+	objects: Res<PlacedObjects>,
+	lift_types: Res<LiftTypes>,
+	heights: Res<CellHeights>,
+) {
+	for (_, object) in objects.iter() {
+		let ObjectInstance::Lift(lift) = object else {continue};
+		let Some(lift_type) = lift_types.0.get(&lift.type_id) else {continue};
+		let color = if lift.is_running() {tailwind::NEUTRAL_300} else {tailwind::NEUTRAL_600};
+		draw_cable(&mut gizmos, lift_type, &heights.0, &lift.supports(), |_| color);
+	}
+}
+
+/// Draws a tower at each support and the cable between them, coloring each span by its index.
+pub fn draw_cable(gizmos: &mut Gizmos, lift_type: &LiftType, heights: &HashMap<Hex, u16>, supports: &[Hex], span_color: impl Fn(usize) -> Srgba) {
+	let tops: Vec<Option<Vec3>> = supports.iter().map(|pos| cell_top(heights, pos).map(|ground| {
+		gizmos.line(ground, ground + Vec3::Y * lift_type.tower_height, tailwind::NEUTRAL_500);
+		ground + Vec3::Y * lift_type.tower_height
+	})).collect();
+	for (i, pair) in tops.windows(2).enumerate() {
+		if let [Some(from), Some(to)] = pair {
+			gizmos.line(*from, *to, span_color(i));
+		}
+	}
+}
+
+/// A rule of the lift type broken by a span of the cable.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SpanProblem {
+	/// The supports are further apart than the lift type allows.
+	TooLong,
+	/// The cable rises more steeply than the lift type allows.
+	TooSteep,
+	/// The cable comes too close to the terrain over this cell.
+	LowClearance(Hex),
+}
+impl SpanProblem {
+	pub const fn describe(&self) -> &'static str {
+		match self {
+			Self::TooLong => "too long",
+			Self::TooSteep => "too steep",
+			Self::LowClearance(_) => "too close to the ground",
+		}
+	}
+}
+
+/// Stretch of cable between two supports.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Span {
+	pub from: Hex,
+	pub to: Hex,
+	pub problems: Vec<SpanProblem>,
+}
+
+impl LiftType {
+	/// Height of the cable where it's held up at a cell, or None if the cell has no height.
+	pub fn cable_height(&self, heights: &HashMap<Hex, u16>, pos: &Hex) -> Option<f32> {
+		heights.get(pos).map(|height| *height as f32 + self.tower_height)
+	}
+
+	/// Rules broken by a straight span of cable between two supports.
+	/// Cells without a height count as being far below the cable.
+	pub fn span_problems(&self, heights: &HashMap<Hex, u16>, from: Hex, to: Hex) -> Vec<SpanProblem> {
+		let mut problems = Vec::new();
+		let length = from.unsigned_distance_to(to);
+		if length > self.max_span {problems.push(SpanProblem::TooLong)}
+		let (Some(from_height), Some(to_height)) = (self.cable_height(heights, &from), self.cable_height(heights, &to)) else {return problems};
+		if length > 0 && (to_height - from_height).abs() / length as f32 > self.max_gradient {problems.push(SpanProblem::TooSteep)}
+		for (i, pos) in from.line_to(to).enumerate() {
+			if i == 0 || pos == to {continue}
+			let Some(ground) = heights.get(&pos) else {continue};
+			let cable = from_height + (to_height - from_height) * i as f32 / length as f32;
+			if cable - (*ground as f32) < self.min_clearance {problems.push(SpanProblem::LowClearance(pos))}
+		}
+		problems
+	}
+
+	/// Spans of cable between each pair of consecutive supports.
+	pub fn spans(&self, heights: &HashMap<Hex, u16>, supports: &[Hex]) -> Vec<Span> {
+		supports.windows(2).map(|pair| Span {from: pair[0], to: pair[1], problems: self.span_problems(heights, pair[0], pair[1])}).collect()
+	}
+
+	/// Towers along the straight line from the bottom to the top station.
+	/// Each span reaches as far as it can without breaking a rule, and where no span is fine the longest one allowed is used.
+	pub fn auto_towers(&self, heights: &HashMap<Hex, u16>, bottom: Hex, top: Hex) -> Vec<Hex> {
+		let line: Vec<Hex> = bottom.line_to(top).collect();
+		let last = line.len() - 1;
+		let mut towers = Vec::new();
+		let mut i = 0;
+		while !self.span_problems(heights, line[i], top).is_empty() {
+			let furthest = (i + self.max_span.max(1) as usize).min(last - 1);
+			if furthest <= i {break}
+			let next = (i + 1..=furthest).rev().find(|j| self.span_problems(heights, line[i], line[*j]).is_empty()).unwrap_or(furthest);
+			towers.push(line[next]);
+			i = next;
+		}
+		towers
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn drag_lift() -> LiftType {
+		LiftType {name: "Test Lift", cost: 0, upkeep: 0, max_wind: None, max_span: 4, max_gradient: 1., min_clearance: 1., tower_height: 3., capacity: 720}
+	}
+
+	/// Flat ground along the x axis, with the heights specified for some cells.
+	fn ground(length: i32, bumps: &[(i32, u16)]) -> HashMap<Hex, u16> {
+		let mut heights: HashMap<Hex, u16> = (0..=length).map(|x| (Hex::new(x, 0), 10)).collect();
+		for (x, height) in bumps {
+			heights.insert(Hex::new(*x, 0), *height);
+		}
+		heights
+	}
+
+	#[test]
+	fn spans_break_the_rules() {
+		let lift = drag_lift();
+		let heights = ground(10, &[(2, 13), (10, 20)]);
+		assert_eq!(lift.span_problems(&heights, Hex::new(0, 0), Hex::new(1, 0)), vec![]);
+		assert_eq!(lift.span_problems(&heights, Hex::new(3, 0), Hex::new(8, 0)), vec![SpanProblem::TooLong]);
+		assert_eq!(lift.span_problems(&heights, Hex::new(0, 0), Hex::new(4, 0)), vec![SpanProblem::LowClearance(Hex::new(2, 0))]);
+		assert_eq!(lift.span_problems(&heights, Hex::new(8, 0), Hex::new(10, 0)), vec![SpanProblem::TooSteep]);
+	}
+
+	#[test]
+	fn auto_towers_make_every_span_valid() {
+		let lift = drag_lift();
+		let heights = ground(12, &[(5, 12)]);
+		let (bottom, top) = (Hex::new(0, 0), Hex::new(12, 0));
+		let towers = lift.auto_towers(&heights, bottom, top);
+		assert!(!towers.is_empty());
+		let supports: Vec<Hex> = [bottom].into_iter().chain(towers).chain([top]).collect();
+		assert!(lift.spans(&heights, &supports).iter().all(|span| span.problems.is_empty()));
+	}
+
+	#[test]
+	fn short_lifts_need_no_towers() {
+		let lift = drag_lift();
+		let heights = ground(3, &[]);
+		assert_eq!(lift.auto_towers(&heights, Hex::new(0, 0), Hex::new(3, 0)), vec![]);
+	}
+
+	#[test]
+	fn lifts_only_run_when_open() {
+		let mut lift = LiftInstance::new(LiftTypeId(1), Hex::new(0, 0), Hex::new(3, 0), Vec::new());
+		assert!(lift.is_open_at(OPENING_TIME) && !lift.is_open_at(CLOSING_TIME));
+		lift.closes_at = 12. * 60.;
+		assert!(!lift.is_open_at(13. * 60.));
+		assert_eq!(lift.status(13. * 60.), "Outside operating hours");
+		lift.closed = true;
+		assert!(!lift.is_running() && !lift.is_open_at(10. * 60.));
+	}
+
+	#[test]
+	fn queue_wait_follows_capacity() {
+		let queue = LiftQueue {waiting: 36., ..default()};
+		assert_eq!(queue.wait_minutes(&drag_lift()), 3.);
+	}
+}
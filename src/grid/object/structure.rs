@@ -1,28 +1,385 @@
 use std::collections::HashMap;
-use bevy::prelude::*;
+use bevy::{prelude::*, color::palettes::tailwind};
 use hexx::Hex;
+use serde::{Serialize, Deserialize};
 
-use crate::util::rotation::Rotation;
+use crate::util::{hex::axial_to_xz, rotation::Rotation};
+use crate::economy::Money;
+use crate::snowmaking::SnowCannon;
+use crate::facility::Facility;
+use crate::guest::Need;
+use crate::grid::{CellHeights, CellSurfaces, PlacedObjects, surface::Surface, geometry::TerrainChanged, object::{ObjectInstance, ObjectInstanceId, ObjectEntity, ObjectPlaced, BuildCategory}};
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
-pub struct StructureTypeId(u32);
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+mod temp_scenes;
+use temp_scenes::*;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub struct StructureTypeId(pub u32);
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub struct StructureInstance {
 	pub type_id: StructureTypeId,
 	pub position: Hex,
 	pub rotation: Option<Rotation>,
 }
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct StructureType {
 	pub name: &'static str,
 	pub scene: Handle<Scene>,
 	pub footprint: Footprint,
 	pub has_rotation: bool,
-	
+	/// Construction cost.
+	pub cost: Money,
+	/// Running cost per day.
+	pub upkeep: Money,
+	/// Where the structure is listed in the build menu.
+	pub category: BuildCategory,
+	pub role: StructureRole,
 }
 #[derive(Resource)]
-pub struct StructureTypes(HashMap<StructureTypeId, StructureType>);
+pub struct StructureTypes(pub HashMap<StructureTypeId, StructureType>);
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 /// Each cell has a height, and optionally a bottom (under which there is no collision).
-pub struct Footprint(HashMap<Hex, (u16, Option<u16>)>);
\ No newline at end of file
+pub struct Footprint(HashMap<Hex, (u16, Option<u16>)>);
+impl Footprint {
+	/// Footprint covering only the center cell.
+	pub fn single(height: u16) -> Self {
+		Self(HashMap::from([(Hex::ZERO, (height, None))]))
+	}
+
+	/// Cells covered by a structure with this footprint at the position, turned by the rotation.
+	pub fn cells(&self, position: Hex, rotation: Option<Rotation>) -> impl Iterator<Item = Hex> + '_ {
+		let turns = rotation.map_or(0, |rotation| rotation as u32);
+		// Matches `rotation_quat`, which turns clockwise seen from above.
+		self.0.keys().map(move |offset| position + offset.rotate_cw(turns))
+	}
+}
+
+/// Why a structure can't go on some cells.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PlacementProblem {
+	OffMap,
+	Water,
+	/// Another object is already there.
+	Occupied(ObjectInstanceId),
+}
+impl PlacementProblem {
+	pub const fn describe(&self) -> &'static str {
+		match self {
+			Self::OffMap => "off the map",
+			Self::Water => "on water",
+			Self::Occupied(_) => "in the way of another object",
+		}
+	}
+}
+
+/// The first reason that a structure can't cover the cells, or None if it can.
+/// `occupied` maps cells to the objects on them, leaving out any objects being moved.
+pub fn placement_problem(cells: impl IntoIterator<Item = Hex>, heights: &CellHeights, surfaces: &CellSurfaces, occupied: &HashMap<Hex, ObjectInstanceId>) -> Option<PlacementProblem> {
+	cells.into_iter().find_map(|pos| {
+		if !heights.0.contains_key(&pos) {Some(PlacementProblem::OffMap)}
+		else if surfaces.0.get(&pos) == Some(&Surface::Water) {Some(PlacementProblem::Water)}
+		else {occupied.get(&pos).map(|instance_id| PlacementProblem::Occupied(*instance_id))}
+	})
+}
+
+/// What a structure does, apart from being looked at.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum StructureRole {
+	#[default]
+	Decoration,
+	SnowCannon(SnowCannon),
+	/// Houses this many snowcats.
+	SnowcatGarage(u32),
+	/// Stops avalanches and holds the snow in place around it.
+	AvalancheBarrier,
+	/// Stops the wind from loading snow onto nearby slopes.
+	SnowFence,
+	/// Houses this many staff members.
+	StaffQuarters(u32),
+	/// Somewhere guests go to satisfy a need.
+	Facility(Facility),
+	/// Base for the ski patrol.
+	PatrolHut,
+	/// Where cashiers sell lift tickets.
+	TicketOffice,
+	/// Where the ski patrol brings injured guests.
+	FirstAidHut,
+}
+
+/// Loads all structure scenes into the catalogue.
+pub fn load_assets(
+	mut commands: Commands,
+	mut scene_assets: ResMut<Assets<Scene>>,
+	mut mesh_assets: ResMut<Assets<Mesh>>,
+	mut material_assets: ResMut<Assets<StandardMaterial>>,
+) {
+	commands.insert_resource(StructureTypes(HashMap::from([
+		(StructureTypeId(1), StructureType {
+			name: "Tree",
+			scene: scene_assets.add(tree_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(2),
+			has_rotation: false,
+			cost: 50,
+			upkeep: 0,
+			category: BuildCategory::Nature,
+			role: StructureRole::Decoration,
+		}),
+		(StructureTypeId(111), StructureType {
+			name: "Red Box",
+			scene: scene_assets.add(red_box_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(1),
+			has_rotation: true,
+			cost: 2_000,
+			upkeep: 20,
+			category: BuildCategory::Decoration,
+			role: StructureRole::Decoration,
+		}),
+		(StructureTypeId(222), StructureType {
+			name: "Blue Sphere",
+			scene: scene_assets.add(blue_sphere_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(1),
+			has_rotation: false,
+			cost: 1_500,
+			upkeep: 10,
+			category: BuildCategory::Decoration,
+			role: StructureRole::Decoration,
+		}),
+		(StructureTypeId(301), StructureType {
+			name: "Snow Cannon",
+			scene: scene_assets.add(snow_cannon_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 25_000,
+			upkeep: 50,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::SnowCannon(SnowCannon {
+				radius: 2,
+				water_per_minute: 0.5,
+				energy_per_minute: 2.,
+				snow_per_minute: 0.05,
+				max_temperature: -2.,
+			}),
+		}),
+		(StructureTypeId(302), StructureType {
+			name: "Snowcat Garage",
+			scene: scene_assets.add(garage_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 60_000,
+			upkeep: 100,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::SnowcatGarage(2),
+		}),
+		(StructureTypeId(303), StructureType {
+			name: "Avalanche Barrier",
+			scene: scene_assets.add(barrier_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 8_000,
+			upkeep: 5,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::AvalancheBarrier,
+		}),
+		(StructureTypeId(304), StructureType {
+			name: "Snow Fence",
+			scene: scene_assets.add(fence_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(1),
+			has_rotation: true,
+			cost: 1_500,
+			upkeep: 2,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::SnowFence,
+		}),
+		(StructureTypeId(305), StructureType {
+			name: "Staff Quarters",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::AMBER_700)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 30_000,
+			upkeep: 40,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::StaffQuarters(10),
+		}),
+		(StructureTypeId(306), StructureType {
+			name: "Ski School",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::SKY_600)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 20_000,
+			upkeep: 30,
+			category: BuildCategory::Facilities,
+			role: StructureRole::Facility(Facility {need: Need::Lessons, capacity: 24, service_minutes: 60., price: 60, catchment: 20}),
+		}),
+		(StructureTypeId(307), StructureType {
+			name: "Patrol Hut",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::RED_600)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 15_000,
+			upkeep: 20,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::PatrolHut,
+		}),
+		(StructureTypeId(308), StructureType {
+			name: "Ticket Office",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::EMERALD_600)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 10_000,
+			upkeep: 15,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::TicketOffice,
+		}),
+		(StructureTypeId(309), StructureType {
+			name: "First-Aid Hut",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::NEUTRAL_100)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 20_000,
+			upkeep: 30,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::FirstAidHut,
+		}),
+		(StructureTypeId(401), StructureType {
+			name: "Restaurant",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::ORANGE_600)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 80_000,
+			upkeep: 120,
+			category: BuildCategory::Facilities,
+			role: StructureRole::Facility(Facility {need: Need::Hunger, capacity: 30, service_minutes: 40., price: 25, catchment: 15}),
+		}),
+		(StructureTypeId(402), StructureType {
+			name: "Café",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::ROSE_500)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 35_000,
+			upkeep: 50,
+			category: BuildCategory::Facilities,
+			role: StructureRole::Facility(Facility {need: Need::Cold, capacity: 15, service_minutes: 20., price: 8, catchment: 12}),
+		}),
+		(StructureTypeId(403), StructureType {
+			name: "Toilets",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::SLATE_500)),
+			footprint: Footprint::single(1),
+			has_rotation: true,
+			cost: 8_000,
+			upkeep: 15,
+			category: BuildCategory::Facilities,
+			role: StructureRole::Facility(Facility {need: Need::Bladder, capacity: 4, service_minutes: 4., price: 0, catchment: 12}),
+		}),
+		(StructureTypeId(404), StructureType {
+			name: "Rental Shop",
+			scene: scene_assets.add(hut_scene(&mut mesh_assets, &mut material_assets, tailwind::VIOLET_600)),
+			footprint: Footprint::single(2),
+			has_rotation: true,
+			cost: 40_000,
+			upkeep: 60,
+			category: BuildCategory::Facilities,
+			role: StructureRole::Facility(Facility {need: Need::Rental, capacity: 6, service_minutes: 10., price: 40, catchment: 25}),
+		}),
+		(StructureTypeId(405), StructureType {
+			name: "Parking Lot",
+			scene: scene_assets.add(parking_scene(&mut mesh_assets, &mut material_assets)),
+			footprint: Footprint::single(0),
+			has_rotation: false,
+			cost: 12_000,
+			upkeep: 10,
+			category: BuildCategory::Infrastructure,
+			// Everyone parks on arrival, so the catchment isn't used.
+			role: StructureRole::Facility(Facility {need: Need::Parking, capacity: 150, service_minutes: 0., price: 10, catchment: 0}),
+		}),
+	])));
+}
+
+#[derive(Component, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+/// The type ID for this structure.
+/// All entities with a StructureEntity component will also have an ObjectEntity component for the instance ID.
+pub struct StructureEntity(pub StructureTypeId);
+
+/// Spawns the entity for a newly placed structure.
+pub fn spawn_structure(
+	trigger: Trigger<ObjectPlaced>,
+	mut commands: Commands,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+) {
+	let instance_id = trigger.0;
+	let instance = match objects.get(&instance_id) {
+		Some(ObjectInstance::Structure(instance)) => instance,
+		Some(ObjectInstance::Lift(_)) => return,
+		None => {error!("Failed to spawn placed structure with unknown instance ID {:?}.", instance_id); return}
+	};
+	let structure_type = match structure_types.0.get(&instance.type_id) {Some(structure_type) => structure_type, None => {error!("Failed to spawn structure with unknown structure type ID {:?} on cell {:?}", instance.type_id, instance.position); return}};
+	let [x, z] = axial_to_xz(&instance.position);
+	let y = match heights.0.get(&instance.position) {Some(height) => *height as f32, None => {error!("Failed to spawn structure {:?} on cell {:?} because it's missing a cell height.", structure_type.name, instance.position); return}};
+	commands.spawn((
+		ObjectEntity(instance_id),
+		StructureEntity(instance.type_id),
+		SceneRoot(structure_type.scene.clone()),
+		Transform {
+			translation: Vec3::new(x, y, z),
+			rotation: rotation_quat(instance.rotation),
+			..default()
+		},
+	));
+}
+
+/// Keeps structures standing on the terrain when the height of their cell changes.
+pub fn follow_terrain(
+	trigger: Trigger<TerrainChanged>,
+	objects: Res<PlacedObjects>,
+	heights: Res<CellHeights>,
+	mut entities: Query<(&ObjectEntity, &mut Transform), With<StructureEntity>>,
+) {
+	for (object_entity, mut transform) in entities.iter_mut() {
+		let Some(ObjectInstance::Structure(instance)) = objects.get(&object_entity.0) else {continue};
+		if !trigger.0.contains(&instance.position) {continue}
+		let Some(height) = heights.0.get(&instance.position) else {continue};
+		if transform.translation.y != *height as f32 {
+			transform.translation.y = *height as f32;
+		}
+	}
+}
+
+/// Rotation around the y axis, in steps of 60 degrees.
+pub fn rotation_quat(rotation: Option<Rotation>) -> Quat {
+	match rotation {
+		Some(rotation) => Quat::from_rotation_y(-(rotation as usize as f32) * std::f32::consts::FRAC_PI_3),
+		None => Quat::IDENTITY,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::hex::axial_to_xz;
+
+	#[test]
+	fn footprint_turns_with_the_scene() {
+		let offset = Hex::new(2, -1);
+		let footprint = Footprint(HashMap::from([(offset, (1, None))]));
+		for rotation in Rotation::ALL {
+			let cell = footprint.cells(Hex::ZERO, Some(rotation)).next().unwrap();
+			let [x, z] = axial_to_xz(&offset);
+			let turned = rotation_quat(Some(rotation)) * Vec3::new(x, 0., z);
+			let [cell_x, cell_z] = axial_to_xz(&cell);
+			assert!((turned.x - cell_x).abs() < 1e-4 && (turned.z - cell_z).abs() < 1e-4, "{:?} turned {:?} to {:?}", rotation, offset, cell);
+		}
+	}
+
+	#[test]
+	fn placement_needs_free_dry_land() {
+		let heights = CellHeights(Hex::ZERO.range(1).map(|pos| (pos, 5)).collect());
+		let surfaces = CellSurfaces(HashMap::from([(Hex::new(1, 0), Surface::Water)]));
+		let occupied = HashMap::from([(Hex::new(0, 1), ObjectInstanceId(7))]);
+		assert_eq!(placement_problem([Hex::ZERO], &heights, &surfaces, &occupied), None);
+		assert_eq!(placement_problem([Hex::new(5, 0)], &heights, &surfaces, &occupied), Some(PlacementProblem::OffMap));
+		assert_eq!(placement_problem([Hex::new(1, 0)], &heights, &surfaces, &occupied), Some(PlacementProblem::Water));
+		assert_eq!(placement_problem([Hex::ZERO, Hex::new(0, 1)], &heights, &surfaces, &occupied), Some(PlacementProblem::Occupied(ObjectInstanceId(7))));
+	}
+}
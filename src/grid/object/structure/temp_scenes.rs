@@ -0,0 +1,213 @@
+use bevy::{prelude::*, color::palettes::tailwind};
+
+pub fn tree_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let leaves = material_assets.add(Color::from(tailwind::GREEN_800));
+	let wood = material_assets.add(Color::from(tailwind::AMBER_950));
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cylinder::new(0.1, 0.5))),
+		MeshMaterial3d(wood.clone()),
+		Transform::from_xyz(0., 0.25, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	for i in 0..3 {
+		let i_f = i as f32;
+		world.spawn((
+			Mesh3d(mesh_assets.add(Cone::new(0.5 - i_f / 10., 0.5 - i_f / 20.))),
+			MeshMaterial3d(leaves.clone()),
+			Transform::from_xyz(0., 0.75 + i_f / 4., 0.),
+			Pickable {
+				is_hoverable: true,
+				should_block_lower: false,
+			},
+		));
+	}
+	Scene::new(world)
+}
+
+pub fn red_box_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cuboid::new(1., 1., 1.))),
+		MeshMaterial3d(material_assets.add(Color::srgb(1., 0., 0.))),
+		Transform::from_xyz(0., 0.5, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	Scene::new(world)
+}
+
+pub fn blue_sphere_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Sphere::new(0.5))),
+		MeshMaterial3d(material_assets.add(Color::srgb(0., 0., 1.))),
+		Transform::from_xyz(0., 0.25, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	Scene::new(world)
+}
+
+pub fn snow_cannon_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let metal = material_assets.add(Color::from(tailwind::ZINC_500));
+	let barrel = material_assets.add(Color::from(tailwind::YELLOW_400));
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cylinder::new(0.05, 1.5))),
+		MeshMaterial3d(metal),
+		Transform::from_xyz(0., 0.75, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cylinder::new(0.25, 0.6))),
+		MeshMaterial3d(barrel),
+		Transform::from_xyz(0., 1.6, 0.2).with_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_3)),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	Scene::new(world)
+}
+
+pub fn garage_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cuboid::new(1.6, 1.2, 1.2))),
+		MeshMaterial3d(material_assets.add(Color::from(tailwind::STONE_400))),
+		Transform::from_xyz(0., 0.6, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cuboid::new(1., 0.8, 0.05))),
+		MeshMaterial3d(material_assets.add(Color::from(tailwind::RED_700))),
+		Transform::from_xyz(0., 0.4, -0.6),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	Scene::new(world)
+}
+
+pub fn barrier_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let steel = material_assets.add(Color::from(tailwind::ZINC_700));
+	let mut world = World::new();
+	for i in 0..3 {
+		world.spawn((
+			Mesh3d(mesh_assets.add(Cuboid::new(0.08, 1.2, 0.08))),
+			MeshMaterial3d(steel.clone()),
+			Transform::from_xyz(-0.6 + i as f32 * 0.6, 0.6, 0.),
+			Pickable {
+				is_hoverable: true,
+				should_block_lower: false,
+			},
+		));
+	}
+	for i in 0..4 {
+		world.spawn((
+			Mesh3d(mesh_assets.add(Cuboid::new(1.4, 0.05, 0.1))),
+			MeshMaterial3d(steel.clone()),
+			Transform::from_xyz(0., 0.3 + i as f32 * 0.25, 0.),
+			Pickable {
+				is_hoverable: true,
+				should_block_lower: false,
+			},
+		));
+	}
+	Scene::new(world)
+}
+
+pub fn fence_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cuboid::new(1.6, 0.6, 0.04))),
+		MeshMaterial3d(material_assets.add(Color::from(tailwind::ORANGE_500))),
+		Transform::from_xyz(0., 0.3, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	Scene::new(world)
+}
+
+/// Small hut with a colored roof.
+pub fn hut_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+	roof_color: Srgba,
+) -> Scene {
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cuboid::new(1.2, 0.8, 1.))),
+		MeshMaterial3d(material_assets.add(Color::from(tailwind::AMBER_100))),
+		Transform::from_xyz(0., 0.4, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cone::new(0.9, 0.6))),
+		MeshMaterial3d(material_assets.add(Color::from(roof_color))),
+		Transform::from_xyz(0., 1.1, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	Scene::new(world)
+}
+
+pub fn parking_scene(
+	mesh_assets: &mut Assets<Mesh>,
+	material_assets: &mut Assets<StandardMaterial>,
+) -> Scene {
+	let mut world = World::new();
+	world.spawn((
+		Mesh3d(mesh_assets.add(Cylinder::new(0.9, 0.05))),
+		MeshMaterial3d(material_assets.add(Color::from(tailwind::ZINC_600))),
+		Transform::from_xyz(0., 0.025, 0.),
+		Pickable {
+			is_hoverable: true,
+			should_block_lower: false,
+		},
+	));
+	Scene::new(world)
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use hexx::Hex;
+use serde::{Serialize, Deserialize};
+
+use crate::util::hex::{cell_normal, cell_slope};
+use crate::clock::GameClock;
+use crate::weather::Weather;
+use crate::scene::sun_rotation;
+use crate::economy::pricing::ResortValue;
+use crate::grid::{CellHeights, CellSurfaces, surface::Surface};
+
+/// Simulates the snow cover of every cell.
+pub struct SnowPlugin;
+impl Plugin for SnowPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<CellSnow>();
+		app.add_systems(Startup, setup);
+		app.add_systems(Update, (simulate_snow, rate_piste_snow).chain());
+	}
+}
+
+/// Snow shallower than this (cm) makes a piste unskiable.
+pub const MIN_SKIABLE_DEPTH: f32 = 20.;
+/// Snow shallower than this (cm) counts as bare ground.
+pub const BARE_DEPTH: f32 = 1.;
+/// Snow depth (cm) that completely covers the ground.
+pub const FULL_COVER_DEPTH: f32 = 30.;
+/// Melting per degree above zero per in-game minute (cm).
+pub const MELT_RATE: f32 = 0.002;
+/// How much faster snow melts on a slope facing straight at the sun.
+pub const SUN_MELT_FACTOR: f32 = 1.5;
+/// Slush freezes into ice below this temperature (°C).
+pub const REFREEZE_TEMPERATURE: f32 = -2.;
+/// New snow (cm) that has to build up before the surface counts as powder.
+pub const POWDER_DEPTH: f32 = 2.;
+/// Skier passes before powder is packed down.
+pub const PACKING_TRAFFIC: f32 = 20.;
+/// Skier passes before packed snow is scraped into ice or moguls.
+pub const ICING_TRAFFIC: f32 = 200.;
+/// Packed snow on cells at least this steep is pushed into moguls instead of being scraped into ice.
+pub const MOGUL_MIN_SLOPE: u16 = 3;
+/// Fraction of the remaining traffic that is forgotten per in-game minute, as the snow settles.
+pub const TRAFFIC_DECAY: f32 = 0.002;
+
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum SnowQuality {
+	Powder,
+	/// Freshly groomed corduroy.
+	Groomed,
+	#[default]
+	Packed,
+	Moguls,
+	Icy,
+	Slush,
+	Bare,
+}
+impl SnowQuality {
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Powder => "Powder",
+			Self::Groomed => "Groomed",
+			Self::Packed => "Packed",
+			Self::Moguls => "Moguls",
+			Self::Icy => "Icy",
+			Self::Slush => "Slush",
+			Self::Bare => "Bare",
+		}
+	}
+
+	/// How nice it is to ski on, from 0 to 1.
+	pub const fn score(&self) -> f32 {
+		match self {
+			Self::Powder => 1.,
+			Self::Groomed => 1.,
+			Self::Packed => 0.8,
+			Self::Moguls => 0.6,
+			Self::Icy => 0.4,
+			Self::Slush => 0.5,
+			Self::Bare => 0.,
+		}
+	}
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct SnowCell {
+	/// Snow depth (cm).
+	pub depth: f32,
+	pub quality: SnowQuality,
+	/// Skier passes since the snow was last fresh.
+	pub traffic: f32,
+	/// New snow (cm) that isn't deep enough yet to turn the surface into powder.
+	pub fresh: f32,
+}
+impl SnowCell {
+	pub fn is_skiable(&self) -> bool {
+		self.depth >= MIN_SKIABLE_DEPTH && self.quality != SnowQuality::Bare
+	}
+
+	/// How nice the cell is to ski on, from 0 to 1, taking both depth and quality into account.
+	pub fn score(&self) -> f32 {
+		self.quality.score() * (self.depth / MIN_SKIABLE_DEPTH).clamp(0., 1.)
+	}
+
+	/// Fraction of the ground that is visibly covered, from 0 to 1.
+	pub fn coverage(&self) -> f32 {
+		(self.depth / FULL_COVER_DEPTH).clamp(0., 1.)
+	}
+}
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CellSnow(pub HashMap<Hex, SnowCell>);
+impl CellSnow {
+	/// Records that a skier passed over a cell.
+	pub fn add_traffic(&mut self, pos: &Hex, passes: f32) {
+		match self.0.get_mut(pos) {Some(cell) => cell.traffic += passes, None => warn!("Skier passed over cell {:?} which has no snow cell.", pos)}
+	}
+
+	/// Cost for guests to ski through a piste cell, or None if there isn't enough snow.
+	/// Bad snow is avoided when there is a better way down.
+	pub fn piste_cost(&self, pos: &Hex) -> Option<u32> {
+		let cell = self.0.get(pos)?;
+		if !cell.is_skiable() {return None}
+		Some(1 + ((1. - cell.score()) * 4.) as u32)
+	}
+}
+
+/// Starting depth is deeper the higher up the cell is.
+pub fn setup(
+	mut snow: ResMut<CellSnow>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+) {
+	for (pos, height) in heights.0.iter() {
+		let depth = match surfaces.0.get(pos) {
+			Some(Surface::Water) => 0.,
+			_ => 10. + 2. * *height as f32,
+		};
+		snow.0.insert(*pos, SnowCell {
+			depth: depth,
+			quality: if depth < BARE_DEPTH {SnowQuality::Bare} else {SnowQuality::Packed},
+			traffic: 0.,
+			fresh: 0.,
+		});
+	}
+}
+
+/// Adds snowfall, melts snow depending on altitude and sun exposure, and wears it down with traffic.
+fn simulate_snow(
+	clock: Res<GameClock>,
+	weather: Res<Weather>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	mut snow: ResMut<CellSnow>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	let (sun_rotation, sun_elevation) = sun_rotation(&clock);
+	// The light points away from the sun.
+	let towards_sun = sun_rotation * Vec3::Z;
+	let snowfall = weather.snowfall_per_minute() * minutes;
+
+	for (pos, cell) in snow.0.iter_mut() {
+		if surfaces.0.get(pos) == Some(&Surface::Water) {continue}
+		let height = match heights.0.get(pos) {Some(height) => *height, None => {error_once!("Snow cell {:?} is missing a height.", pos); continue}};
+		let temperature = weather.temperature_at(height, clock.minute);
+		let sun = if sun_elevation > 0. {Some(towards_sun)} else {None};
+		weather_cell(cell, &heights.0, pos, temperature, snowfall, sun, minutes);
+	}
+}
+
+/// Updates one cell for the weather and traffic of the last minutes.
+/// `sun` points towards the sun, or is None while it's down.
+fn weather_cell(cell: &mut SnowCell, heights: &HashMap<Hex, u16>, pos: &Hex, temperature: f32, snowfall: f32, sun: Option<Vec3>, minutes: f32) {
+	if snowfall > 0. && temperature < 1. {
+		cell.depth += snowfall;
+		cell.fresh += snowfall;
+		if cell.fresh >= POWDER_DEPTH {
+			cell.quality = SnowQuality::Powder;
+			cell.traffic = 0.;
+			cell.fresh = 0.;
+		}
+	}
+
+	if temperature > 0. && cell.depth > 0. {
+		let exposure = sun.map_or(0., |sun| cell_normal(heights, pos).dot(sun).max(0.));
+		cell.depth = (cell.depth - MELT_RATE * temperature * (1. + SUN_MELT_FACTOR * exposure) * minutes).max(0.);
+		cell.quality = SnowQuality::Slush;
+		cell.fresh = 0.;
+	} else if cell.quality == SnowQuality::Slush && temperature < REFREEZE_TEMPERATURE {
+		cell.quality = SnowQuality::Icy;
+	}
+
+	match cell.quality {
+		SnowQuality::Powder | SnowQuality::Groomed if cell.traffic > PACKING_TRAFFIC => cell.quality = SnowQuality::Packed,
+		SnowQuality::Packed if cell.traffic > ICING_TRAFFIC => {
+			cell.quality = if cell_slope(heights, pos) >= MOGUL_MIN_SLOPE {SnowQuality::Moguls} else {SnowQuality::Icy};
+		},
+		_ => (),
+	}
+	cell.traffic *= (1. - TRAFFIC_DECAY).powf(minutes);
+
+	if cell.depth < BARE_DEPTH {
+		cell.quality = SnowQuality::Bare;
+	} else if cell.quality == SnowQuality::Bare {
+		cell.quality = SnowQuality::Packed;
+	}
+}
+
+/// Rates the snow on all pistes, which guests take into account when deciding whether the prices are worth it.
+fn rate_piste_snow(
+	snow: Res<CellSnow>,
+	surfaces: Res<CellSurfaces>,
+	mut value: ResMut<ResortValue>,
+) {
+	let scores: Vec<f32> = surfaces.0.iter()
+	.filter(|(_, surface)| **surface == Surface::Piste)
+	.filter_map(|(pos, _)| snow.0.get(pos).map(|cell| cell.score()))
+	.collect();
+	let snow_quality = if scores.is_empty() {1.} else {scores.iter().sum::<f32>() / scores.len() as f32};
+	if value.snow_quality != snow_quality {
+		value.snow_quality = snow_quality;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Flat ground, except for a ramp going up towards +x around the origin.
+	fn heights(ramp: bool) -> HashMap<Hex, u16> {
+		Hex::ZERO.range(2).map(|pos| (pos, if ramp {(10 + pos.x * 2) as u16} else {10})).collect()
+	}
+
+	fn cell(quality: SnowQuality, traffic: f32) -> SnowCell {
+		SnowCell {depth: 50., quality: quality, traffic: traffic, fresh: 0.}
+	}
+
+	#[test]
+	fn powder_needs_real_snowfall() {
+		let heights = heights(false);
+		let mut snow = cell(SnowQuality::Packed, 50.);
+		weather_cell(&mut snow, &heights, &Hex::ZERO, -5., POWDER_DEPTH / 4., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Packed, "A few flakes turn the piste into powder.");
+		for _ in 0..3 {
+			weather_cell(&mut snow, &heights, &Hex::ZERO, -5., POWDER_DEPTH / 4., None, 1.);
+		}
+		assert_eq!((snow.quality, snow.traffic), (SnowQuality::Powder, 0.));
+	}
+
+	#[test]
+	fn traffic_packs_and_scrapes_the_snow() {
+		let mut snow = cell(SnowQuality::Powder, PACKING_TRAFFIC + 1.);
+		weather_cell(&mut snow, &heights(false), &Hex::ZERO, -5., 0., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Packed);
+		let mut snow = cell(SnowQuality::Packed, ICING_TRAFFIC + 1.);
+		weather_cell(&mut snow, &heights(false), &Hex::ZERO, -5., 0., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Icy, "Flat pistes are scraped into ice.");
+		let mut snow = cell(SnowQuality::Packed, ICING_TRAFFIC + 1.);
+		weather_cell(&mut snow, &heights(true), &Hex::ZERO, -5., 0., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Moguls, "Steep pistes are pushed into moguls.");
+	}
+
+	#[test]
+	fn slush_refreezes_into_ice() {
+		let mut snow = cell(SnowQuality::Powder, 0.);
+		weather_cell(&mut snow, &heights(false), &Hex::ZERO, 3., 0., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Slush);
+		weather_cell(&mut snow, &heights(false), &Hex::ZERO, REFREEZE_TEMPERATURE / 2., 0., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Slush, "Slush only freezes in real cold.");
+		weather_cell(&mut snow, &heights(false), &Hex::ZERO, REFREEZE_TEMPERATURE - 1., 0., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Icy);
+	}
+
+	#[test]
+	fn thin_snow_is_bare() {
+		let mut snow = SnowCell {depth: BARE_DEPTH + 0.01, ..cell(SnowQuality::Packed, 0.)};
+		weather_cell(&mut snow, &heights(false), &Hex::ZERO, 5., 0., None, 10.);
+		assert_eq!(snow.quality, SnowQuality::Bare);
+		snow.depth = BARE_DEPTH * 2.;
+		weather_cell(&mut snow, &heights(false), &Hex::ZERO, -5., 0., None, 1.);
+		assert_eq!(snow.quality, SnowQuality::Packed, "Snow made on bare ground is packed.");
+	}
+
+	#[test]
+	fn slopes_facing_the_sun_melt_faster() {
+		let heights = heights(true);
+		// The ramp goes up towards +x, so it faces -x.
+		let melted = |sun: Option<Vec3>| {
+			let mut snow = cell(SnowQuality::Packed, 0.);
+			weather_cell(&mut snow, &heights, &Hex::ZERO, 5., 0., sun, 60.);
+			50. - snow.depth
+		};
+		let (facing, away, night) = (melted(Some(Vec3::new(-1., 1., 0.).normalize())), melted(Some(Vec3::new(1., 1., 0.).normalize())), melted(None));
+		assert!(facing > away, "Facing the sun melted {} cm and facing away {} cm.", facing, away);
+		assert!(away >= night && night > 0.);
+	}
+}
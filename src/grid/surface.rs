@@ -1,6 +1,40 @@
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default, Serialize, Deserialize)]
 pub enum Surface {
 	#[default]
 	None,
 	Piste,
+	Water,
+}
+
+/// Difficulty of a piste cell, decided by its slope.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum PisteGrade {
+	Green,
+	Blue,
+	Red,
+	Black,
+}
+impl PisteGrade {
+	pub const ALL: [Self; 4] = [Self::Green, Self::Blue, Self::Red, Self::Black];
+
+	/// Grade of a piste cell with the slope from `cell_slope`.
+	pub const fn from_slope(slope: u16) -> Self {
+		match slope {
+			0..=1 => Self::Green,
+			2 => Self::Blue,
+			3 => Self::Red,
+			_ => Self::Black,
+		}
+	}
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Green => "Green",
+			Self::Blue => "Blue",
+			Self::Red => "Red",
+			Self::Black => "Black",
+		}
+	}
 }
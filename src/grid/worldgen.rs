@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use noise::{Perlin, NoiseFn};
+
+use crate::util::hex::{axial_to_xz, offset_to_axial};
+use crate::grid::{CellHeights, CellSurfaces, surface::Surface};
+
+#[derive(Resource, Debug, PartialEq, Clone, Copy)]
+pub struct WorldGenSettings {
+	/// It is recommended to use an odd number for width to avoid sharp corners.
+	pub width: u16,
+	pub length: u16,
+	/// 1-50
+	pub peak_height: f64,
+	/// 1-50
+	pub peak_width: f64,
+	/// 1-50
+	pub slope_height: f64, // TODO: Add more parameters.
+	pub seed: u32,
+}
+impl Default for WorldGenSettings {
+	fn default() -> Self {Self {
+		width: 95, length: 50, peak_height: 10., peak_width: 30., slope_height: 40., seed: rand::random(),
+	}}
+}
+impl WorldGenSettings {
+	pub const WATER_HEIGHT: f64 = -3.;
+}
+
+/// Generates the terrain heights and surfaces.
+pub fn setup(
+	settings: Res<WorldGenSettings>,
+	mut heights: ResMut<CellHeights>,
+	mut surfaces: ResMut<CellSurfaces>,
+) {
+	let perlin = Perlin::new(settings.seed);
+	let max_z = settings.length as f64 * f64::sqrt(3.); // TODO: Use fancy new std::f32::consts::SQRT_3 when available. https://github.com/rust-lang/rust/issues/103883
+	for col in 0..settings.width as i32 {
+		for row in 0..settings.length as i32 + (col % 2) { // Adds one extra row every other column (avoids sharp corners).
+			let pos = offset_to_axial(col, row);
+
+			let [x, z] = axial_to_xz(&pos);
+			let height = perlin.get([x as f64 / settings.peak_width, z as f64 / settings.peak_width])
+			* settings.peak_height + (z as f64 / max_z) * settings.slope_height;
+			heights.0.insert(pos, height as u16);
+
+			// Add water if height is low enough.
+			let surface = if height < WorldGenSettings::WATER_HEIGHT {Surface::Water} else {Surface::None};
+			surfaces.0.insert(pos, surface);
+		}
+	}
+}
@@ -0,0 +1,437 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use bevy::{prelude::*, color::palettes::tailwind};
+use bevy_egui::EguiContexts;
+use hexx::{Hex, algorithms::a_star};
+use serde::{Serialize, Deserialize};
+
+use crate::clock::{GameClock, DayStarted, OPENING_TIME, CLOSING_TIME};
+use crate::economy::{Economy, Money, ledger::Category};
+use crate::save::ResortLoaded;
+use crate::tool::{Tool, ToolState, pick::Hover};
+use crate::staff::Staffing;
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::Surface,
+	snow::{CellSnow, SnowQuality, BARE_DEPTH},
+	geometry::{TerrainChanged, gizmo::{cell_outline, cell_top, path_line}},
+	object::{ObjectInstanceId, ObjectInstance, structure::{StructureTypes, StructureRole}},
+};
+
+/// Snowcats that leave their garages at night to groom the pistes.
+pub struct GroomingPlugin;
+impl Plugin for GroomingPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<GroomingSettings>();
+		app.init_resource::<GroomingPlans>();
+		app.init_resource::<Grooming>();
+		app.add_systems(Startup, load_assets);
+		app.add_systems(Update, (
+			(dispatch_snowcats, drive_snowcats).chain(),
+			draw_routes.run_if(|settings: Res<GroomingSettings>| settings.show_routes),
+			record_route,
+		));
+		app.add_observer(pay_grooming);
+		app.add_observer(recall_snowcats);
+		app.add_observer(retry_skipped);
+	}
+}
+
+/// Cells driven per in-game minute.
+pub const SNOWCAT_SPEED: f32 = 0.5;
+/// Fuel used per cell driven (l).
+pub const FUEL_PER_CELL: f32 = 2.;
+/// Price of fuel per liter.
+pub const FUEL_PRICE: f32 = 1.5;
+/// Snowcats avoid driving off piste, but can if there is no other way.
+const OFF_PISTE_COST: u32 = 10;
+
+#[derive(Resource, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct GroomingSettings {
+	/// Minute of the day when the snowcats leave their garages.
+	pub start: f32,
+	/// Minute of the day when the snowcats head back, which is usually in the next morning.
+	pub end: f32,
+	pub show_routes: bool,
+}
+impl Default for GroomingSettings {
+	fn default() -> Self {Self {start: CLOSING_TIME + 60., end: OPENING_TIME - 60., show_routes: false}}
+}
+impl GroomingSettings {
+	/// Whether snowcats should be out grooming at the minute of the day.
+	/// The shift may wrap around midnight.
+	pub fn is_shift(&self, minute: f32) -> bool {
+		if self.start <= self.end {minute >= self.start && minute < self.end}
+		else {minute >= self.start || minute < self.end}
+	}
+}
+
+/// How the snowcats of a garage choose what to groom.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum GroomingPlan {
+	/// Grooms the piste cells with the worst snow first.
+	#[default]
+	Priority,
+	/// Drives through the cells in order, and starts over from the first cell when done.
+	Route(Vec<Hex>),
+}
+
+/// Plans for each snowcat garage, where garages without a plan use `GroomingPlan::Priority`.
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct GroomingPlans {
+	pub plans: HashMap<ObjectInstanceId, GroomingPlan>,
+	/// Clicked terrain cells are added to the route of this garage.
+	#[serde(skip)]
+	pub recording: Option<ObjectInstanceId>,
+}
+
+/// What grooming used over a night.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct GroomingUsage {
+	pub cells_groomed: u32,
+	/// Liters.
+	pub fuel: f32,
+}
+impl GroomingUsage {
+	pub fn fuel_cost(&self) -> Money {(self.fuel * FUEL_PRICE).round() as Money}
+}
+
+#[derive(Resource, Debug, Default, Clone)]
+pub struct Grooming {
+	/// Snowcats sent out from each garage this shift.
+	pub dispatched: HashMap<ObjectInstanceId, u32>,
+	/// Cells that no snowcat could find a way to, which are left alone until the terrain changes.
+	pub skipped: HashSet<Hex>,
+	pub today: GroomingUsage,
+	pub yesterday: GroomingUsage,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Snowcat {
+	pub garage: ObjectInstanceId,
+	pub position: Hex,
+	/// Cells left to drive through, not including `position`.
+	pub path: VecDeque<Hex>,
+	/// Progress towards the next cell in the path, from 0 to 1.
+	pub progress: f32,
+	/// Index of the next cell in a `GroomingPlan::Route`.
+	pub route_index: usize,
+	pub heading_home: bool,
+}
+
+#[derive(Resource, Debug, Clone)]
+struct SnowcatAssets {
+	mesh: Handle<Mesh>,
+	material: Handle<StandardMaterial>,
+}
+
+fn load_assets(
+	mut commands: Commands,
+	mut mesh_assets: ResMut<Assets<Mesh>>,
+	mut material_assets: ResMut<Assets<StandardMaterial>>,
+) {
+	commands.insert_resource(SnowcatAssets {
+		mesh: mesh_assets.add(Cuboid::new(0.6, 0.4, 0.9)),
+		material: material_assets.add(Color::from(tailwind::RED_600)),
+	});
+}
+
+/// Cost for a snowcat to drive into a cell, or None if it can't.
+fn driving_cost(heights: &HashMap<Hex, u16>, surfaces: &HashMap<Hex, Surface>, pos: Hex) -> Option<u32> {
+	if !heights.contains_key(&pos) {return None}
+	match surfaces.get(&pos) {
+		Some(Surface::Water) => None,
+		Some(Surface::Piste) => Some(1),
+		_ => Some(OFF_PISTE_COST),
+	}
+}
+
+/// Cells to drive through from start to end, not including start.
+fn find_path(heights: &HashMap<Hex, u16>, surfaces: &HashMap<Hex, Surface>, start: Hex, end: Hex) -> Option<VecDeque<Hex>> {
+	let path = a_star(start, end, |_, next| driving_cost(heights, surfaces, next))?;
+	Some(path.into_iter().skip(1).collect())
+}
+
+/// Index of the next route cell to drive to, counting on past the end of the route, or None if every cell is skipped.
+fn next_route_index(route: &[Hex], start: usize, skipped: &HashSet<Hex>) -> Option<usize> {
+	(start..start + route.len()).find(|index| !skipped.contains(&route[index % route.len()]))
+}
+
+/// The piste cell with the worst snow that no other snowcat is heading for, and the closest of equally bad cells.
+/// Bare, groomed and powder cells are left alone.
+fn priority_target(surfaces: &HashMap<Hex, Surface>, snow: &CellSnow, claimed: &HashSet<Hex>, skipped: &HashSet<Hex>, from: Hex) -> Option<Hex> {
+	surfaces.iter()
+	.filter(|(pos, surface)| **surface == Surface::Piste && !claimed.contains(*pos) && !skipped.contains(*pos))
+	.filter_map(|(pos, _)| snow.0.get(pos).map(|cell| (pos, cell)))
+	.filter(|(_, cell)| cell.depth >= BARE_DEPTH && !matches!(cell.quality, SnowQuality::Groomed | SnowQuality::Powder))
+	.min_by_key(|(pos, cell)| ((cell.score() * 10.) as u32, from.unsigned_distance_to(**pos)))
+	.map(|(pos, _)| *pos)
+}
+
+/// Sends out a snowcat for every driver on duty at a garage during the shift, up to the capacity of the garage.
+/// Snowcats that came back early aren't sent out again until the next shift.
+fn dispatch_snowcats(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	settings: Res<GroomingSettings>,
+	staffing: Res<Staffing>,
+	assets: Res<SnowcatAssets>,
+	heights: Res<CellHeights>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	mut grooming: ResMut<Grooming>,
+) {
+	if !settings.is_shift(clock.minute) {
+		if !grooming.dispatched.is_empty() {
+			grooming.dispatched.clear();
+		}
+		return
+	}
+	for (instance_id, drivers) in staffing.drivers.iter() {
+		let Some(ObjectInstance::Structure(instance)) = objects.get(instance_id) else {continue};
+		let Some(StructureRole::SnowcatGarage(capacity)) = structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.role) else {continue};
+		let dispatched = grooming.dispatched.get(instance_id).copied().unwrap_or_default();
+		let count = (*drivers as u32).min(capacity).saturating_sub(dispatched);
+		if count == 0 {continue}
+		let Some(translation) = cell_top(&heights.0, &instance.position) else {error_once!("Snowcat garage {:?} is on cell {:?} which has no height.", instance_id, instance.position); continue};
+		for _ in 0..count {
+			commands.spawn((
+				Snowcat {garage: *instance_id, position: instance.position, path: VecDeque::new(), progress: 0., route_index: 0, heading_home: false},
+				Mesh3d(assets.mesh.clone()),
+				MeshMaterial3d(assets.material.clone()),
+				Transform::from_translation(translation),
+			));
+		}
+		*grooming.dispatched.entry(*instance_id).or_default() += count;
+	}
+}
+
+/// Snowcats aren't saved, so the loaded resort sends out its own.
+fn recall_snowcats(
+	_trigger: Trigger<ResortLoaded>,
+	mut commands: Commands,
+	mut grooming: ResMut<Grooming>,
+	snowcats: Query<Entity, With<Snowcat>>,
+) {
+	for entity in snowcats.iter() {
+		commands.entity(entity).despawn();
+	}
+	grooming.dispatched.clear();
+	grooming.skipped.clear();
+}
+
+/// Cells that couldn't be reached before might be now.
+fn retry_skipped(
+	_trigger: Trigger<TerrainChanged>,
+	mut grooming: ResMut<Grooming>,
+) {
+	if !grooming.skipped.is_empty() {
+		grooming.skipped.clear();
+	}
+}
+
+/// Moves snowcats along their paths, grooms the piste cells they pass, and picks new targets.
+fn drive_snowcats(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	settings: Res<GroomingSettings>,
+	plans: Res<GroomingPlans>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	mut snow: ResMut<CellSnow>,
+	mut grooming: ResMut<Grooming>,
+	mut snowcats: Query<(Entity, &mut Snowcat, &mut Transform)>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	// Cells that other snowcats are already heading for.
+	let mut claimed: HashSet<Hex> = snowcats.iter().filter_map(|(_, snowcat, _)| snowcat.path.back().copied()).collect();
+
+	for (entity, mut snowcat, mut transform) in snowcats.iter_mut() {
+		let garage_pos = match objects.get(&snowcat.garage) {
+			Some(ObjectInstance::Structure(instance)) => instance.position,
+			_ => {warn!("Snowcat {:?} lost its garage {:?}.", entity, snowcat.garage); commands.entity(entity).despawn(); continue}
+		};
+
+		if snowcat.path.is_empty() {
+			if snowcat.heading_home && snowcat.position == garage_pos {
+				commands.entity(entity).despawn();
+				continue
+			}
+			let target = if snowcat.heading_home || !settings.is_shift(clock.minute) {None} else {
+				match plans.plans.get(&snowcat.garage) {
+					Some(GroomingPlan::Route(route)) if !route.is_empty() => next_route_index(route, snowcat.route_index, &grooming.skipped).map(|index| {
+						snowcat.route_index = index + 1;
+						route[index % route.len()]
+					}),
+					_ => priority_target(&surfaces.0, &snow, &claimed, &grooming.skipped, snowcat.position),
+				}
+			};
+			let target = match target {
+				Some(target) => target,
+				None => {snowcat.heading_home = true; garage_pos},
+			};
+			match find_path(&heights.0, &surfaces.0, snowcat.position, target) {
+				Some(path) => snowcat.path = path,
+				None if target == garage_pos => {warn!("Snowcat {:?} can't find its way back to its garage, so it's towed home.", entity); commands.entity(entity).despawn(); continue},
+				None => {grooming.skipped.insert(target); continue},
+			}
+			claimed.insert(target);
+		}
+
+		snowcat.progress += SNOWCAT_SPEED * minutes;
+		while snowcat.progress >= 1. {
+			let Some(next) = snowcat.path.pop_front() else {snowcat.progress = 0.; break};
+			snowcat.progress -= 1.;
+			snowcat.position = next;
+			grooming.today.fuel += FUEL_PER_CELL;
+			if surfaces.0.get(&next) == Some(&Surface::Piste) && let Some(cell) = snow.0.get_mut(&next) && cell.depth >= BARE_DEPTH {
+				cell.quality = SnowQuality::Groomed;
+				cell.traffic = 0.;
+				grooming.today.cells_groomed += 1;
+			}
+		}
+
+		let from = cell_top(&heights.0, &snowcat.position).unwrap_or(transform.translation);
+		let to = snowcat.path.front().and_then(|next| cell_top(&heights.0, next)).unwrap_or(from);
+		transform.translation = from.lerp(to, snowcat.progress);
+		if to != from {
+			let y = transform.translation.y;
+			transform.look_at(Vec3::new(to.x, y, to.z), Vec3::Y);
+		}
+	}
+}
+
+/// Pays for last night's fuel. Drivers are paid with the rest of the staff.
+fn pay_grooming(
+	_trigger: Trigger<DayStarted>,
+	mut grooming: ResMut<Grooming>,
+	mut economy: ResMut<Economy>,
+) {
+	let fuel_cost = grooming.today.fuel_cost();
+	if fuel_cost > 0 {
+		economy.charge(Category::Fuel, fuel_cost);
+	}
+	grooming.yesterday = grooming.today;
+	grooming.today = GroomingUsage::default();
+}
+
+/// Adds clicked cells to the route that is being recorded.
+/// Recording puts the tools away, so the clicks only go to the route, and picking a tool stops it.
+fn record_route(
+	mut contexts: EguiContexts,
+	mut plans: ResMut<GroomingPlans>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	hover: Res<Hover>,
+	tools: Res<ToolState>,
+) {
+	let Some(garage) = plans.recording else {return};
+	if tools.tool != Tool::None {
+		plans.recording = None;
+		return
+	}
+	if !mouse_button.just_pressed(MouseButton::Left) || contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area()) {return}
+	let Some(pos) = hover.cell() else {return};
+	match plans.plans.entry(garage).or_insert(GroomingPlan::Route(Vec::new())) {
+		GroomingPlan::Route(route) => route.push(pos),
+		plan => *plan = GroomingPlan::Route(vec![pos]),
+	}
+}
+
+/// Draws the planned routes of all garages, and the paths the snowcats are currently driving.
+fn draw_routes(
+	mut gizmos: Gizmos,
+	plans: Res<GroomingPlans>,
+	heights: Res<CellHeights>,
+	snowcats: Query<&Snowcat>,
+) {
+	for (garage, plan) in plans.plans.iter() {
+		let GroomingPlan::Route(route) = plan else {continue};
+		let color = if plans.recording == Some(*garage) {tailwind::YELLOW_300} else {tailwind::ORANGE_400};
+		// Routes loop back to their start.
+		path_line(&mut *gizmos, &heights.0, route.iter().chain(route.first()).copied(), color);
+		for pos in route {
+			cell_outline(&mut *gizmos, &heights.0, pos, color);
+		}
+	}
+	for snowcat in snowcats.iter() {
+		path_line(&mut *gizmos, &heights.0, [snowcat.position].into_iter().chain(snowcat.path.iter().copied()), tailwind::RED_400);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grid::{
+		snow::SnowCell,
+		object::{BuildCategory, structure::{StructureType, StructureInstance, StructureTypeId, Footprint}},
+	};
+
+	#[test]
+	fn worst_snow_is_groomed_first() {
+		let surfaces: HashMap<Hex, Surface> = Hex::ZERO.range(3).map(|pos| (pos, Surface::Piste)).collect();
+		let cell = |quality: SnowQuality| SnowCell {depth: 50., quality: quality, ..default()};
+		let mut snow = CellSnow(surfaces.keys().map(|pos| (*pos, cell(SnowQuality::Groomed))).collect());
+		let (none, mut claimed) = (HashSet::new(), HashSet::new());
+		assert_eq!(priority_target(&surfaces, &snow, &none, &none, Hex::ZERO), None, "Groomed snow is groomed again.");
+		snow.0.insert(Hex::new(2, 0), cell(SnowQuality::Packed));
+		snow.0.insert(Hex::new(-2, 0), cell(SnowQuality::Icy));
+		snow.0.insert(Hex::new(1, 0), cell(SnowQuality::Icy));
+		snow.0.insert(Hex::new(0, 1), SnowCell {depth: 0., ..cell(SnowQuality::Bare)});
+		assert_eq!(priority_target(&surfaces, &snow, &none, &none, Hex::ZERO), Some(Hex::new(1, 0)), "The closest of the icy cells comes first.");
+		claimed.insert(Hex::new(1, 0));
+		assert_eq!(priority_target(&surfaces, &snow, &claimed, &none, Hex::ZERO), Some(Hex::new(-2, 0)));
+		let skipped = HashSet::from([Hex::new(-2, 0)]);
+		assert_eq!(priority_target(&surfaces, &snow, &claimed, &skipped, Hex::ZERO), Some(Hex::new(2, 0)));
+	}
+
+	#[test]
+	fn routes_leave_out_skipped_cells() {
+		let route = [Hex::new(0, 0), Hex::new(1, 0), Hex::new(2, 0)];
+		let skipped = HashSet::from([Hex::new(1, 0)]);
+		assert_eq!(next_route_index(&route, 1, &HashSet::new()), Some(1));
+		assert_eq!(next_route_index(&route, 1, &skipped), Some(2));
+		assert_eq!(next_route_index(&route, 3, &skipped), Some(3), "The route starts over.");
+		assert_eq!(next_route_index(&route, 0, &route.into_iter().collect()), None);
+	}
+
+	#[test]
+	fn snowcats_are_sent_out_for_each_driver() {
+		let mut world = World::new();
+		let mut objects = PlacedObjects::default();
+		let garage = objects.push(ObjectInstance::Structure(StructureInstance {type_id: StructureTypeId(302), position: Hex::ZERO, rotation: None}));
+		world.insert_resource(objects);
+		world.insert_resource(StructureTypes(HashMap::from([(StructureTypeId(302), StructureType {
+			name: "Snowcat Garage",
+			scene: Handle::default(),
+			footprint: Footprint::single(2),
+			has_rotation: false,
+			cost: 0,
+			upkeep: 0,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::SnowcatGarage(2),
+		})])));
+		world.insert_resource(CellHeights(HashMap::from([(Hex::ZERO, 5)])));
+		world.insert_resource(SnowcatAssets {mesh: Handle::default(), material: Handle::default()});
+		world.insert_resource(GroomingSettings::default());
+		world.insert_resource(GameClock {minute: CLOSING_TIME + 120., ..default()});
+		world.init_resource::<Staffing>();
+		world.init_resource::<Grooming>();
+		let mut schedule = Schedule::default();
+		schedule.add_systems(dispatch_snowcats);
+		let mut snowcats = world.query::<&Snowcat>();
+
+		schedule.run(&mut world);
+		assert_eq!(snowcats.iter(&world).count(), 0, "Snowcats go out without drivers.");
+		world.resource_mut::<Staffing>().drivers.insert(garage, 1);
+		schedule.run(&mut world);
+		assert_eq!(snowcats.iter(&world).count(), 1);
+		world.resource_mut::<Staffing>().drivers.insert(garage, 3);
+		schedule.run(&mut world);
+		schedule.run(&mut world);
+		assert_eq!(snowcats.iter(&world).count(), 2, "The garage only holds two snowcats.");
+		world.resource_mut::<GameClock>().minute = 12. * 60.;
+		schedule.run(&mut world);
+		assert!(world.resource::<Grooming>().dispatched.is_empty(), "The count isn't reset for the next shift.");
+	}
+}
@@ -0,0 +1,550 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use bevy::prelude::*;
+use hexx::{Hex, algorithms::a_star};
+use rand::{random, random_bool, random_range, seq::IndexedRandom};
+use serde::{Serialize, Deserialize};
+
+use crate::util::hex::cell_slope;
+use crate::clock::{GameClock, OPENING_TIME, CLOSING_TIME};
+use crate::weather::Weather;
+use crate::staff::{Staffing, walking_path};
+use crate::avalanche::Avalanches;
+use crate::facility::{Facility, Facilities, FacilityPrices, placed_facilities};
+use crate::satisfaction::{Experience, GuestLeft};
+use crate::accident::{Accident, accident_chance};
+use crate::economy::{
+	Economy,
+	ledger::Category,
+	pricing::{Ticket, TicketPrices, ResortValue, SeasonPassHolders, daily_arrivals, price_satisfaction},
+};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::{Surface, PisteGrade},
+	snow::CellSnow,
+	geometry::overlay::{AddOverlay, CellColors, scale_color},
+	object::{ObjectInstanceId, ObjectInstance, structure::StructureTypes, lift::{LiftInstance, LiftType, LiftTypes, LiftQueues}},
+};
+
+/// Spawns guests that arrive depending on ticket prices, and removes them when they leave.
+pub struct GuestPlugin;
+impl Plugin for GuestPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<GuestArrivals>();
+		app.add_systems(Update, (arrive, grow_needs, ski, leave));
+		app.add_overlay("Guest density", "Green is empty, red is crowded.", guest_density_overlay);
+	}
+}
+
+/// Chance that a season pass holder visits on any given day.
+pub const SEASON_PASS_VISIT_CHANCE: f32 = 0.2;
+/// Guest demand on weekends, relative to weekdays.
+pub const WEEKEND_FACTOR: f32 = 1.4;
+/// How long half-day guests stay, in in-game minutes.
+pub const HALF_DAY_MINUTES: f32 = 3.5 * 60.;
+/// Guests waiting for a ticket give up and go home when the queue is longer than this.
+pub const MAX_TICKET_QUEUE: f32 = 30.;
+/// Guests look for a facility when a need gets this high.
+pub const NEED_THRESHOLD: f32 = 0.6;
+/// Needs above this make guests unhappy.
+pub const UNMET_NEED: f32 = 0.9;
+/// Hunger gained per in-game minute.
+pub const HUNGER_RATE: f32 = 1. / 240.;
+/// Bladder need gained per in-game minute.
+pub const BLADDER_RATE: f32 = 1. / 180.;
+/// Cold gained per in-game minute per degree below zero, which goes away again when it's warmer.
+pub const COLD_RATE: f32 = 1. / 1200.;
+/// Share of the guests that need to rent skis.
+pub const RENTAL_SHARE: f64 = 0.4;
+/// Guests less skilled than this might want lessons.
+pub const LESSON_SKILL: f32 = 0.25;
+/// Cells walked per in-game minute.
+pub const GUEST_WALKING_SPEED: f32 = 3.;
+/// Cells skied per in-game minute.
+pub const GUEST_SKIING_SPEED: f32 = 10.;
+/// Cells that lifts carry guests up per in-game minute.
+pub const LIFT_SPEED: f32 = 4.;
+/// In-game minutes before a guest who can't get to any run tries again.
+const REPLAN_MINUTES: f32 = 5.;
+/// Traffic added to a piste cell by one guest skiing through it.
+const RUN_TRAFFIC: f32 = 1.;
+/// Runs on a cell where at least this many other guests are skiing feel crowded.
+pub const CROWDED_GUESTS: usize = 3;
+/// Chance that a guest tries a run one grade harder than their skill allows.
+pub const OVERREACH_CHANCE: f64 = 0.1;
+
+/// Something that a guest needs, which facilities satisfy.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Need {
+	Hunger,
+	Cold,
+	Bladder,
+	/// Guests without their own gear need to rent skis before skiing.
+	Rental,
+	Lessons,
+	/// Parking is taken when arriving, and kept for the whole stay.
+	Parking,
+}
+impl Need {
+	pub const ALL: [Self; 6] = [Self::Hunger, Self::Cold, Self::Bladder, Self::Rental, Self::Lessons, Self::Parking];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Hunger => "Hunger",
+			Self::Cold => "Cold",
+			Self::Bladder => "Bladder",
+			Self::Rental => "Rental",
+			Self::Lessons => "Lessons",
+			Self::Parking => "Parking",
+		}
+	}
+
+	pub const fn revenue_category(&self) -> Category {
+		match self {
+			// Toilets are usually run by the restaurants.
+			Self::Hunger | Self::Cold | Self::Bladder => Category::Food,
+			Self::Rental => Category::Rentals,
+			Self::Lessons => Category::Lessons,
+			Self::Parking => Category::Parking,
+		}
+	}
+
+	/// Whether guests walk to a facility to satisfy the need.
+	pub const fn is_visited(&self) -> bool {!matches!(self, Self::Parking)}
+}
+
+/// How much a guest needs each `Need`, from 0 to 1.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Needs([f32; Need::ALL.len()]);
+impl Needs {
+	pub fn get(&self, need: Need) -> f32 {self.0[need as usize]}
+
+	pub fn get_mut(&mut self, need: Need) -> &mut f32 {&mut self.0[need as usize]}
+
+	/// Needs at or above the threshold, most pressing first, with equal needs in the order of `Need::ALL`.
+	pub fn pressing(&self, threshold: f32) -> Vec<Need> {
+		let mut needs: Vec<Need> = Need::ALL.into_iter().filter(|need| self.get(*need) >= threshold).collect();
+		// The sort is stable, so equal needs stay in order.
+		needs.sort_by(|a, b| self.get(*b).total_cmp(&self.get(*a)));
+		needs
+	}
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Activity {
+	#[default]
+	Skiing,
+	Walking {facility: ObjectInstanceId, minutes_left: f32},
+	Queuing(ObjectInstanceId),
+	Using {facility: ObjectInstanceId, minutes_left: f32},
+	/// Hurt in an accident, waiting for the ski patrol or being carried to first aid.
+	Injured,
+}
+
+#[derive(Component, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Guest {
+	pub ticket: Ticket,
+	pub experience: Experience,
+	/// Minute of the day when the guest goes home.
+	pub leaves_at: f32,
+	/// 0-1, where 0 is a first-timer and 1 can ski anything.
+	pub skill: f32,
+	/// The cell where the guest is, or the last cell they skied.
+	pub position: Hex,
+	pub needs: Needs,
+	pub activity: Activity,
+	/// In-game minutes until the guest gets off the lift they're queuing for or riding.
+	pub run_minutes_left: f32,
+	pub parked: Option<ObjectInstanceId>,
+	/// The run the guest is on. Not saved, so guests plan a new run after loading.
+	#[serde(skip)]
+	pub run: Option<Run>,
+}
+impl Guest {
+	/// The steepest piste grade the guest dares to ski.
+	pub fn max_grade(&self) -> PisteGrade {
+		PisteGrade::ALL[((self.skill * PisteGrade::ALL.len() as f32) as usize).min(PisteGrade::ALL.len() - 1)]
+	}
+}
+
+/// A guest's way down to a lift, up it, and down the run they chose.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Run {
+	pub grade: PisteGrade,
+	/// The lift to ride at the end of the path, or None once the guest got on.
+	pub lift: Option<ObjectInstanceId>,
+	/// Cells that the guest still has to go through, next first.
+	pub path: VecDeque<Hex>,
+	/// Cells to ski from the top of the lift to the end of the run.
+	pub descent: VecDeque<Hex>,
+	/// Cells gone through per in-game minute along the path.
+	pub speed: f32,
+	/// How far the guest got towards the next cell of the path, from 0 to 1.
+	pub progress: f32,
+}
+
+/// Guests that are on their way, but haven't arrived yet.
+/// Arrivals are spread out over the opening hours, so fractions of guests are kept until they add up.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct GuestArrivals {
+	pub day_pass: f32,
+	pub half_day: f32,
+	pub season_pass: f32,
+	/// Tickets that the cashiers and machines have time to sell, which don't carry over if nobody is queuing.
+	pub ticket_sales: f32,
+}
+
+fn arrive(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	mut arrivals: ResMut<GuestArrivals>,
+	mut economy: ResMut<Economy>,
+	prices: Res<TicketPrices>,
+	value: Res<ResortValue>,
+	holders: Res<SeasonPassHolders>,
+	staffing: Res<Staffing>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	facility_prices: Res<FacilityPrices>,
+	mut facilities: ResMut<Facilities>,
+	guests: Query<&Guest>,
+) {
+	if !clock.is_open() {return}
+	let mut parking_lots: Vec<(ObjectInstanceId, Hex, Facility)> = placed_facilities(&objects, &structure_types)
+	.filter(|(_, _, facility)| facility.need == Need::Parking)
+	.collect();
+	parking_lots.sort_by_key(|(instance_id, _, _)| *instance_id);
+	let mut parked: HashMap<ObjectInstanceId, u32> = HashMap::new();
+	for guest in guests.iter() {
+		if let Some(lot) = guest.parked {*parked.entry(lot).or_default() += 1}
+	}
+	let entrance = match objects.iter().find_map(|(_, object)| match object {ObjectInstance::Lift(lift) => Some(lift.bottom), _ => None}) {
+		Some(bottom) => bottom,
+		None => Hex::ZERO, // TODO: Add a resort entrance.
+	};
+	let weekday_factor = if clock.weekday().is_weekend() {WEEKEND_FACTOR} else {1.};
+	let day_fraction = clock.delta_minutes / (CLOSING_TIME - OPENING_TIME) * clock.season_phase().demand_factor() * weekday_factor;
+	arrivals.day_pass += daily_arrivals(&value, &prices, Ticket::DayPass) * day_fraction;
+	arrivals.half_day += daily_arrivals(&value, &prices, Ticket::HalfDay) * day_fraction;
+	arrivals.season_pass += holders.0 as f32 * SEASON_PASS_VISIT_CHANCE * day_fraction;
+	arrivals.ticket_sales = (arrivals.ticket_sales + staffing.ticket_sales_rate() * clock.delta_minutes).min((arrivals.day_pass + arrivals.half_day).max(1.));
+
+	let GuestArrivals {day_pass, half_day, season_pass, ticket_sales} = &mut *arrivals;
+	for ticket in Ticket::ALL {
+		let pending = match ticket {
+			Ticket::DayPass => &mut *day_pass,
+			Ticket::HalfDay => &mut *half_day,
+			Ticket::SeasonPass => &mut *season_pass,
+		};
+		if ticket != Ticket::SeasonPass && *pending > MAX_TICKET_QUEUE {
+			*pending = MAX_TICKET_QUEUE;
+		}
+		while *pending >= 1. {
+			let price = prices.get(ticket);
+			match ticket {
+				Ticket::DayPass | Ticket::HalfDay => {
+					// Guests without a ticket keep queuing.
+					if *ticket_sales < 1. {break}
+					*ticket_sales -= 1.;
+					economy.earn(Category::LiftTickets, price);
+				},
+				Ticket::SeasonPass => (), // Already paid for.
+			}
+			*pending -= 1.;
+			let leaves_at = match ticket {
+				Ticket::HalfDay => clock.minute + HALF_DAY_MINUTES,
+				Ticket::DayPass | Ticket::SeasonPass => random_range(clock.minute..=CLOSING_TIME),
+			}.min(CLOSING_TIME);
+			let mut experience = Experience {
+				price: price_satisfaction(price, value.fair_price(ticket)),
+				..default()
+			};
+			let free_lot = parking_lots.iter().find(|(instance_id, _, facility)| parked.get(instance_id).copied().unwrap_or_default() < facility.capacity);
+			let (position, lot) = match (free_lot, parking_lots.first()) {
+				(Some((instance_id, position, facility)), _) => {
+					*parked.entry(*instance_id).or_default() += 1;
+					let parking_price = facility_prices.get(instance_id, facility);
+					if parking_price > 0 {
+						economy.earn(Category::Parking, parking_price);
+						let state = facilities.0.entry(*instance_id).or_default();
+						state.revenue_today += parking_price;
+						state.revenue_total += parking_price;
+					}
+					(*position, Some(*instance_id))
+				},
+				(None, Some((_, position, _))) => {
+					experience.parking_full = true;
+					(*position, None)
+				},
+				(None, None) => (entrance, None),
+			};
+			// Most guests are beginners.
+			let skill = random::<f32>().powi(2);
+			let mut needs = Needs::default();
+			*needs.get_mut(Need::Hunger) = random_range(0. ..0.3);
+			*needs.get_mut(Need::Bladder) = random_range(0. ..0.3);
+			if random_bool(RENTAL_SHARE) {*needs.get_mut(Need::Rental) = 1.}
+			if skill < LESSON_SKILL && random_bool(0.5) {*needs.get_mut(Need::Lessons) = 1.}
+			commands.spawn(Guest {
+				ticket: ticket,
+				experience: experience,
+				leaves_at: leaves_at,
+				skill: skill,
+				position: position,
+				needs: needs,
+				activity: Activity::Skiing,
+				run_minutes_left: 0.,
+				parked: lot,
+				run: None,
+			});
+		}
+	}
+}
+
+/// Needs grow over the day, and unmet needs spoil the experience.
+fn grow_needs(
+	clock: Res<GameClock>,
+	weather: Res<Weather>,
+	heights: Res<CellHeights>,
+	mut guests: Query<&mut Guest>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	for mut guest in guests.iter_mut() {
+		if let Activity::Using {..} = guest.activity {continue}
+		let height = heights.0.get(&guest.position).copied().unwrap_or_default();
+		let temperature = weather.temperature_at(height, clock.minute);
+		let needs = &mut guest.needs;
+		*needs.get_mut(Need::Hunger) += HUNGER_RATE * minutes;
+		*needs.get_mut(Need::Bladder) += BLADDER_RATE * minutes;
+		*needs.get_mut(Need::Cold) -= temperature * COLD_RATE * minutes;
+		for need in Need::ALL {
+			let value = needs.get_mut(need);
+			*value = value.clamp(0., 1.);
+		}
+		let unmet = Need::ALL.iter().filter(|need| guest.needs.get(**need) >= UNMET_NEED).count();
+		if unmet > 0 {
+			guest.experience.unmet_need_minutes += unmet as f32 * minutes;
+		}
+	}
+}
+
+/// Cost for guests to ski from one cell onto the next, or None if they can't.
+/// Guests only ski downhill, along the skiable pistes.
+fn skiing_cost(heights: &HashMap<Hex, u16>, pistes: &HashMap<Hex, u32>, from: Hex, to: Hex) -> Option<u32> {
+	if heights.get(&to)? > heights.get(&from)? {return None}
+	pistes.get(&to).copied()
+}
+
+/// Cells to ski through from start to end, not including start.
+/// The start and end don't have to be pistes, so guests can ski from and to the lifts.
+fn skiing_path(heights: &HashMap<Hex, u16>, pistes: &HashMap<Hex, u32>, start: Hex, end: Hex) -> Option<VecDeque<Hex>> {
+	let path = a_star(start, end, |from, to| if to == start || to == end {Some(1)} else {skiing_cost(heights, pistes, from, to)})?;
+	Some(path.into_iter().skip(1).collect())
+}
+
+/// Piste cells that guests can ski down to from the start.
+fn reachable_pistes(heights: &HashMap<Hex, u16>, pistes: &HashMap<Hex, u32>, start: Hex) -> HashSet<Hex> {
+	let mut reached = HashSet::new();
+	let mut frontier = vec![start];
+	while let Some(pos) = frontier.pop() {
+		for next in pos.all_neighbors() {
+			if reached.contains(&next) || skiing_cost(heights, pistes, pos, next).is_none() {continue}
+			reached.insert(next);
+			frontier.push(next);
+		}
+	}
+	reached
+}
+
+/// Guests ski runs that suit their skill and the visibility, on pistes with enough snow that aren't closed.
+/// For each run they ski or walk to the bottom of an open lift that reaches it, line up, ride up and ski down to the end of the run.
+/// Every run has a chance of ending in an accident.
+fn ski(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	lift_types: Res<LiftTypes>,
+	avalanches: Res<Avalanches>,
+	weather: Res<Weather>,
+	mut snow: ResMut<CellSnow>,
+	mut queues: ResMut<LiftQueues>,
+	mut guests: Query<(Entity, &mut Guest)>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	let open_lifts: HashMap<ObjectInstanceId, (&LiftInstance, &LiftType)> = objects.iter().filter_map(|(instance_id, object)| match object {
+		ObjectInstance::Lift(lift) if lift.is_open_at(clock.minute) => lift_types.0.get(&lift.type_id).map(|lift_type| (*instance_id, (lift, lift_type))),
+		_ => None,
+	}).collect();
+	// Only worked out if some guest plans a new run.
+	let mut pistes: Option<HashMap<Hex, u32>> = None;
+	let mut runs: Option<HashMap<Hex, (PisteGrade, Vec<ObjectInstanceId>)>> = None;
+	let mut skiers: HashMap<Hex, usize> = HashMap::new();
+	for (_, guest) in guests.iter().filter(|(_, guest)| guest.activity == Activity::Skiing) {
+		*skiers.entry(guest.position).or_default() += 1;
+	}
+	for (entity, mut guest) in guests.iter_mut() {
+		if guest.activity != Activity::Skiing {continue}
+		if guest.run_minutes_left > 0. {
+			guest.run_minutes_left -= minutes;
+			continue
+		}
+		let Some(mut run) = guest.run.take() else {
+			let pistes = pistes.get_or_insert_with(|| surfaces.0.iter()
+				.filter(|(pos, surface)| **surface == Surface::Piste && !avalanches.is_closed(pos))
+				.filter_map(|(pos, _)| snow.piste_cost(pos).map(|cost| (*pos, cost)))
+				.collect());
+			let runs = runs.get_or_insert_with(|| {
+				let mut runs: HashMap<Hex, (PisteGrade, Vec<ObjectInstanceId>)> = HashMap::new();
+				for (instance_id, (lift, _)) in open_lifts.iter() {
+					for pos in reachable_pistes(&heights.0, pistes, lift.top) {
+						runs.entry(pos).or_insert_with(|| (PisteGrade::from_slope(cell_slope(&heights.0, &pos)), Vec::new())).1.push(*instance_id);
+					}
+				}
+				runs
+			});
+			match plan_run(&guest, &weather, &heights, &surfaces, pistes, runs, &open_lifts) {
+				Some((run, grades)) => {
+					guest.experience.grades_available = grades;
+					guest.run = Some(run);
+				},
+				None => {
+					// The guest waits without a lift to ride.
+					guest.experience.no_lift_minutes += REPLAN_MINUTES;
+					guest.run_minutes_left = REPLAN_MINUTES;
+				},
+			}
+			continue
+		};
+
+		run.progress += run.speed * minutes;
+		while run.progress >= 1. && let Some(next) = run.path.pop_front() {
+			run.progress -= 1.;
+			*skiers.entry(guest.position).or_default() -= 1;
+			*skiers.entry(next).or_default() += 1;
+			guest.position = next;
+			if surfaces.0.get(&next) == Some(&Surface::Piste) {snow.add_traffic(&next, RUN_TRAFFIC)}
+		}
+		if !run.path.is_empty() {
+			guest.run = Some(run);
+			continue
+		}
+		run.progress = 0.;
+
+		if let Some(instance_id) = run.lift.take() {
+			// Guests that find their lift closed plan another run.
+			let Some((lift, lift_type)) = open_lifts.get(&instance_id) else {continue};
+			let queue = queues.0.entry(instance_id).or_default();
+			let wait = queue.wait_minutes(lift_type);
+			queue.waiting += 1.;
+			queue.rides_today += 1;
+			guest.run_minutes_left = wait + lift.bottom.unsigned_distance_to(lift.top) as f32 / LIFT_SPEED;
+			guest.experience.queue_minutes += wait;
+			run.path = std::mem::take(&mut run.descent);
+			run.speed = GUEST_SKIING_SPEED;
+			guest.run = Some(run);
+			continue
+		}
+
+		let pos = guest.position;
+		let others = skiers.get(&pos).copied().unwrap_or_default().saturating_sub(1);
+		let cell = snow.0.get(&pos).copied().unwrap_or_default();
+		if random_bool(accident_chance(run.grade, guest.skill, cell.quality, others)) {
+			commands.trigger(Accident {guest: entity, position: pos});
+		}
+		let experience = &mut guest.experience;
+		experience.runs += 1;
+		experience.snow += cell.score();
+		if others >= CROWDED_GUESTS {experience.crowded_runs += 1}
+		experience.grades_skied.insert(run.grade);
+	}
+}
+
+/// Picks a run for the guest among those reachable from the open lifts, and the way there.
+/// Also returns how many grades the guest could choose from, or None if they can't get to any run.
+fn plan_run(
+	guest: &Guest,
+	weather: &Weather,
+	heights: &CellHeights,
+	surfaces: &CellSurfaces,
+	pistes: &HashMap<Hex, u32>,
+	runs: &HashMap<Hex, (PisteGrade, Vec<ObjectInstanceId>)>,
+	open_lifts: &HashMap<ObjectInstanceId, (&LiftInstance, &LiftType)>,
+) -> Option<(Run, usize)> {
+	let max_grade = match random_bool(OVERREACH_CHANCE) {
+		true => PisteGrade::ALL[(guest.max_grade() as usize + 1).min(PisteGrade::ALL.len() - 1)],
+		false => guest.max_grade(),
+	};
+	let choices: Vec<(&Hex, &(PisteGrade, Vec<ObjectInstanceId>))> = runs.iter().filter(|(_, (grade, _))| *grade <= max_grade).collect();
+	let grades: HashSet<PisteGrade> = choices.iter().map(|(_, (grade, _))| *grade).collect();
+	// Fog keeps guests off the harder runs, and nobody skis a run they can't see at all.
+	let (end, (grade, lift_ids)) = choices.choose_weighted(&mut rand::rng(), |(_, (grade, _))| weather.grade_willingness(*grade)).ok()?;
+	let (lift_id, (lift, _)) = lift_ids.iter()
+		.filter_map(|instance_id| open_lifts.get_key_value(instance_id))
+		.min_by_key(|(instance_id, (lift, _))| (guest.position.unsigned_distance_to(lift.bottom), **instance_id))?;
+	let descent = [lift.top].into_iter().chain(skiing_path(&heights.0, pistes, lift.top, **end)?).collect();
+	// Guests ski to the lift if they can, and walk otherwise.
+	let (path, speed) = match skiing_path(&heights.0, pistes, guest.position, lift.bottom) {
+		Some(path) => (path, GUEST_SKIING_SPEED),
+		None => (walking_path(&heights.0, &surfaces.0, guest.position, lift.bottom)?, GUEST_WALKING_SPEED),
+	};
+	let run = Run {grade: *grade, lift: Some(*lift_id), path: path, descent: descent, speed: speed, progress: 0.};
+	Some((run, grades.len()))
+}
+
+/// Colors the cells with guests on them by how crowded they are.
+fn guest_density_overlay(guests: Query<&Guest>) -> CellColors {
+	let mut counts: HashMap<Hex, usize> = HashMap::new();
+	for guest in guests.iter() {
+		*counts.entry(guest.position).or_default() += 1;
+	}
+	counts.into_iter().map(|(pos, count)| (pos, scale_color(count as f32 / (CROWDED_GUESTS + 1) as f32))).collect()
+}
+
+/// Guests go home when their stay is over, or when the lifts close.
+/// Injured guests can't leave on their own until the resort closes.
+fn leave(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	guests: Query<(Entity, &Guest)>,
+) {
+	for (entity, guest) in guests.iter() {
+		let injured = guest.activity == Activity::Injured;
+		if !clock.is_open() || (clock.minute >= guest.leaves_at && !injured) {
+			commands.trigger(GuestLeft(guest.clone()));
+			commands.entity(entity).despawn();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ties_go_to_the_first_need() {
+		let mut needs = Needs::default();
+		*needs.get_mut(Need::Bladder) = 0.7;
+		*needs.get_mut(Need::Hunger) = 0.7;
+		*needs.get_mut(Need::Cold) = 0.4;
+		assert_eq!(needs.pressing(0.3), vec![Need::Hunger, Need::Bladder, Need::Cold]);
+		assert_eq!(needs.pressing(0.5), vec![Need::Hunger, Need::Bladder]);
+	}
+
+	#[test]
+	fn guests_ski_downhill_along_pistes() {
+		// A piste down the x axis from a lift top at the origin, with a bump partway and a branch off to the side.
+		let heights: HashMap<Hex, u16> = Hex::ZERO.range(6).map(|pos| (pos, (20 - pos.x * 2) as u16 + u16::from(pos == Hex::new(4, 0)) * 10)).collect();
+		let mut pistes: HashMap<Hex, u32> = (1..=5).map(|x| (Hex::new(x, 0), 1)).collect();
+		pistes.insert(Hex::new(1, 1), 1);
+		let reached = reachable_pistes(&heights, &pistes, Hex::ZERO);
+		assert_eq!(reached, HashSet::from([Hex::new(1, 0), Hex::new(2, 0), Hex::new(3, 0), Hex::new(1, 1)]));
+		let path = skiing_path(&heights, &pistes, Hex::ZERO, Hex::new(3, 0)).unwrap();
+		assert_eq!(path, VecDeque::from([Hex::new(1, 0), Hex::new(2, 0), Hex::new(3, 0)]));
+		assert!(skiing_path(&heights, &pistes, Hex::ZERO, Hex::new(5, 0)).is_none(), "the bump blocks the way");
+		// The end can be off the piste, like a lift station.
+		assert_eq!(skiing_path(&heights, &pistes, Hex::new(2, 0), Hex::new(3, -1)).unwrap().back(), Some(&Hex::new(3, -1)));
+	}
+}
@@ -1,6 +1,8 @@
 // Disable console on windows for release builds.
 // https://github.com/bevyengine/bevy_github_ci_template/issues/55#issue-2391468497
 #![cfg_attr(not(feature = "fast-compile"), windows_subsystem = "windows")]
+// Bevy systems take their parameters as arguments and queries as types, and fields are written out in full.
+#![allow(clippy::too_many_arguments, clippy::type_complexity, clippy::redundant_field_names)]
 
 use bevy::prelude::*;
 use bevy_mod_outline::OutlinePlugin;
@@ -10,6 +12,21 @@ mod util;
 mod grid;
 mod scene;
 mod camera;
+mod clock;
+mod economy;
+mod guest;
+mod weather;
+mod snowmaking;
+mod grooming;
+mod avalanche;
+mod staff;
+mod facility;
+mod satisfaction;
+mod accident;
+mod save;
+mod tool;
+mod thumbnail;
+mod ui;
 
 fn main() {
 	App::new()
@@ -19,9 +36,30 @@ fn main() {
 		OutlinePlugin,
 		MeshPickingPlugin,
 		EguiPlugin {enable_multipass_for_primary_context: true},
+		clock::ClockPlugin,
+		scene::ScenePlugin,
 		grid::GridPlugin,
 		camera::CameraPlugin,
+		economy::EconomyPlugin,
+		guest::GuestPlugin,
+		weather::WeatherPlugin,
+		// Resort operations.
+		(
+			snowmaking::SnowmakingPlugin,
+			grooming::GroomingPlugin,
+			avalanche::AvalanchePlugin,
+			staff::StaffPlugin,
+			facility::FacilityPlugin,
+			satisfaction::SatisfactionPlugin,
+			accident::AccidentPlugin,
+		),
+		save::SavePlugin,
+		// Player interface.
+		(
+			tool::ToolPlugin,
+			thumbnail::ThumbnailPlugin,
+			ui::UiPlugin,
+		),
 	))
-	.add_systems(Startup, scene::setup)
 	.run();
 }
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::clock::GameClock;
+use crate::guest::Guest;
+use crate::economy::pricing::ResortValue;
+use crate::grid::surface::PisteGrade;
+
+/// Guests write reviews when they leave, which add up to the star rating of the resort.
+pub struct SatisfactionPlugin;
+impl Plugin for SatisfactionPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<ResortRating>();
+		app.add_observer(write_review);
+	}
+}
+
+/// Queuing this long in total (in-game minutes) makes for the worst queue experience.
+pub const QUEUE_TOLERANCE: f32 = 60.;
+/// Having unmet needs for this long in total (in-game minutes) makes for the worst facility experience.
+pub const UNMET_NEED_TOLERANCE: f32 = 60.;
+/// Waiting this long in total for a lift to run (in-game minutes) makes for the worst lift experience.
+pub const NO_LIFT_TOLERANCE: f32 = 30.;
+/// Injured guests waiting this long (in-game minutes) for the ski patrol feel abandoned.
+pub const RESCUE_TOLERANCE: f32 = 60.;
+/// Number of reviews that the rating is based on.
+pub const RATED_REVIEWS: usize = 200;
+/// Rating of a resort without any reviews.
+pub const DEFAULT_RATING: f32 = 3.;
+
+/// Something guests take into account when deciding how much they liked their stay.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Factor {
+	Snow,
+	Lifts,
+	Queues,
+	Crowding,
+	Facilities,
+	Price,
+	Variety,
+	Safety,
+}
+impl Factor {
+	pub const ALL: [Self; 8] = [Self::Snow, Self::Lifts, Self::Queues, Self::Crowding, Self::Facilities, Self::Price, Self::Variety, Self::Safety];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Snow => "Snow",
+			Self::Lifts => "Lifts",
+			Self::Queues => "Queues",
+			Self::Crowding => "Crowding",
+			Self::Facilities => "Food and facilities",
+			Self::Price => "Price",
+			Self::Variety => "Variety",
+			Self::Safety => "Safety",
+		}
+	}
+
+	/// How much the factor counts towards the satisfaction.
+	pub const fn weight(&self) -> f32 {
+		match self {
+			Self::Safety => 3.,
+			Self::Snow | Self::Lifts => 2.,
+			Self::Price => 1.5,
+			Self::Queues | Self::Crowding | Self::Facilities => 1.,
+			Self::Variety => 0.5,
+		}
+	}
+
+	pub const fn complaint(&self) -> &'static str {
+		match self {
+			Self::Snow => "the snow was terrible",
+			Self::Lifts => "the lifts kept stopping",
+			Self::Queues => "the queues were endless",
+			Self::Crowding => "the pistes were packed with people",
+			Self::Facilities => "there was nowhere to eat, warm up or go to the toilet",
+			Self::Price => "it was way too expensive",
+			Self::Variety => "the same runs got boring",
+			Self::Safety => "nobody came to help after a bad fall",
+		}
+	}
+
+	pub const fn praise(&self) -> &'static str {
+		match self {
+			Self::Snow => "the great snow",
+			Self::Lifts => "how reliable the lifts were",
+			Self::Queues => "that there were no queues",
+			Self::Crowding => "the empty pistes",
+			Self::Facilities => "the nice facilities",
+			Self::Price => "how cheap it was",
+			Self::Variety => "all the different runs",
+			Self::Safety => "how quickly the ski patrol came",
+		}
+	}
+}
+
+/// What happened to a guest during their stay.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Experience {
+	/// 0-1, where 1 is a bargain.
+	pub price: f32,
+	pub runs: u32,
+	/// Sum of the snow scores of all runs.
+	pub snow: f32,
+	pub crowded_runs: u32,
+	pub grades_skied: HashSet<PisteGrade>,
+	/// Number of grades at the resort that the guest dares to ski.
+	pub grades_available: usize,
+	/// In-game minutes spent queuing.
+	pub queue_minutes: f32,
+	/// In-game minutes spent with any unmet need.
+	pub unmet_need_minutes: f32,
+	/// In-game minutes spent waiting because no lift was running.
+	pub no_lift_minutes: f32,
+	pub parking_full: bool,
+	pub injured: bool,
+	/// In-game minutes until the ski patrol reached the injured guest, or None if they never did.
+	pub rescue_minutes: Option<f32>,
+}
+impl Experience {
+	/// How good the factor was, from 0 to 1, or None if the guest hasn't experienced it yet.
+	pub fn score(&self, factor: Factor) -> Option<f32> {
+		let score = match factor {
+			Factor::Snow => {
+				if self.runs == 0 {return None}
+				self.snow / self.runs as f32
+			},
+			Factor::Lifts => 1. - self.no_lift_minutes / NO_LIFT_TOLERANCE,
+			Factor::Queues => 1. - self.queue_minutes / QUEUE_TOLERANCE,
+			Factor::Crowding => {
+				if self.runs == 0 {return None}
+				1. - self.crowded_runs as f32 / self.runs as f32
+			},
+			Factor::Facilities => 1. - self.unmet_need_minutes / UNMET_NEED_TOLERANCE - if self.parking_full {0.3} else {0.},
+			Factor::Price => self.price,
+			Factor::Variety => {
+				if self.grades_available == 0 {return None}
+				self.grades_skied.len() as f32 / self.grades_available as f32
+			},
+			Factor::Safety => {
+				if !self.injured {return None}
+				self.rescue_minutes.map_or(0., |minutes| 1. - minutes / RESCUE_TOLERANCE)
+			},
+		};
+		Some(score.clamp(0., 1.))
+	}
+
+	/// Weighted average of all experienced factors, from 0 to 1.
+	pub fn satisfaction(&self) -> f32 {
+		let (sum, weights) = Factor::ALL.iter()
+		.filter_map(|factor| self.score(*factor).map(|score| (score * factor.weight(), factor.weight())))
+		.fold((0., 0.), |(sum, weights), (score, weight)| (sum + score, weights + weight));
+		if weights > 0. {sum / weights} else {0.5}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Review {
+	pub day: u32,
+	/// 1-5.
+	pub stars: u8,
+	/// The worst factors, worst first.
+	pub complaints: Vec<Factor>,
+	pub praise: Option<Factor>,
+}
+impl Review {
+	/// Factors below this score are complained about.
+	pub const COMPLAINT_SCORE: f32 = 0.5;
+	/// Factors above this score may be praised.
+	pub const PRAISE_SCORE: f32 = 0.8;
+	/// Most complaints in one review.
+	pub const MAX_COMPLAINTS: usize = 2;
+
+	pub fn new(experience: &Experience, day: u32) -> Self {
+		let mut scores: Vec<(Factor, f32)> = Factor::ALL.iter().filter_map(|factor| experience.score(*factor).map(|score| (*factor, score))).collect();
+		scores.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+		// Being left injured ruins the whole stay.
+		let abandoned = experience.injured && experience.rescue_minutes.is_none();
+		Self {
+			day: day,
+			stars: if abandoned {1} else {1 + (experience.satisfaction() * 4.).round() as u8},
+			complaints: scores.iter().filter(|(_, score)| *score < Self::COMPLAINT_SCORE).take(Self::MAX_COMPLAINTS).map(|(factor, _)| *factor).collect(),
+			praise: scores.last().filter(|(_, score)| *score >= Self::PRAISE_SCORE).map(|(factor, _)| *factor),
+		}
+	}
+
+	/// A short review in the guest's own words.
+	pub fn text(&self) -> String {
+		let stars = "★".repeat(self.stars as usize) + &"☆".repeat(5 - self.stars as usize);
+		let mut text = match self.praise {
+			Some(praise) => format!("{} Loved {}", stars, praise.praise()),
+			None => format!("{} It was {}", stars, match self.stars {1 => "awful", 2 => "disappointing", 3 => "okay", 4 => "good", _ => "great"}),
+		};
+		let complaints: Vec<&str> = self.complaints.iter().map(|factor| factor.complaint()).collect();
+		if !complaints.is_empty() {
+			text += &format!(", but {}", complaints.join(" and "));
+		}
+		text + "."
+	}
+}
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ResortRating {
+	/// The latest reviews, newest first.
+	pub reviews: VecDeque<Review>,
+}
+impl ResortRating {
+	/// Average stars of the latest reviews.
+	pub fn stars(&self) -> f32 {
+		if self.reviews.is_empty() {return DEFAULT_RATING}
+		self.reviews.iter().map(|review| review.stars as f32).sum::<f32>() / self.reviews.len() as f32
+	}
+
+	/// How often each factor is complained about in the latest reviews, most common first.
+	pub fn top_complaints(&self) -> Vec<(Factor, usize)> {
+		let mut counts: HashMap<Factor, usize> = HashMap::new();
+		for factor in self.reviews.iter().flat_map(|review| review.complaints.iter()) {
+			*counts.entry(*factor).or_default() += 1;
+		}
+		let mut counts: Vec<(Factor, usize)> = counts.into_iter().collect();
+		counts.sort_by(|(factor_a, a), (factor_b, b)| b.cmp(a).then(factor_a.cmp(factor_b)));
+		counts
+	}
+}
+
+#[derive(Event, Debug, Clone)]
+/// A guest went home.
+pub struct GuestLeft(pub Guest);
+
+fn write_review(
+	trigger: Trigger<GuestLeft>,
+	clock: Res<GameClock>,
+	mut rating: ResMut<ResortRating>,
+	mut value: ResMut<ResortValue>,
+) {
+	rating.reviews.push_front(Review::new(&trigger.0.experience, clock.day));
+	rating.reviews.truncate(RATED_REVIEWS);
+	let stars = rating.stars();
+	if value.rating != stars {
+		value.rating = stars;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn satisfaction_averages_the_experienced_factors() {
+		// Without runs or injuries, only lifts, queues, facilities and price count.
+		let bargain = Experience {price: 1., ..default()};
+		assert_eq!(bargain.score(Factor::Snow), None);
+		assert_eq!(bargain.score(Factor::Safety), None);
+		assert_eq!(bargain.satisfaction(), 1.);
+		let expensive = Experience {price: 0., ..default()};
+		assert!((expensive.satisfaction() - 4. / 5.5).abs() < 1e-6);
+		let queued = Experience {price: 1., queue_minutes: QUEUE_TOLERANCE * 2., ..default()};
+		assert_eq!(queued.score(Factor::Queues), Some(0.));
+		assert!(queued.satisfaction() < bargain.satisfaction());
+	}
+
+	#[test]
+	fn reviews_complain_about_the_worst_factors() {
+		let experience = Experience {
+			price: 0.9,
+			runs: 10,
+			snow: 2.,
+			crowded_runs: 7,
+			queue_minutes: QUEUE_TOLERANCE * 0.4,
+			unmet_need_minutes: UNMET_NEED_TOLERANCE * 0.1,
+			..default()
+		};
+		let review = Review::new(&experience, 3);
+		assert_eq!(review.day, 3);
+		assert_eq!(review.complaints, vec![Factor::Snow, Factor::Crowding]);
+		assert_eq!(review.praise, Some(Factor::Lifts));
+		assert_eq!(review.stars, 1 + (experience.satisfaction() * 4.).round() as u8);
+
+		let abandoned = Experience {price: 1., injured: true, rescue_minutes: None, ..default()};
+		let review = Review::new(&abandoned, 0);
+		assert_eq!(review.stars, 1);
+		assert_eq!(review.complaints.first(), Some(&Factor::Safety));
+	}
+
+	#[test]
+	fn review_text_reads_well() {
+		for factor in Factor::ALL {
+			let review = Review {day: 0, stars: 4, complaints: vec![factor], praise: Some(factor)};
+			assert_eq!(review.text(), format!("★★★★☆ Loved {}, but {}.", factor.praise(), factor.complaint()));
+			assert!(!review.text().contains("the the") && !review.text().contains("Loved a "), "{}", review.text());
+		}
+		let review = Review {day: 0, stars: 5, complaints: Vec::new(), praise: Some(Factor::Price)};
+		assert_eq!(review.text(), "★★★★★ Loved how cheap it was.");
+		let review = Review {day: 0, stars: 2, complaints: vec![Factor::Queues, Factor::Snow], praise: None};
+		assert_eq!(review.text(), "★★☆☆☆ It was disappointing, but the queues were endless and the snow was terrible.");
+	}
+
+	#[test]
+	fn top_complaints_are_the_most_common() {
+		let review = |complaints: Vec<Factor>| Review {day: 0, stars: 2, complaints: complaints, praise: None};
+		let rating = ResortRating {reviews: VecDeque::from([
+			review(vec![Factor::Price, Factor::Queues]),
+			review(vec![Factor::Queues]),
+			review(vec![Factor::Snow]),
+			review(vec![]),
+		])};
+		assert_eq!(rating.top_complaints(), vec![(Factor::Queues, 2), (Factor::Snow, 1), (Factor::Price, 1)]);
+		assert_eq!(rating.stars(), 2.);
+		assert_eq!(ResortRating::default().stars(), DEFAULT_RATING);
+	}
+}
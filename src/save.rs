@@ -0,0 +1,367 @@
+use std::{fs, fmt, path::{Path, PathBuf}};
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::clock::GameClock;
+use crate::weather::Weather;
+use crate::guest::Guest;
+use crate::staff::StaffMember;
+use crate::facility::{Facilities, FacilityPrices};
+use crate::camera::flythrough::Flythrough;
+use crate::satisfaction::ResortRating;
+use crate::avalanche::Avalanches;
+use crate::snowmaking::{Snowmaking, SnowmakingSettings};
+use crate::grooming::{GroomingPlans, GroomingSettings};
+use crate::accident::Incidents;
+use crate::economy::{
+	Economy,
+	loan::Loans,
+	pricing::{TicketPrices, SeasonPassHolders},
+};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	snow::CellSnow,
+};
+
+/// Saves the resort to a file and loads it back.
+/// Loading replaces the saved resources, and every module rebuilds its entities when `ResortLoaded` is triggered.
+pub struct SavePlugin;
+impl Plugin for SavePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Update, quick_save_shortcuts);
+		app.add_observer(save_resort);
+		app.add_observer(load_resort);
+	}
+}
+
+/// Version of the save format, which goes up whenever `SaveData` changes.
+pub const SAVE_VERSION: u32 = 8;
+/// Directory where saves are kept.
+pub const SAVE_DIRECTORY: &str = "saves";
+/// File name used by quick save and quick load, without the extension.
+pub const QUICK_SAVE_NAME: &str = "quicksave";
+/// Start of binary save files, to tell them apart from other files.
+const BINARY_MAGIC: &[u8; 4] = b"SRTB";
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SaveFormat {
+	/// Readable text, for debugging.
+	#[default]
+	Ron,
+	/// Compact binary.
+	Binary,
+}
+impl SaveFormat {
+	pub const ALL: [Self; 2] = [Self::Ron, Self::Binary];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Ron => "RON (text)",
+			Self::Binary => "Binary",
+		}
+	}
+
+	pub const fn extension(&self) -> &'static str {
+		match self {
+			Self::Ron => "ron",
+			Self::Binary => "sav",
+		}
+	}
+
+	/// Format of a save file, going by its extension.
+	pub fn from_path(path: &Path) -> Self {
+		if path.extension().is_some_and(|extension| extension == Self::Ron.extension()) {Self::Ron} else {Self::Binary}
+	}
+
+	/// Path of the save with the specified name in the save directory.
+	pub fn path(&self, name: &str) -> PathBuf {
+		Path::new(SAVE_DIRECTORY).join(name).with_extension(self.extension())
+	}
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+	Io(std::io::Error),
+	/// The data couldn't be encoded or decoded.
+	Format(String),
+	/// The file isn't a save in the expected format.
+	NotASave,
+	/// Saved by a newer version of the game.
+	TooNew(u32),
+	/// Saved by an older version that can't be migrated.
+	UnsupportedVersion(u32),
+}
+impl fmt::Display for SaveError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Io(error) => write!(f, "{}", error),
+			Self::Format(error) => write!(f, "{}", error),
+			Self::NotASave => write!(f, "not a save file"),
+			Self::TooNew(version) => write!(f, "saved by a newer version ({}, this is {})", version, SAVE_VERSION),
+			Self::UnsupportedVersion(version) => write!(f, "saved by an old version ({}) that can't be loaded anymore", version),
+		}
+	}
+}
+
+/// Everything about the resort that is saved.
+/// Entities are saved as their components, and everything else that can be worked out again is left out.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SaveData {
+	/// Comes first, so it can be read before knowing the layout of the rest.
+	pub version: u32,
+	pub clock: GameClock,
+	pub weather: Weather,
+	pub heights: CellHeights,
+	pub surfaces: CellSurfaces,
+	pub snow: CellSnow,
+	pub objects: PlacedObjects,
+	pub economy: Economy,
+	pub loans: Loans,
+	pub prices: TicketPrices,
+	pub season_pass_holders: SeasonPassHolders,
+	pub facility_prices: FacilityPrices,
+	pub guests: Vec<Guest>,
+	pub staff: Vec<StaffMember>,
+	pub flythrough: Flythrough,
+	pub rating: ResortRating,
+	pub avalanches: Avalanches,
+	pub snowmaking: Snowmaking,
+	pub snowmaking_settings: SnowmakingSettings,
+	pub facilities: Facilities,
+	pub grooming_plans: GroomingPlans,
+	pub grooming_settings: GroomingSettings,
+	pub incidents: Incidents,
+}
+
+/// The start of every version of `SaveData`.
+#[derive(Deserialize)]
+struct SaveHeader {
+	version: u32,
+}
+
+pub fn encode(data: &SaveData, format: SaveFormat) -> Result<Vec<u8>, SaveError> {
+	match format {
+		SaveFormat::Ron => ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())
+		.map(String::into_bytes)
+		.map_err(|error| SaveError::Format(error.to_string())),
+		SaveFormat::Binary => {
+			let mut bytes = BINARY_MAGIC.to_vec();
+			bytes.extend(bincode::serde::encode_to_vec(data, bincode::config::standard()).map_err(|error| SaveError::Format(error.to_string()))?);
+			Ok(bytes)
+		},
+	}
+}
+
+/// Decodes the start of the file as `T`, ignoring anything after it.
+fn decode_as<T: DeserializeOwned>(bytes: &[u8], format: SaveFormat) -> Result<T, SaveError> {
+	match format {
+		SaveFormat::Ron => {
+			let text = std::str::from_utf8(bytes).map_err(|_| SaveError::NotASave)?;
+			ron::from_str(text).map_err(|error| SaveError::Format(error.to_string()))
+		},
+		SaveFormat::Binary => {
+			let payload = bytes.strip_prefix(BINARY_MAGIC.as_slice()).ok_or(SaveError::NotASave)?;
+			bincode::serde::decode_from_slice(payload, bincode::config::standard())
+			.map(|(value, _)| value)
+			.map_err(|error| SaveError::Format(error.to_string()))
+		},
+	}
+}
+
+/// Decodes a save file, migrating it if it was saved by an older version.
+pub fn decode(bytes: &[u8], format: SaveFormat) -> Result<SaveData, SaveError> {
+	let SaveHeader {version} = decode_as(bytes, format)?;
+	match version {
+		SAVE_VERSION => decode_as(bytes, format),
+		version if version > SAVE_VERSION => Err(SaveError::TooNew(version)),
+		version => migrate(version, bytes, format),
+	}
+}
+
+/// Migration hook for saves from older versions.
+/// When `SaveData` changes, keep the old layout as `SaveDataV{version}`, decode it here, and convert it into the next version.
+fn migrate(version: u32, _bytes: &[u8], _format: SaveFormat) -> Result<SaveData, SaveError> {
+	// No older versions can be loaded.
+	Err(SaveError::UnsupportedVersion(version))
+}
+
+#[derive(Event, Debug, Clone)]
+/// Saves the resort to the file, in the format going by its extension.
+pub struct SaveResort(pub PathBuf);
+fn save_resort(
+	trigger: Trigger<SaveResort>,
+	(clock, weather, flythrough): (Res<GameClock>, Res<Weather>, Res<Flythrough>),
+	(heights, surfaces, snow, objects): (Res<CellHeights>, Res<CellSurfaces>, Res<CellSnow>, Res<PlacedObjects>),
+	(economy, loans, prices, holders): (Res<Economy>, Res<Loans>, Res<TicketPrices>, Res<SeasonPassHolders>),
+	(facilities, facility_prices, rating, incidents): (Res<Facilities>, Res<FacilityPrices>, Res<ResortRating>, Res<Incidents>),
+	(avalanches, snowmaking, snowmaking_settings): (Res<Avalanches>, Res<Snowmaking>, Res<SnowmakingSettings>),
+	(grooming_plans, grooming_settings): (Res<GroomingPlans>, Res<GroomingSettings>),
+	guests: Query<&Guest>,
+	staff: Query<&StaffMember>,
+) {
+	let path = &trigger.0;
+	let format = SaveFormat::from_path(path);
+	let data = SaveData {
+		version: SAVE_VERSION,
+		clock: *clock,
+		weather: weather.clone(),
+		heights: heights.clone(),
+		surfaces: surfaces.clone(),
+		snow: snow.clone(),
+		objects: objects.clone(),
+		economy: economy.clone(),
+		loans: loans.clone(),
+		prices: *prices,
+		season_pass_holders: *holders,
+		facility_prices: facility_prices.clone(),
+		guests: guests.iter().cloned().collect(),
+		staff: staff.iter().cloned().collect(),
+		flythrough: flythrough.clone(),
+		rating: rating.clone(),
+		avalanches: avalanches.clone(),
+		snowmaking: snowmaking.clone(),
+		snowmaking_settings: snowmaking_settings.clone(),
+		facilities: facilities.clone(),
+		grooming_plans: grooming_plans.clone(),
+		grooming_settings: grooming_settings.clone(),
+		incidents: incidents.clone(),
+	};
+	let bytes = match encode(&data, format) {Ok(bytes) => bytes, Err(error) => {error!("Failed to encode the resort: {}", error); return}};
+	if let Err(error) = path.parent().map_or(Ok(()), fs::create_dir_all) {
+		error!("Failed to create the save directory for {:?}: {}", path, error);
+		return
+	}
+	match fs::write(path, bytes) {
+		Ok(()) => info!("Saved the resort to {:?}.", path),
+		Err(error) => error!("Failed to save the resort to {:?}: {}", path, error),
+	}
+}
+
+#[derive(Event, Debug, Clone)]
+/// Replaces the current resort with the one saved in the file.
+pub struct LoadResort(pub PathBuf);
+fn load_resort(
+	trigger: Trigger<LoadResort>,
+	mut commands: Commands,
+	guests: Query<Entity, With<Guest>>,
+	staff: Query<Entity, With<StaffMember>>,
+) {
+	let path = &trigger.0;
+	let data = match fs::read(path).map_err(SaveError::Io).and_then(|bytes| decode(&bytes, SaveFormat::from_path(path))) {
+		Ok(data) => data,
+		Err(error) => {error!("Failed to load the resort from {:?}: {}", path, error); return}
+	};
+	for entity in guests.iter().chain(staff.iter()) {
+		commands.entity(entity).despawn();
+	}
+	commands.insert_resource(data.clock);
+	commands.insert_resource(data.weather);
+	commands.insert_resource(data.heights);
+	commands.insert_resource(data.surfaces);
+	commands.insert_resource(data.snow);
+	commands.insert_resource(data.objects);
+	commands.insert_resource(data.economy);
+	commands.insert_resource(data.loans);
+	commands.insert_resource(data.prices);
+	commands.insert_resource(data.season_pass_holders);
+	commands.insert_resource(data.facility_prices);
+	commands.insert_resource(data.flythrough);
+	commands.insert_resource(data.rating);
+	commands.insert_resource(data.avalanches);
+	commands.insert_resource(data.snowmaking);
+	commands.insert_resource(data.snowmaking_settings);
+	commands.insert_resource(data.facilities);
+	commands.insert_resource(data.grooming_plans);
+	commands.insert_resource(data.grooming_settings);
+	commands.insert_resource(data.incidents);
+	for guest in data.guests {
+		commands.spawn(guest);
+	}
+	for member in data.staff {
+		commands.spawn(member);
+	}
+	commands.trigger(ResortLoaded);
+	info!("Loaded the resort from {:?}.", path);
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// A save was loaded, so everything built from the saved resources has to be rebuilt.
+pub struct ResortLoaded;
+
+/// F5 saves to the quick save, and F9 loads it.
+fn quick_save_shortcuts(
+	mut commands: Commands,
+	input: Res<ButtonInput<KeyCode>>,
+) {
+	if input.just_pressed(KeyCode::F5) {commands.trigger(SaveResort(SaveFormat::Binary.path(QUICK_SAVE_NAME)))}
+	if input.just_pressed(KeyCode::F9) {commands.trigger(LoadResort(SaveFormat::Binary.path(QUICK_SAVE_NAME)))}
+}
+
+#[cfg(test)]
+mod tests {
+	use hexx::Hex;
+	use super::*;
+	use crate::satisfaction::{Review, Factor};
+	use crate::grid::surface::Surface;
+
+	/// A small resort with something in most of the saved resources.
+	fn sample_data() -> SaveData {
+		let heights = CellHeights(Hex::ZERO.range(3).map(|pos| (pos, (pos.x + 5) as u16)).collect());
+		let mut surfaces = CellSurfaces(heights.0.keys().map(|pos| (*pos, Surface::Piste)).collect());
+		surfaces.0.insert(Hex::new(2, 0), Surface::Water);
+		let mut rating = ResortRating::default();
+		rating.reviews.push_back(Review {day: 2, stars: 4, complaints: vec![Factor::Lifts], praise: Some(Factor::Snow)});
+		let mut avalanches = Avalanches::default();
+		avalanches.risk.insert(Hex::new(1, 1), 0.35);
+		avalanches.closed.insert(Hex::new(0, 1), 2);
+		let mut incidents = Incidents::default();
+		incidents.heatmap.insert(Hex::new(-1, 0), 3);
+		incidents.today.accidents = 3;
+		let snowmaking = Snowmaking::full(&surfaces);
+		SaveData {
+			version: SAVE_VERSION,
+			clock: GameClock::default(),
+			weather: Weather::new(7),
+			heights: heights,
+			surfaces: surfaces,
+			snow: CellSnow::default(),
+			objects: PlacedObjects::default(),
+			economy: Economy::default(),
+			loans: Loans::default(),
+			prices: TicketPrices::default(),
+			season_pass_holders: SeasonPassHolders::default(),
+			facility_prices: FacilityPrices::default(),
+			guests: Vec::new(),
+			staff: Vec::new(),
+			flythrough: Flythrough::default(),
+			rating: rating,
+			avalanches: avalanches,
+			snowmaking: snowmaking,
+			snowmaking_settings: SnowmakingSettings::default(),
+			facilities: Facilities::default(),
+			grooming_plans: GroomingPlans::default(),
+			grooming_settings: GroomingSettings::default(),
+			incidents: incidents,
+		}
+	}
+
+	#[test]
+	fn saves_load_back_the_same() {
+		let data = sample_data();
+		for format in SaveFormat::ALL {
+			let bytes = encode(&data, format).unwrap();
+			assert_eq!(decode(&bytes, format).unwrap(), data, "{} save doesn't load back the same", format.name());
+		}
+	}
+
+	#[test]
+	fn other_versions_are_rejected() {
+		for format in SaveFormat::ALL {
+			let newer = encode(&SaveData {version: SAVE_VERSION + 1, ..sample_data()}, format).unwrap();
+			assert!(matches!(decode(&newer, format), Err(SaveError::TooNew(version)) if version == SAVE_VERSION + 1));
+			let ancient = encode(&SaveData {version: 1, ..sample_data()}, format).unwrap();
+			assert!(matches!(decode(&ancient, format), Err(SaveError::UnsupportedVersion(1))));
+			assert!(decode(b"not a save", format).is_err());
+		}
+	}
+}
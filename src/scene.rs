@@ -1,6 +1,20 @@
 use std::f32::consts::PI;
 use bevy::prelude::*;
 
+use crate::clock::{GameClock, DAYS_PER_YEAR};
+
+pub struct ScenePlugin;
+impl Plugin for ScenePlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(Startup, setup);
+		app.add_systems(Update, update_sun);
+	}
+}
+
+/// The directional light that follows the time of day.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Sun;
+
 pub fn setup(
 	mut commands: Commands,
 	mut gizmo_assets: ResMut<Assets<GizmoAsset>>,
@@ -13,6 +27,7 @@ pub fn setup(
 	});
 
 	commands.spawn((
+		Sun,
 		DirectionalLight {
 			illuminance: light_consts::lux::FULL_DAYLIGHT,
 			shadows_enabled: true,
@@ -21,3 +36,37 @@ pub fn setup(
 		Transform::from_rotation(Quat::from_euler(EulerRot::YXZ, -0.75 * PI, PI / -8., 0.))
 	));
 }
+
+/// Hour when the sun is at its highest.
+const NOON: f32 = 12.5;
+/// Day of the year (counted from November 1st) with the least daylight.
+const WINTER_SOLSTICE: f32 = 51.;
+
+/// Rotation of the sunlight and elevation of the sun (in radians) at the current time.
+/// Days are short and the sun stays low in the winter, and both grow towards spring.
+pub fn sun_rotation(clock: &GameClock) -> (Quat, f32) {
+	// 0 at the winter solstice, 1 at the summer solstice.
+	let summer = (1. - (2. * PI * (clock.day_of_year() as f32 - WINTER_SOLSTICE) / DAYS_PER_YEAR as f32).cos()) / 2.;
+	let daylight_hours = 7. + 9. * summer;
+	let max_elevation = (10. + 45. * summer) * PI / 180.;
+
+	// 0 at sunrise, 1 at sunset.
+	let progress = (clock.hour() - NOON) / daylight_hours + 0.5;
+	let elevation = if (0. ..=1.).contains(&progress) {max_elevation * (PI * progress).sin()} else {0.};
+	/// The sun never sets completely, to avoid lighting the terrain from below.
+	const MIN_ELEVATION: f32 = 0.02;
+	let yaw = -0.75 * PI + (progress.clamp(0., 1.) - 0.5) * 0.8 * PI;
+	(Quat::from_euler(EulerRot::YXZ, yaw, -elevation.max(MIN_ELEVATION), 0.), elevation)
+}
+
+/// Moves the sun across the sky and dims it at night.
+fn update_sun(
+	clock: Res<GameClock>,
+	sun: Single<(&mut Transform, &mut DirectionalLight), With<Sun>>,
+) {
+	let (mut transform, mut light) = sun.into_inner();
+	let (rotation, elevation) = sun_rotation(&clock);
+	transform.rotation = rotation;
+	light.illuminance = (light_consts::lux::FULL_DAYLIGHT * elevation.sin() / (45. * PI / 180_f32).sin())
+	.clamp(light_consts::lux::CIVIL_TWILIGHT, light_consts::lux::FULL_DAYLIGHT);
+}
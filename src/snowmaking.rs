@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+use bevy::{prelude::*, color::palettes::tailwind};
+use hexx::Hex;
+use serde::{Serialize, Deserialize};
+
+use crate::clock::{GameClock, DayStarted};
+use crate::weather::Weather;
+use crate::economy::{Economy, Money, ledger::Category};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::Surface,
+	snow::{CellSnow, SnowQuality, MIN_SKIABLE_DEPTH},
+	geometry::{gizmo::cell_outline, overlay::{AddOverlay, CellColors}},
+	object::{ObjectInstanceId, ObjectInstance, structure::{StructureTypes, StructureRole}},
+};
+
+/// Snow cannons, which are fed with water from the lakes and run on electricity.
+pub struct SnowmakingPlugin;
+impl Plugin for SnowmakingPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<SnowmakingSettings>();
+		app.init_resource::<Snowmaking>();
+		app.add_systems(Startup, fill_reservoir);
+		app.add_systems(Update, (
+			make_snow,
+			draw_coverage.run_if(|settings: Res<SnowmakingSettings>| settings.show_coverage),
+		));
+		app.add_overlay("Snowmaking coverage", "Cells covered by cannons, in the color of the cannon's status.", coverage_overlay);
+		app.add_observer(pay_energy);
+	}
+}
+
+/// How far from a water cell a cannon can be placed and still get water (cells).
+pub const PIPE_RANGE: u32 = 6;
+/// Water stored per lake cell (m³).
+pub const WATER_PER_CELL: f32 = 200.;
+/// Water that flows back into each lake cell per in-game minute (m³).
+pub const REFILL_PER_CELL: f32 = 0.02;
+/// Price of electricity per kWh.
+pub const ENERGY_PRICE: f32 = 0.2;
+
+/// Stats for a type of snow cannon.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SnowCannon {
+	/// Cells within this distance get snow.
+	pub radius: u32,
+	/// Water used per in-game minute (m³).
+	pub water_per_minute: f32,
+	/// Electricity used per in-game minute (kWh).
+	pub energy_per_minute: f32,
+	/// Snow added to every covered cell per in-game minute (cm).
+	pub snow_per_minute: f32,
+	/// The cannon only works below this temperature (°C).
+	pub max_temperature: f32,
+}
+impl SnowCannon {
+	/// All cells covered by a cannon placed at pos.
+	pub fn coverage(&self, pos: Hex) -> impl Iterator<Item = Hex> {pos.range(self.radius)}
+}
+
+#[derive(Resource, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SnowmakingSettings {
+	pub enabled: bool,
+	/// Cannons stop once all their cells are at least this deep (cm).
+	pub target_depth: f32,
+	pub show_coverage: bool,
+}
+impl Default for SnowmakingSettings {
+	fn default() -> Self {Self {enabled: true, target_depth: 60., show_coverage: false}}
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum CannonStatus {
+	Running,
+	/// All covered cells are already deep enough.
+	#[default]
+	Idle,
+	Disabled,
+	TooWarm,
+	/// No lake within `PIPE_RANGE`.
+	NoWater,
+	ReservoirEmpty,
+}
+impl CannonStatus {
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Running => "Running",
+			Self::Idle => "Idle",
+			Self::Disabled => "Disabled",
+			Self::TooWarm => "Too warm",
+			Self::NoWater => "No water",
+			Self::ReservoirEmpty => "Reservoir empty",
+		}
+	}
+
+	pub fn color(&self) -> Color {
+		match self {
+			Self::Running => Color::from(tailwind::GREEN_400),
+			Self::Idle => Color::from(tailwind::SKY_400),
+			Self::Disabled => Color::from(tailwind::GRAY_400),
+			Self::TooWarm => Color::from(tailwind::ORANGE_400),
+			Self::NoWater | Self::ReservoirEmpty => Color::from(tailwind::RED_500),
+		}
+	}
+}
+
+/// Resources used for snowmaking over a day.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct SnowmakingUsage {
+	/// m³.
+	pub water: f32,
+	/// kWh.
+	pub energy: f32,
+	/// Total snow added, summed over all cells (cm).
+	pub snow: f32,
+}
+impl SnowmakingUsage {
+	pub fn energy_cost(&self) -> Money {(self.energy * ENERGY_PRICE).round() as Money}
+}
+
+#[derive(Resource, Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Snowmaking {
+	/// Water currently stored in the lakes (m³).
+	pub reservoir: f32,
+	pub reservoir_capacity: f32,
+	/// Not saved, since it's worked out again every frame.
+	#[serde(skip)]
+	pub statuses: HashMap<ObjectInstanceId, CannonStatus>,
+	pub today: SnowmakingUsage,
+	pub yesterday: SnowmakingUsage,
+}
+impl Snowmaking {
+	/// Reservoir sized after the lakes, and full.
+	pub fn full(surfaces: &CellSurfaces) -> Self {
+		let lake_cells = surfaces.0.values().filter(|surface| **surface == Surface::Water).count();
+		let capacity = lake_cells as f32 * WATER_PER_CELL;
+		Self {reservoir: capacity, reservoir_capacity: capacity, ..default()}
+	}
+}
+
+/// Sizes the reservoir after the lakes in the world.
+fn fill_reservoir(
+	mut snowmaking: ResMut<Snowmaking>,
+	surfaces: Res<CellSurfaces>,
+) {
+	*snowmaking = Snowmaking::full(&surfaces);
+}
+
+/// Whether a cannon placed on pos can be connected to a lake.
+pub fn has_water_supply(surfaces: &HashMap<Hex, Surface>, pos: Hex) -> bool {
+	pos.range(PIPE_RANGE).any(|cell| surfaces.get(&cell) == Some(&Surface::Water))
+}
+
+fn make_snow(
+	clock: Res<GameClock>,
+	weather: Res<Weather>,
+	settings: Res<SnowmakingSettings>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	mut snowmaking: ResMut<Snowmaking>,
+	mut snow: ResMut<CellSnow>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	let lake_cells = snowmaking.reservoir_capacity / WATER_PER_CELL;
+	snowmaking.reservoir = (snowmaking.reservoir + lake_cells * REFILL_PER_CELL * minutes).min(snowmaking.reservoir_capacity);
+
+	let mut statuses = HashMap::new();
+	for (instance_id, object) in objects.iter() {
+		let ObjectInstance::Structure(instance) = object else {continue};
+		let cannon = match structure_types.0.get(&instance.type_id) {
+			Some(structure_type) => match structure_type.role {StructureRole::SnowCannon(cannon) => cannon, _ => continue},
+			None => {error_once!("Structure {:?} has unknown structure type ID {:?}.", instance_id, instance.type_id); continue}
+		};
+		let height = match heights.0.get(&instance.position) {Some(height) => *height, None => {error_once!("Snow cannon {:?} is on cell {:?} which has no height.", instance_id, instance.position); continue}};
+		let cells: Vec<Hex> = cannon.coverage(instance.position)
+		.filter(|pos| surfaces.0.get(pos) != Some(&Surface::Water))
+		.filter(|pos| snow.0.get(pos).is_some_and(|cell| cell.depth < settings.target_depth))
+		.collect();
+
+		let water = cannon.water_per_minute * minutes;
+		let status = if !settings.enabled {CannonStatus::Disabled}
+		else if !has_water_supply(&surfaces.0, instance.position) {CannonStatus::NoWater}
+		else if weather.temperature_at(height, clock.minute) > cannon.max_temperature {CannonStatus::TooWarm}
+		else if cells.is_empty() {CannonStatus::Idle}
+		else if snowmaking.reservoir < water {CannonStatus::ReservoirEmpty}
+		else {CannonStatus::Running};
+		statuses.insert(*instance_id, status);
+		if status != CannonStatus::Running {continue}
+
+		snowmaking.reservoir -= water;
+		snowmaking.today.water += water;
+		snowmaking.today.energy += cannon.energy_per_minute * minutes;
+		for pos in cells {
+			let Some(cell) = snow.0.get_mut(&pos) else {continue};
+			let added = (cannon.snow_per_minute * minutes).min(settings.target_depth - cell.depth);
+			cell.depth += added;
+			// Machine made snow is dense, and covers up ice, slush and bare ground, but spoils nothing better.
+			if matches!(cell.quality, SnowQuality::Icy | SnowQuality::Slush | SnowQuality::Bare) {cell.quality = SnowQuality::Packed}
+			snowmaking.today.snow += added;
+		}
+	}
+	snowmaking.statuses = statuses;
+}
+
+/// Pays for the electricity used yesterday.
+fn pay_energy(
+	_trigger: Trigger<DayStarted>,
+	mut snowmaking: ResMut<Snowmaking>,
+	mut economy: ResMut<Economy>,
+) {
+	let cost = snowmaking.today.energy_cost();
+	if cost > 0 {
+		economy.charge(Category::SnowmakingEnergy, cost);
+	}
+	snowmaking.yesterday = snowmaking.today;
+	snowmaking.today = SnowmakingUsage::default();
+}
+
+/// Outlines the cells covered by each cannon, colored by the status of the cannon.
+/// Where cannons overlap, running ones win, so the overlay shows which cells are getting snow.
+fn coverage_overlay(
+	snowmaking: Res<Snowmaking>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+) -> CellColors {
+	let mut statuses: HashMap<Hex, CannonStatus> = HashMap::new();
+	for (instance_id, object) in objects.iter() {
+		let ObjectInstance::Structure(instance) = object else {continue};
+		let Some(StructureRole::SnowCannon(cannon)) = structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.role) else {continue};
+		let status = snowmaking.statuses.get(instance_id).copied().unwrap_or_default();
+		for pos in cannon.coverage(instance.position) {
+			let shown = statuses.entry(pos).or_insert(status);
+			if status == CannonStatus::Running {*shown = status}
+		}
+	}
+	statuses.into_iter().map(|(pos, status)| (pos, status.color().to_linear())).collect()
+}
+
+fn draw_coverage(
+	mut gizmos: Gizmos,
+	snowmaking: Res<Snowmaking>,
+	heights: Res<CellHeights>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+) {
+	for (instance_id, object) in objects.iter() {
+		let ObjectInstance::Structure(instance) = object else {continue};
+		let Some(StructureRole::SnowCannon(cannon)) = structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.role) else {continue};
+		let color = snowmaking.statuses.get(instance_id).copied().unwrap_or_default().color();
+		for pos in cannon.coverage(instance.position) {
+			cell_outline(&mut *gizmos, &heights.0, &pos, color);
+		}
+	}
+}
+
+/// What it would take to make every cell covered by a cannon skiable.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct SkiableEstimate {
+	pub covered_cells: usize,
+	/// In-game hours of running all cannons that can get water.
+	pub hours: f32,
+	pub energy_cost: Money,
+	/// m³.
+	pub water: f32,
+}
+
+/// Estimates the running time and cost to bring all covered cells up to `MIN_SKIABLE_DEPTH`, assuming it's cold enough.
+pub fn skiable_estimate(
+	snow: &CellSnow,
+	surfaces: &HashMap<Hex, Surface>,
+	objects: &PlacedObjects,
+	structure_types: &StructureTypes,
+) -> SkiableEstimate {
+	let mut estimate = SkiableEstimate::default();
+	let mut covered = HashSet::new();
+	for (_, object) in objects.iter() {
+		let ObjectInstance::Structure(instance) = object else {continue};
+		let Some(StructureRole::SnowCannon(cannon)) = structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.role) else {continue};
+		if !has_water_supply(surfaces, instance.position) {continue}
+		let missing = cannon.coverage(instance.position)
+		.filter(|pos| surfaces.get(pos) != Some(&Surface::Water))
+		.filter_map(|pos| snow.0.get(&pos).map(|cell| (MIN_SKIABLE_DEPTH - cell.depth).max(0.)))
+		.fold(0_f32, f32::max);
+		covered.extend(cannon.coverage(instance.position));
+		let minutes = missing / cannon.snow_per_minute;
+		estimate.hours = estimate.hours.max(minutes / 60.);
+		estimate.energy_cost += (minutes * cannon.energy_per_minute * ENERGY_PRICE).round() as Money;
+		estimate.water += minutes * cannon.water_per_minute;
+	}
+	estimate.covered_cells = covered.len();
+	estimate
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grid::{
+		snow::SnowCell,
+		object::{BuildCategory, structure::{StructureType, StructureInstance, StructureTypeId, Footprint}},
+	};
+
+	const CANNON: SnowCannon = SnowCannon {radius: 2, water_per_minute: 0.5, energy_per_minute: 2., snow_per_minute: 0.05, max_temperature: -2.};
+
+	fn structure_types() -> StructureTypes {
+		StructureTypes(HashMap::from([(StructureTypeId(301), StructureType {
+			name: "Snow Cannon",
+			scene: Handle::default(),
+			footprint: Footprint::single(2),
+			has_rotation: false,
+			cost: 0,
+			upkeep: 0,
+			category: BuildCategory::Infrastructure,
+			role: StructureRole::SnowCannon(CANNON),
+		})]))
+	}
+
+	/// Flat ground with a lake at the edge, a cannon in the middle, and thin snow that's icy in places.
+	fn resort(temperature: f32, lake: bool) -> World {
+		let mut world = World::new();
+		let heights = CellHeights(Hex::ZERO.range(5).map(|pos| (pos, 0)).collect());
+		let mut surfaces = CellSurfaces(heights.0.keys().map(|pos| (*pos, Surface::None)).collect());
+		if lake {surfaces.0.insert(Hex::new(5, 0), Surface::Water);}
+		let mut snow = CellSnow(heights.0.keys().map(|pos| (*pos, SnowCell {depth: 10., quality: SnowQuality::Icy, ..default()})).collect());
+		snow.0.insert(Hex::new(1, 0), SnowCell {depth: 10., quality: SnowQuality::Powder, ..default()});
+		let mut objects = PlacedObjects::default();
+		objects.push(ObjectInstance::Structure(StructureInstance {type_id: StructureTypeId(301), position: Hex::ZERO, rotation: None}));
+		let mut weather = Weather::new(1);
+		weather.today.temperature = temperature;
+		world.insert_resource(GameClock {delta_minutes: 10., ..default()});
+		world.insert_resource(weather);
+		world.insert_resource(SnowmakingSettings::default());
+		world.insert_resource(Snowmaking::full(&surfaces));
+		world.insert_resource(heights);
+		world.insert_resource(surfaces);
+		world.insert_resource(snow);
+		world.insert_resource(objects);
+		world.insert_resource(structure_types());
+		world
+	}
+
+	fn run(world: &mut World) -> CannonStatus {
+		let mut schedule = Schedule::default();
+		schedule.add_systems(make_snow);
+		schedule.run(world);
+		world.resource::<Snowmaking>().statuses[&ObjectInstanceId(0)]
+	}
+
+	#[test]
+	fn cannons_cover_up_bad_snow() {
+		let mut world = resort(-20., true);
+		assert_eq!(run(&mut world), CannonStatus::Running);
+		let snow = world.resource::<CellSnow>();
+		assert_eq!(snow.0[&Hex::new(0, 1)].quality, SnowQuality::Packed, "Ice isn't covered up.");
+		assert_eq!(snow.0[&Hex::new(1, 0)].quality, SnowQuality::Powder, "Powder is spoiled.");
+		assert_eq!(snow.0[&Hex::new(1, 0)].depth, 10. + CANNON.snow_per_minute * 10.);
+		assert_eq!(snow.0[&Hex::new(4, 0)].quality, SnowQuality::Icy, "Cells out of reach get snow.");
+		assert_eq!(world.resource::<Snowmaking>().today.water, CANNON.water_per_minute * 10.);
+	}
+
+	#[test]
+	fn cannons_only_run_when_they_can() {
+		assert_eq!(run(&mut resort(10., true)), CannonStatus::TooWarm);
+		assert_eq!(run(&mut resort(-20., false)), CannonStatus::NoWater);
+		let mut empty = resort(-20., true);
+		empty.insert_resource(Snowmaking::default());
+		assert_eq!(run(&mut empty), CannonStatus::ReservoirEmpty);
+		let mut deep = resort(-20., true);
+		deep.resource_mut::<SnowmakingSettings>().target_depth = 10.;
+		assert_eq!(run(&mut deep), CannonStatus::Idle);
+		assert_eq!(deep.resource::<Snowmaking>().today, SnowmakingUsage::default());
+		let mut disabled = resort(-20., true);
+		disabled.resource_mut::<SnowmakingSettings>().enabled = false;
+		assert_eq!(run(&mut disabled), CannonStatus::Disabled);
+	}
+
+	#[test]
+	fn estimate_covers_the_missing_snow() {
+		let world = resort(-20., true);
+		let estimate = skiable_estimate(world.resource::<CellSnow>(), &world.resource::<CellSurfaces>().0, world.resource::<PlacedObjects>(), &structure_types());
+		let minutes = (MIN_SKIABLE_DEPTH - 10.) / CANNON.snow_per_minute;
+		assert_eq!(estimate.covered_cells, Hex::ZERO.range(CANNON.radius).count());
+		assert!((estimate.hours - minutes / 60.).abs() < 1e-4);
+		assert_eq!(estimate.energy_cost, (minutes * CANNON.energy_per_minute * ENERGY_PRICE).round() as Money);
+		assert!((estimate.water - minutes * CANNON.water_per_minute).abs() < 1e-3);
+		let dry = resort(-20., false);
+		assert_eq!(skiable_estimate(dry.resource::<CellSnow>(), &dry.resource::<CellSurfaces>().0, dry.resource::<PlacedObjects>(), &structure_types()), SkiableEstimate::default());
+	}
+}
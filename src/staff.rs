@@ -0,0 +1,485 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use bevy::{prelude::*, color::palettes::tailwind};
+use hexx::{Hex, algorithms::a_star};
+use serde::{Serialize, Deserialize};
+
+use crate::clock::{GameClock, DayStarted, OPENING_TIME, CLOSING_TIME};
+use crate::economy::{Economy, Money, ledger::Category};
+use crate::guest::Need;
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::Surface,
+	geometry::gizmo::cell_top,
+	object::{ObjectInstanceId, ObjectInstance, structure::{StructureTypes, StructureRole}},
+};
+
+/// Staff that are hired, paid and assigned to lifts and structures, and walk between their quarters and their workplace.
+pub struct StaffPlugin;
+impl Plugin for StaffPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Staffing>();
+		app.add_systems(Startup, load_assets);
+		app.add_systems(Update, (
+			walk_to_work,
+			update_staffing,
+			staff_lifts,
+		).chain());
+		app.add_observer(add_staff_mesh);
+		app.add_observer(hire_staff);
+		app.add_observer(fire_staff);
+		app.add_observer(assign_staff);
+		app.add_observer(pay_wages);
+	}
+}
+
+/// Cells walked per in-game minute.
+pub const WALKING_SPEED: f32 = 2.;
+/// Staff can't walk up or down height differences larger than this between neighboring cells.
+pub const MAX_STEP: u16 = 3;
+/// Number of guests that one instructor can teach at once at a ski school.
+pub const LESSON_SIZE: usize = 6;
+/// Skill gained per in-game minute of lessons.
+pub const SKILL_PER_LESSON_MINUTE: f32 = 0.002;
+/// Ski patrol covers cells within this distance from their hut.
+pub const PATROL_RADIUS: u32 = 8;
+/// Injuries on patrolled cells are this much less severe.
+pub const PATROL_SEVERITY_FACTOR: f32 = 0.5;
+/// Tickets sold per in-game minute by each cashier on duty.
+pub const TICKETS_PER_CASHIER_MINUTE: f32 = 1.;
+/// Tickets sold per in-game minute by the ticket machines, which need no staff.
+pub const TICKET_MACHINE_RATE: f32 = 0.5;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum StaffRole {
+	LiftOperator,
+	SkiPatrol,
+	Instructor,
+	Cashier,
+	SnowcatDriver,
+}
+impl StaffRole {
+	pub const ALL: [Self; 5] = [Self::LiftOperator, Self::SkiPatrol, Self::Instructor, Self::Cashier, Self::SnowcatDriver];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::LiftOperator => "Lift operator",
+			Self::SkiPatrol => "Ski patrol",
+			Self::Instructor => "Instructor",
+			Self::Cashier => "Cashier",
+			Self::SnowcatDriver => "Snowcat driver",
+		}
+	}
+
+	/// Wage for a full day shift.
+	pub const fn daily_wage(&self) -> Money {
+		match self {
+			Self::LiftOperator => 140,
+			Self::SkiPatrol => 200,
+			Self::Instructor => 180,
+			Self::Cashier => 120,
+			Self::SnowcatDriver => 180,
+		}
+	}
+
+	/// Whether staff with this role can work at the object.
+	pub fn can_work_at(&self, object: &ObjectInstance, structure_types: &StructureTypes) -> bool {
+		match object {
+			ObjectInstance::Lift(_) => *self == Self::LiftOperator,
+			ObjectInstance::Structure(instance) => match structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.role) {
+				Some(StructureRole::PatrolHut) => *self == Self::SkiPatrol,
+				Some(StructureRole::Facility(facility)) if facility.need == Need::Lessons => *self == Self::Instructor,
+				Some(StructureRole::TicketOffice) => *self == Self::Cashier,
+				Some(StructureRole::SnowcatGarage(_)) => *self == Self::SnowcatDriver,
+				_ => false,
+			},
+		}
+	}
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Shift {
+	/// From before opening until early afternoon.
+	Early,
+	/// From late morning until after closing.
+	Late,
+	#[default]
+	Full,
+	/// From after closing until before opening the next morning, when the snowcats are out.
+	Night,
+}
+impl Shift {
+	pub const ALL: [Self; 4] = [Self::Early, Self::Late, Self::Full, Self::Night];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Early => "Early",
+			Self::Late => "Late",
+			Self::Full => "Full day",
+			Self::Night => "Night",
+		}
+	}
+
+	/// First and last minute of the day that the shift covers.
+	/// A shift that ends before it starts goes on past midnight.
+	pub const fn hours(&self) -> (f32, f32) {
+		match self {
+			Self::Early => (OPENING_TIME - 60., 13. * 60.),
+			Self::Late => (12. * 60., CLOSING_TIME + 60.),
+			Self::Full => (OPENING_TIME - 60., CLOSING_TIME + 60.),
+			Self::Night => (CLOSING_TIME + 60., OPENING_TIME - 60.),
+		}
+	}
+
+	pub fn contains(&self, minute: f32) -> bool {
+		let (start, end) = self.hours();
+		if start <= end {minute >= start && minute < end}
+		else {minute >= start || minute < end}
+	}
+
+	/// Length of the shift in minutes.
+	pub fn minutes(&self) -> f32 {
+		let (start, end) = self.hours();
+		(end - start).rem_euclid(24. * 60.)
+	}
+
+	/// Wage for the shift, where a full day is paid the whole daily wage.
+	pub fn wage(&self, role: StaffRole) -> Money {
+		(role.daily_wage() as f32 * self.minutes() / Self::Full.minutes()).round() as Money
+	}
+}
+
+#[derive(Component, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StaffMember {
+	pub role: StaffRole,
+	pub shift: Shift,
+	/// The staff quarters where they live.
+	pub quarters: ObjectInstanceId,
+	pub workplace: Option<ObjectInstanceId>,
+	pub position: Hex,
+	/// Cells left to walk through, not including `position`.
+	pub path: VecDeque<Hex>,
+	/// Progress towards the next cell in the path, from 0 to 1.
+	pub progress: f32,
+	/// At the workplace during the shift.
+	pub on_duty: bool,
+	/// Somewhere they have been called away to from their workplace, such as an injured guest.
+	pub errand: Option<Hex>,
+}
+impl StaffMember {
+	pub fn status(&self) -> &'static str {
+		if self.errand.is_some() {"On an errand"}
+		else if self.on_duty {"Working"}
+		else if !self.path.is_empty() {"Walking"}
+		else if self.workplace.is_none() {"Unassigned"}
+		else {"Off duty"}
+	}
+}
+
+/// What the staff on duty currently cover.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct Staffing {
+	/// Lifts with an operator on duty.
+	pub operated_lifts: HashSet<ObjectInstanceId>,
+	/// Cells within reach of a patrol hut with ski patrol on duty.
+	pub patrolled: HashSet<Hex>,
+	/// Instructors on duty at each ski school.
+	pub instructors: HashMap<ObjectInstanceId, usize>,
+	pub cashiers: usize,
+	/// Snowcat drivers on duty at each garage.
+	pub drivers: HashMap<ObjectInstanceId, usize>,
+}
+impl Staffing {
+	/// How severe an injury on the cell is, relative to an unpatrolled cell.
+	pub fn injury_severity_factor(&self, pos: &Hex) -> f32 {
+		if self.patrolled.contains(pos) {PATROL_SEVERITY_FACTOR} else {1.}
+	}
+
+	/// Lift tickets that can be sold per in-game minute.
+	pub fn ticket_sales_rate(&self) -> f32 {
+		TICKET_MACHINE_RATE + self.cashiers as f32 * TICKETS_PER_CASHIER_MINUTE
+	}
+}
+
+#[derive(Resource, Debug, Clone)]
+struct StaffAssets {
+	mesh: Handle<Mesh>,
+	materials: HashMap<StaffRole, Handle<StandardMaterial>>,
+}
+
+fn load_assets(
+	mut commands: Commands,
+	mut mesh_assets: ResMut<Assets<Mesh>>,
+	mut material_assets: ResMut<Assets<StandardMaterial>>,
+) {
+	commands.insert_resource(StaffAssets {
+		mesh: mesh_assets.add(Capsule3d::new(0.12, 0.3)),
+		materials: HashMap::from(StaffRole::ALL.map(|role| (role, material_assets.add(Color::from(match role {
+			StaffRole::LiftOperator => tailwind::YELLOW_500,
+			StaffRole::SkiPatrol => tailwind::RED_600,
+			StaffRole::Instructor => tailwind::SKY_500,
+			StaffRole::Cashier => tailwind::EMERALD_500,
+			StaffRole::SnowcatDriver => tailwind::ORANGE_500,
+		}))))),
+	});
+}
+
+/// Where the object can be reached on foot, or None if it doesn't exist.
+pub fn object_location(objects: &PlacedObjects, instance_id: &ObjectInstanceId) -> Option<Hex> {
+	match objects.get(instance_id)? {
+		ObjectInstance::Structure(instance) => Some(instance.position),
+		ObjectInstance::Lift(lift) => Some(lift.bottom),
+	}
+}
+
+/// Cells to walk through from start to end, not including start.
+/// Water and steep steps can't be walked.
+pub fn walking_path(heights: &HashMap<Hex, u16>, surfaces: &HashMap<Hex, Surface>, start: Hex, end: Hex) -> Option<VecDeque<Hex>> {
+	let path = a_star(start, end, |from, to| {
+		if surfaces.get(&to) == Some(&Surface::Water) {return None}
+		let step = heights.get(&from)?.abs_diff(*heights.get(&to)?);
+		if step > MAX_STEP {None} else {Some(1 + step as u32)}
+	})?;
+	Some(path.into_iter().skip(1).collect())
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Hires a staff member into the first staff quarters with a free bed.
+pub struct HireStaff {
+	pub role: StaffRole,
+	pub shift: Shift,
+}
+fn hire_staff(
+	trigger: Trigger<HireStaff>,
+	mut commands: Commands,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	staff: Query<&StaffMember>,
+) {
+	let HireStaff {role, shift} = *trigger;
+	let mut quarters: Vec<(ObjectInstanceId, Hex, u32)> = objects.iter().filter_map(|(instance_id, object)| {
+		let ObjectInstance::Structure(instance) = object else {return None};
+		match structure_types.0.get(&instance.type_id)?.role {
+			StructureRole::StaffQuarters(beds) => Some((*instance_id, instance.position, beds)),
+			_ => None,
+		}
+	}).collect();
+	quarters.sort_by_key(|(instance_id, _, _)| *instance_id);
+	let free = quarters.into_iter().find(|(instance_id, _, beds)| staff.iter().filter(|member| member.quarters == *instance_id).count() < *beds as usize);
+	let (quarters, position) = match free {Some((instance_id, position, _)) => (instance_id, position), None => {warn!("Can't hire a {} without a free bed in any staff quarters.", role.name()); return}};
+	commands.spawn(StaffMember {role: role, shift: shift, quarters: quarters, workplace: None, position: position, path: VecDeque::new(), progress: 0., on_duty: false, errand: None});
+}
+
+/// Gives new staff members, whether hired or loaded, a body in the world.
+fn add_staff_mesh(
+	trigger: Trigger<OnAdd, StaffMember>,
+	mut commands: Commands,
+	assets: Res<StaffAssets>,
+	heights: Res<CellHeights>,
+	staff: Query<&StaffMember>,
+) {
+	let entity = trigger.target();
+	let member = match staff.get(entity) {Ok(member) => member, Err(_) => {error!("Added staff member {:?} is missing.", entity); return}};
+	let translation = match cell_top(&heights.0, &member.position) {Some(translation) => translation, None => {error!("Staff member {:?} is on cell {:?} which has no height.", entity, member.position); return}};
+	commands.entity(entity).insert((
+		Mesh3d(assets.mesh.clone()),
+		MeshMaterial3d(assets.materials[&member.role].clone()),
+		Transform::from_translation(translation + Vec3::Y * 0.27),
+	));
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FireStaff(pub Entity);
+fn fire_staff(
+	trigger: Trigger<FireStaff>,
+	mut commands: Commands,
+	staff: Query<(), With<StaffMember>>,
+) {
+	if !staff.contains(trigger.0) {
+		warn!("Attempted to fire {:?}, who isn't a staff member.", trigger.0);
+		return
+	}
+	commands.entity(trigger.0).despawn();
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Sends a staff member to work at a lift or structure, or unassigns them with None.
+pub struct AssignStaff {
+	pub staff: Entity,
+	pub workplace: Option<ObjectInstanceId>,
+}
+fn assign_staff(
+	trigger: Trigger<AssignStaff>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	mut staff: Query<&mut StaffMember>,
+) {
+	let AssignStaff {staff: entity, workplace} = *trigger;
+	let mut member = match staff.get_mut(entity) {Ok(member) => member, Err(_) => {warn!("Attempted to assign {:?}, who isn't a staff member.", entity); return}};
+	if let Some(instance_id) = workplace {
+		let object = match objects.get(&instance_id) {Some(object) => object, None => {warn!("Attempted to assign staff to unknown object {:?}.", instance_id); return}};
+		if !member.role.can_work_at(object, &structure_types) {
+			warn!("A {} can't work at object {:?}.", member.role.name(), instance_id);
+			return
+		}
+	}
+	member.workplace = workplace;
+	// The current path may lead to the old workplace.
+	member.path.clear();
+}
+
+/// Walks staff to their workplace during their shift, and back to their quarters after it.
+fn walk_to_work(
+	mut commands: Commands,
+	clock: Res<GameClock>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	mut staff: Query<(Entity, &mut StaffMember, &mut Transform)>,
+) {
+	let minutes = clock.delta_minutes;
+	if minutes <= 0. {return}
+	for (entity, mut member, mut transform) in staff.iter_mut() {
+		let home = match object_location(&objects, &member.quarters) {Some(home) => home, None => {warn!("Staff member {:?} lost their quarters, and quits.", entity); commands.entity(entity).despawn(); continue}};
+		let workplace = member.workplace.and_then(|instance_id| object_location(&objects, &instance_id));
+		if member.workplace.is_some() && workplace.is_none() {
+			// The workplace was removed.
+			member.workplace = None;
+		}
+		let target = match (member.errand, workplace) {
+			(Some(errand), _) => errand,
+			(None, Some(workplace)) if member.shift.contains(clock.minute) => workplace,
+			_ => home,
+		};
+
+		if member.path.back().copied().unwrap_or(member.position) != target {
+			member.progress = 0.;
+			match walking_path(&heights.0, &surfaces.0, member.position, target) {
+				Some(path) => member.path = path,
+				None => {
+					// Taking the long way round, which isn't simulated.
+					warn_once!("Staff member {:?} can't walk from {:?} to {:?}.", entity, member.position, target);
+					member.path.clear();
+					member.position = target;
+				},
+			}
+		}
+
+		// Staff standing still aren't touched, so `update_staffing` only runs when something changed.
+		if !member.path.is_empty() {
+			member.progress += WALKING_SPEED * minutes;
+			while member.progress >= 1. {
+				let Some(next) = member.path.pop_front() else {member.progress = 0.; break};
+				member.progress -= 1.;
+				member.position = next;
+			}
+		}
+		let on_duty = workplace == Some(member.position) && member.shift.contains(clock.minute);
+		if member.on_duty != on_duty {
+			member.on_duty = on_duty;
+		}
+
+		let from = cell_top(&heights.0, &member.position).unwrap_or(transform.translation);
+		let to = member.path.front().and_then(|next| cell_top(&heights.0, next)).unwrap_or(from);
+		transform.translation = from.lerp(to, member.progress) + Vec3::Y * 0.27;
+	}
+}
+
+/// Works out what the staff on duty cover, whenever staff or objects changed.
+/// Only the parts that differ are replaced.
+fn update_staffing(
+	objects: Res<PlacedObjects>,
+	mut staffing: ResMut<Staffing>,
+	staff: Query<&StaffMember>,
+	changed: Query<(), Changed<StaffMember>>,
+	mut removed: RemovedComponents<StaffMember>,
+) {
+	let fired = removed.read().count() > 0;
+	if !fired && changed.is_empty() && !objects.is_changed() {return}
+	let mut operated_lifts: HashSet<ObjectInstanceId> = HashSet::new();
+	let mut patrolled: HashSet<Hex> = HashSet::new();
+	let mut instructors: HashMap<ObjectInstanceId, usize> = HashMap::new();
+	let mut cashiers = 0;
+	let mut drivers: HashMap<ObjectInstanceId, usize> = HashMap::new();
+	for member in staff.iter().filter(|member| member.on_duty) {
+		let Some(workplace) = member.workplace else {continue};
+		match member.role {
+			StaffRole::LiftOperator => {operated_lifts.insert(workplace);},
+			StaffRole::SkiPatrol => if let Some(hut) = object_location(&objects, &workplace) {
+				patrolled.extend(hut.range(PATROL_RADIUS));
+			},
+			StaffRole::Instructor => *instructors.entry(workplace).or_default() += 1,
+			StaffRole::Cashier => cashiers += 1,
+			StaffRole::SnowcatDriver => *drivers.entry(workplace).or_default() += 1,
+		}
+	}
+	if staffing.operated_lifts != operated_lifts {staffing.operated_lifts = operated_lifts}
+	if staffing.patrolled != patrolled {staffing.patrolled = patrolled}
+	if staffing.instructors != instructors {staffing.instructors = instructors}
+	if staffing.cashiers != cashiers {staffing.cashiers = cashiers}
+	if staffing.drivers != drivers {staffing.drivers = drivers}
+}
+
+/// Stops lifts without an operator, and starts them once one shows up.
+fn staff_lifts(
+	staffing: Res<Staffing>,
+	mut objects: ResMut<PlacedObjects>,
+) {
+	// Only lifts that change are mutated, to avoid triggering change detection every time.
+	let changed: Vec<(ObjectInstanceId, bool)> = objects.iter().filter_map(|(instance_id, object)| {
+		let ObjectInstance::Lift(lift) = object else {return None};
+		let unstaffed = !staffing.operated_lifts.contains(instance_id);
+		if lift.unstaffed != unstaffed {Some((*instance_id, unstaffed))} else {None}
+	}).collect();
+	for (instance_id, unstaffed) in changed {
+		if let Some(ObjectInstance::Lift(lift)) = objects.get_mut(&instance_id) {
+			lift.unstaffed = unstaffed;
+		}
+	}
+}
+
+fn pay_wages(
+	_trigger: Trigger<DayStarted>,
+	mut economy: ResMut<Economy>,
+	staff: Query<&StaffMember>,
+) {
+	let wages: Money = staff.iter().map(|member| member.shift.wage(member.role)).sum();
+	if wages > 0 {
+		economy.charge(Category::Staff, wages);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn night_shift_goes_past_midnight() {
+		assert!(Shift::Night.contains(CLOSING_TIME + 120.));
+		assert!(Shift::Night.contains(60.));
+		assert!(!Shift::Night.contains(12. * 60.));
+		assert_eq!(Shift::Night.minutes(), 24. * 60. - (CLOSING_TIME - OPENING_TIME) - 120.);
+		assert_eq!(Shift::Full.wage(StaffRole::SnowcatDriver), StaffRole::SnowcatDriver.daily_wage());
+		assert!(Shift::Night.wage(StaffRole::SnowcatDriver) > 0);
+	}
+
+	#[test]
+	fn staffing_only_changes_with_the_staff() {
+		let mut world = World::new();
+		world.init_resource::<PlacedObjects>();
+		world.init_resource::<Staffing>();
+		let mut schedule = Schedule::default();
+		schedule.add_systems(update_staffing);
+		let cashier = world.spawn(StaffMember {
+			role: StaffRole::Cashier, shift: Shift::Full, quarters: ObjectInstanceId(0), workplace: Some(ObjectInstanceId(1)),
+			position: Hex::ZERO, path: VecDeque::new(), progress: 0., on_duty: true, errand: None,
+		}).id();
+		schedule.run(&mut world);
+		assert_eq!(world.resource::<Staffing>().cashiers, 1);
+		world.clear_trackers();
+		schedule.run(&mut world);
+		assert!(!world.is_resource_changed::<Staffing>());
+		world.get_mut::<StaffMember>(cashier).unwrap().on_duty = false;
+		schedule.run(&mut world);
+		assert_eq!(world.resource::<Staffing>().cashiers, 0);
+	}
+}
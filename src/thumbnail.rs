@@ -0,0 +1,193 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+use bevy::{
+	prelude::*,
+	render::{
+		camera::RenderTarget,
+		primitives::Aabb,
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+		view::{RenderLayers, screenshot::{Screenshot, ScreenshotCaptured}},
+	},
+};
+
+use crate::grid::object::structure::{StructureTypeId, StructureTypes};
+
+/// Pictures of every structure type for the build menu, rendered from their scenes away from the resort.
+/// Rendered thumbnails are saved to disk, and loaded from there the next time the game starts.
+pub struct ThumbnailPlugin;
+impl Plugin for ThumbnailPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<Thumbnails>();
+		app.insert_resource(ThumbnailSettings::from_args());
+		app.add_systems(Startup, setup);
+		app.add_systems(Update, (shoot_thumbnails, exit_when_done));
+	}
+}
+
+/// Width and height of a thumbnail, in pixels.
+pub const THUMBNAIL_SIZE: u32 = 128;
+/// Directory where rendered thumbnails are cached, inside the assets directory.
+/// Delete it to render them again, after changing a scene.
+pub const THUMBNAIL_DIRECTORY: &str = "thumbnails";
+/// Render layer that only the thumbnail cameras see.
+const THUMBNAIL_LAYER: usize = 7;
+/// Far below the terrain, so the scenes can't be seen or picked from the resort.
+const STUDIO_ORIGIN: Vec3 = Vec3::new(0., -1000., 0.);
+/// Space between the scenes being rendered at the same time.
+const STUDIO_SPACING: f32 = 50.;
+/// Frames to wait after the scene's meshes show up before capturing it, so it's lit and framed.
+const SETTLE_FRAMES: u32 = 3;
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ThumbnailSettings {
+	/// Renders every thumbnail again, even if it's cached.
+	pub refresh: bool,
+	/// Quits once every thumbnail is saved, for making them without playing.
+	pub exit_when_done: bool,
+}
+impl ThumbnailSettings {
+	/// `--thumbnails` renders and saves every thumbnail, and quits.
+	/// Run it with a software renderer to make them on a machine without a GPU.
+	pub fn from_args() -> Self {
+		let only_thumbnails = std::env::args().any(|arg| arg == "--thumbnails");
+		Self {refresh: only_thumbnails, exit_when_done: only_thumbnails}
+	}
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Thumbnails {
+	pub images: HashMap<StructureTypeId, Handle<Image>>,
+	/// Thumbnails that are still being rendered or saved.
+	pub pending: usize,
+}
+
+/// Path of the cached thumbnail, relative to the assets directory.
+pub fn cache_path(type_id: StructureTypeId) -> PathBuf {
+	Path::new(THUMBNAIL_DIRECTORY).join(format!("structure_{}.png", type_id.0))
+}
+
+/// A scene being photographed for a thumbnail.
+#[derive(Component, Debug, Clone)]
+struct Shoot {
+	type_id: StructureTypeId,
+	camera: Entity,
+	image: Handle<Image>,
+	frames_left: u32,
+}
+
+fn setup(
+	mut commands: Commands,
+	mut thumbnails: ResMut<Thumbnails>,
+	mut images: ResMut<Assets<Image>>,
+	asset_server: Res<AssetServer>,
+	settings: Res<ThumbnailSettings>,
+	structure_types: Res<StructureTypes>,
+) {
+	let mut type_ids: Vec<&StructureTypeId> = structure_types.0.keys().collect();
+	type_ids.sort();
+	let mut studio = 0;
+	for type_id in type_ids {
+		let path = cache_path(*type_id);
+		if !settings.refresh && Path::new("assets").join(&path).exists() {
+			thumbnails.images.insert(*type_id, asset_server.load(path));
+			continue
+		}
+		let size = Extent3d {width: THUMBNAIL_SIZE, height: THUMBNAIL_SIZE, depth_or_array_layers: 1};
+		let mut image = Image::new_fill(size, TextureDimension::D2, &[0, 0, 0, 0], TextureFormat::Rgba8UnormSrgb, RenderAssetUsages::default());
+		image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT;
+		let image = images.add(image);
+		let position = STUDIO_ORIGIN + Vec3::X * STUDIO_SPACING * studio as f32;
+		studio += 1;
+		let camera = commands.spawn((
+			Camera3d::default(),
+			Camera {
+				target: RenderTarget::Image(image.clone().into()),
+				clear_color: ClearColorConfig::Custom(Color::NONE),
+				order: -1,
+				..default()
+			},
+			Transform::from_translation(position + Vec3::ONE * 5.).looking_at(position, Vec3::Y),
+			RenderLayers::layer(THUMBNAIL_LAYER),
+		)).id();
+		commands.spawn((
+			Shoot {type_id: *type_id, camera: camera, image: image.clone(), frames_left: SETTLE_FRAMES},
+			SceneRoot(structure_types.0[type_id].scene.clone()),
+			Transform::from_translation(position),
+			RenderLayers::layer(THUMBNAIL_LAYER),
+		));
+		thumbnails.images.insert(*type_id, image);
+		thumbnails.pending += 1;
+	}
+	if thumbnails.pending > 0 {
+		commands.spawn((
+			DirectionalLight::default(),
+			Transform::from_xyz(1., 2., 0.5).looking_at(Vec3::ZERO, Vec3::Y),
+			RenderLayers::layer(THUMBNAIL_LAYER),
+		));
+	}
+}
+
+/// Moves the scene's meshes onto the thumbnail layer once they're spawned, frames them, and captures the picture after a few frames.
+fn shoot_thumbnails(
+	mut commands: Commands,
+	mut shoots: Query<(Entity, &mut Shoot)>,
+	children: Query<&Children>,
+	meshes: Query<(&Aabb, &GlobalTransform)>,
+	layers: Query<(), With<RenderLayers>>,
+	mut cameras: Query<&mut Transform, With<Camera>>,
+) {
+	for (root, mut shoot) in shoots.iter_mut() {
+		let descendants: Vec<Entity> = children.iter_descendants(root).collect();
+		let bounds: Vec<(Vec3, f32)> = descendants.iter().filter_map(|entity| meshes.get(*entity).ok()).map(|(aabb, transform)| {
+			let (scale, _, _) = transform.to_scale_rotation_translation();
+			(transform.transform_point(aabb.center.into()), Vec3::from(aabb.half_extents).length() * scale.max_element())
+		}).collect();
+		// The scene hasn't been spawned yet.
+		if bounds.is_empty() {continue}
+		for entity in descendants.iter().filter(|entity| !layers.contains(**entity)) {
+			commands.entity(*entity).insert(RenderLayers::layer(THUMBNAIL_LAYER)).remove::<Pickable>();
+		}
+		let min = bounds.iter().map(|(center, radius)| center - radius).reduce(Vec3::min).unwrap_or_default();
+		let max = bounds.iter().map(|(center, radius)| center + radius).reduce(Vec3::max).unwrap_or_default();
+		let (center, radius) = ((min + max) / 2., (max - min).length() / 2.);
+		if let Ok(mut transform) = cameras.get_mut(shoot.camera) {
+			// The default field of view is a quarter turn, so this fits the whole scene in view.
+			let distance = radius / (std::f32::consts::FRAC_PI_8).sin() * 1.1;
+			*transform = Transform::from_translation(center + Vec3::new(1., 0.8, 1.).normalize() * distance).looking_at(center, Vec3::Y);
+		}
+		if shoot.frames_left > 0 {
+			shoot.frames_left -= 1;
+			continue
+		}
+		let path = Path::new("assets").join(cache_path(shoot.type_id));
+		commands.spawn(Screenshot::image(shoot.image.clone())).observe(save_thumbnail(path));
+		commands.entity(shoot.camera).despawn();
+		commands.entity(root).despawn();
+	}
+}
+
+/// Saves the captured thumbnail with its transparency, so it can be loaded instead of rendered next time.
+fn save_thumbnail(path: PathBuf) -> impl FnMut(Trigger<ScreenshotCaptured>, ResMut<Thumbnails>) {
+	move |trigger, mut thumbnails| {
+		thumbnails.pending = thumbnails.pending.saturating_sub(1);
+		let image = match trigger.0.clone().try_into_dynamic() {Ok(image) => image, Err(error) => {error!("Failed to convert thumbnail {:?}: {}", path, error); return}};
+		if let Err(error) = path.parent().map_or(Ok(()), fs::create_dir_all) {
+			error!("Failed to create the thumbnail directory for {:?}: {}", path, error);
+			return
+		}
+		match image.to_rgba8().save(&path) {
+			Ok(()) => info!("Saved thumbnail {:?}.", path),
+			Err(error) => error!("Failed to save thumbnail {:?}: {}", path, error),
+		}
+	}
+}
+
+fn exit_when_done(
+	mut exit: EventWriter<AppExit>,
+	settings: Res<ThumbnailSettings>,
+	thumbnails: Res<Thumbnails>,
+) {
+	if settings.exit_when_done && thumbnails.pending == 0 {
+		exit.write(AppExit::Success);
+	}
+}
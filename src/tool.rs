@@ -0,0 +1,251 @@
+use std::collections::{HashSet, BTreeSet};
+use bevy::{prelude::*, color::{Mix, palettes::tailwind}};
+use bevy_egui::EguiContexts;
+use hexx::Hex;
+
+use crate::economy::Money;
+use crate::grid::{
+	CellHeights, CellSurfaces,
+	geometry::gizmo::{cell_outline, cell_top},
+	edit::{Edit, EditStep, EditGrid, BeginStroke, EndStroke},
+};
+
+pub mod brush;
+use brush::{TerrainBrush, earth_cost};
+pub mod piste;
+use piste::{PisteBrush, RoutePlan, paint_edits};
+pub mod lift;
+use lift::LiftPlan;
+pub mod structure;
+pub mod select;
+pub mod pick;
+use select::{SelectShape, Carry};
+use crate::save::ResortLoaded;
+use crate::grid::object::ObjectInstanceId;
+use crate::util::rotation::Rotation;
+use crate::grid::object::{lift::LiftTypeId, structure::StructureTypeId};
+
+/// Tools that the player edits the resort with, by clicking or dragging over the terrain.
+/// Every change goes through `EditGrid`, so a drag can be undone as one step.
+pub struct ToolPlugin;
+impl Plugin for ToolPlugin {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<ToolState>();
+		app.init_resource::<pick::Hover>();
+		app.add_systems(PreUpdate, pick::pick_under_cursor);
+		app.add_systems(Update, pick::draw_focus);
+		app.add_systems(Update, (use_tool, draw_brush, piste::draw_piste_tools, lift::plan_lift, lift::draw_lift_plan));
+		app.add_systems(Update, (piste::plan_route, piste::update_route).chain());
+		app.add_systems(Startup, structure::setup_ghost_materials);
+		app.add_systems(Update, (structure::place_structure, structure::update_ghost, structure::tint_ghost, structure::draw_footprint).chain());
+		app.add_systems(Update, (select::select_objects, select::carry_objects, select::draw_selection).chain());
+		app.add_observer(piste::accept_route);
+		app.add_observer(lift::confirm_lift);
+		app.add_observer(forget_plans);
+	}
+}
+
+/// Cost of moving the earth to raise or lower a cell by one step.
+pub const EARTH_COST: Money = 500;
+/// Seconds between uses of the terrain brush while the mouse button is held down.
+pub const BRUSH_INTERVAL: f32 = 0.15;
+/// Cost of turning a cell into piste.
+pub const PISTE_COST: Money = 200;
+/// Highest a cell can be raised.
+pub const MAX_HEIGHT: u16 = 200;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Tool {
+	#[default]
+	None,
+	/// Shapes the terrain with the brush, reversing raise and lower while holding shift.
+	Terrain,
+	/// Paints piste, or removes it while holding shift.
+	Piste,
+	/// Proposes a piste from a lift's top station down to a chosen cell, within a band of grades.
+	PisteRoute,
+	/// Lays out a lift between two stations, with towers that can be dragged before placing it.
+	Lift,
+	/// Places structures, showing a ghost of the structure on the hovered cell first.
+	Structure,
+	/// Selects placed objects to move or duplicate them.
+	Select,
+}
+impl Tool {
+	pub const ALL: [Self; 7] = [Self::None, Self::Terrain, Self::Piste, Self::PisteRoute, Self::Lift, Self::Structure, Self::Select];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::None => "None",
+			Self::Terrain => "Terrain",
+			Self::Piste => "Piste",
+			Self::PisteRoute => "Piste route",
+			Self::Lift => "Lift",
+			Self::Structure => "Structure",
+			Self::Select => "Select",
+		}
+	}
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ToolState {
+	pub tool: Tool,
+	pub brush: TerrainBrush,
+	pub piste: PisteBrush,
+	/// The route being planned with the piste route tool.
+	pub route: Option<RoutePlan>,
+	/// Type of lift placed by the lift tool.
+	pub lift_type: LiftTypeId,
+	/// The lift being laid out with the lift tool.
+	pub lift: Option<LiftPlan>,
+	/// Type of structure placed by the structure tool.
+	pub structure_type: StructureTypeId,
+	/// Rotation of placed structures, for types that can be rotated.
+	pub rotation: Rotation,
+	pub select_shape: SelectShape,
+	/// Objects selected with the select tool.
+	pub selection: BTreeSet<ObjectInstanceId>,
+	/// The selected objects while they're picked up.
+	pub carry: Option<Carry>,
+	/// Piste cell clicked with the select tool, shown in the inspector while nothing is selected.
+	pub inspected_cell: Option<Hex>,
+	/// Cells dragged over so far while selecting.
+	selecting: Option<Vec<Hex>>,
+	/// None while the mouse button is up.
+	stroke: Option<Stroke>,
+}
+impl Default for ToolState {
+	fn default() -> Self {
+		Self {
+			tool: Tool::None,
+			brush: TerrainBrush::default(),
+			piste: PisteBrush::default(),
+			route: None,
+			lift_type: LiftTypeId(1),
+			lift: None,
+			structure_type: StructureTypeId(1),
+			rotation: Rotation::A,
+			select_shape: SelectShape::Box,
+			selection: BTreeSet::new(),
+			carry: None,
+			inspected_cell: None,
+			selecting: None,
+			stroke: None,
+		}
+	}
+}
+impl ToolState {
+	/// The brush as it's used right now, reversed while holding shift.
+	pub fn active_brush(&self, reverse: bool) -> TerrainBrush {
+		if reverse {TerrainBrush {mode: self.brush.mode.reversed(), ..self.brush}} else {self.brush}
+	}
+
+	/// The cell and its height where the current stroke began, or the hovered cell if there is no stroke.
+	pub fn stroke_start(&self, hovered: Option<Hex>, heights: &CellHeights) -> Option<(Hex, u16)> {
+		match &self.stroke {
+			Some(stroke) => Some((stroke.start, stroke.start_height)),
+			None => hovered.and_then(|pos| heights.0.get(&pos).map(|height| (pos, *height))),
+		}
+	}
+}
+
+/// Everything since the mouse button went down.
+#[derive(Debug, Clone)]
+struct Stroke {
+	start: Hex,
+	start_height: u16,
+	/// Cells painted so far, so dragging over a cell only changes it once.
+	painted: HashSet<Hex>,
+	/// Time until the terrain brush is used again.
+	timer: Timer,
+}
+
+/// Throws away plans and the selection, which refer to the old resort.
+fn forget_plans(
+	_trigger: Trigger<ResortLoaded>,
+	mut state: ResMut<ToolState>,
+) {
+	state.route = None;
+	state.lift = None;
+	state.selection.clear();
+	state.carry = None;
+	state.inspected_cell = None;
+	state.selecting = None;
+}
+
+/// Left click or drag uses the tool on the cells under the cursor, and shift reverses it.
+/// Uses real time, so the terrain can still be shaped while the game is paused.
+fn use_tool(
+	mut commands: Commands,
+	mut contexts: EguiContexts,
+	mut state: ResMut<ToolState>,
+	hover: Res<pick::Hover>,
+	time: Res<Time<Real>>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	input: Res<ButtonInput<KeyCode>>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+) {
+	if mouse_button.just_released(MouseButton::Left) && state.stroke.is_some() {
+		state.stroke = None;
+		commands.trigger(EndStroke);
+	}
+	// The other tools act on clicks instead of painting while dragging.
+	if !matches!(state.tool, Tool::Terrain | Tool::Piste) {return}
+	let Some(pos) = hover.focused_cell() else {return};
+	if mouse_button.just_pressed(MouseButton::Left) {
+		let over_ui = contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area());
+		if over_ui {return}
+		let Some(start_height) = heights.0.get(&pos).copied() else {return};
+		// The timer starts finished, so the brush is used as soon as the button goes down.
+		let mut timer = Timer::from_seconds(BRUSH_INTERVAL, TimerMode::Once);
+		timer.tick(timer.duration());
+		state.stroke = Some(Stroke {start: pos, start_height: start_height, painted: HashSet::new(), timer: timer});
+		commands.trigger(BeginStroke);
+	}
+	if !mouse_button.pressed(MouseButton::Left) {return}
+	let reverse = input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+	let (tool, brush, radius) = (state.tool, state.active_brush(reverse), state.piste.radius);
+	let Some(stroke) = &mut state.stroke else {return};
+	match tool {
+		Tool::None | Tool::PisteRoute | Tool::Lift | Tool::Structure | Tool::Select => (),
+		Tool::Terrain => {
+			if !stroke.timer.tick(time.delta()).finished() {return}
+			stroke.timer.reset();
+			let edits = brush.edits(pos, stroke.start, stroke.start_height, &heights);
+			if edits.is_empty() {return}
+			let cost = earth_cost(&edits);
+			commands.trigger(EditGrid(EditStep::new(edits, cost)));
+		},
+		Tool::Piste => {
+			let cells: Vec<Hex> = pos.range(radius).filter(|cell| stroke.painted.insert(*cell)).collect();
+			let (edits, cost) = paint_edits(cells, 0, reverse, &heights, &surfaces);
+			if edits.is_empty() {return}
+			commands.trigger(EditGrid(EditStep::new(edits, cost)));
+		},
+	}
+}
+
+/// Outlines the cells under the terrain brush, brighter where it's stronger, with lines showing how far the next use moves each cell.
+fn draw_brush(
+	mut gizmos: Gizmos,
+	state: Res<ToolState>,
+	hover: Res<pick::Hover>,
+	heights: Res<CellHeights>,
+	input: Res<ButtonInput<KeyCode>>,
+) {
+	if state.tool != Tool::Terrain {return}
+	let Some(center) = hover.focused_cell() else {return};
+	let Some((start, start_height)) = state.stroke_start(Some(center), &heights) else {return};
+	let brush = state.active_brush(input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]));
+	let (edge, middle) = (Color::from(tailwind::YELLOW_900), Color::from(tailwind::YELLOW_300));
+	for pos in center.range(brush.radius) {
+		cell_outline(&mut *gizmos, &heights.0, &pos, edge.mix(&middle, brush.falloff.weight(pos.unsigned_distance_to(center), brush.radius)));
+	}
+	for edit in brush.edits(center, start, start_height, &heights) {
+		let Edit::Height {pos, before, after} = edit else {continue};
+		let Some(top) = cell_top(&heights.0, &pos) else {continue};
+		let color = if after > before {tailwind::GREEN_400} else {tailwind::RED_400};
+		gizmos.line(top, top + Vec3::Y * (after as f32 - before as f32), color);
+	}
+}
@@ -0,0 +1,226 @@
+use hexx::Hex;
+
+use crate::economy::Money;
+use crate::grid::{CellHeights, edit::Edit};
+use super::{EARTH_COST, MAX_HEIGHT};
+
+/// Largest brush radius, in cells from the center.
+pub const MAX_RADIUS: u32 = 8;
+/// Largest number of height steps a brush changes a cell by in one go.
+pub const MAX_STRENGTH: u16 = 5;
+
+/// What the terrain brush does to the cells under it.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum BrushMode {
+	#[default]
+	Raise,
+	Lower,
+	/// Brings cells to the target height of the brush.
+	Flatten,
+	/// Brings cells towards the average of their neighbours.
+	Smooth,
+	/// Brings cells to the height of the cell where the stroke began.
+	Level,
+	/// Makes an even slope from the cell where the stroke began to the cell under the cursor.
+	Ramp,
+}
+impl BrushMode {
+	pub const ALL: [Self; 6] = [Self::Raise, Self::Lower, Self::Flatten, Self::Smooth, Self::Level, Self::Ramp];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Raise => "Raise",
+			Self::Lower => "Lower",
+			Self::Flatten => "Flatten",
+			Self::Smooth => "Smooth",
+			Self::Level => "Level",
+			Self::Ramp => "Ramp",
+		}
+	}
+
+	/// The mode used while holding shift.
+	pub const fn reversed(&self) -> Self {
+		match self {
+			Self::Raise => Self::Lower,
+			Self::Lower => Self::Raise,
+			mode => *mode,
+		}
+	}
+}
+
+/// How the strength of the brush drops off towards its edge.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Falloff {
+	/// Full strength everywhere.
+	Constant,
+	#[default]
+	Linear,
+	/// Full strength near the center, dropping off quickly near the edge.
+	Smooth,
+}
+impl Falloff {
+	pub const ALL: [Self; 3] = [Self::Constant, Self::Linear, Self::Smooth];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Constant => "Constant",
+			Self::Linear => "Linear",
+			Self::Smooth => "Smooth",
+		}
+	}
+
+	/// Share of the full strength, from 0 to 1, at a distance (cells) from the center.
+	pub fn weight(&self, distance: u32, radius: u32) -> f32 {
+		if distance > radius {return 0.}
+		let x = distance as f32 / (radius + 1) as f32;
+		match self {
+			Self::Constant => 1.,
+			Self::Linear => 1. - x,
+			Self::Smooth => 1. - x * x * (3. - 2. * x),
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TerrainBrush {
+	pub mode: BrushMode,
+	pub falloff: Falloff,
+	/// Cells this far from the center are changed, so 0 only changes the center.
+	pub radius: u32,
+	/// Most height steps a cell at the center changes by in one go.
+	pub strength: u16,
+	/// Height that `BrushMode::Flatten` brings cells to.
+	pub target: u16,
+}
+impl Default for TerrainBrush {
+	fn default() -> Self {Self {mode: BrushMode::Raise, falloff: Falloff::Linear, radius: 2, strength: 1, target: 10}}
+}
+impl TerrainBrush {
+	/// The height edits from using the brush once at `center`, for a stroke that began on `start` at `start_height`.
+	/// Cells only move towards their target by up to the strength at their distance, so repeated use shapes the terrain gradually.
+	pub fn edits(&self, center: Hex, start: Hex, start_height: u16, heights: &CellHeights) -> Vec<Edit> {
+		let cells: Vec<(Hex, u16)> = center.range(self.radius).filter_map(|pos| heights.0.get(&pos).map(|height| (pos, *height))).collect();
+		let ramp_length = center.unsigned_distance_to(start);
+		let center_height = heights.0.get(&center).copied().unwrap_or(start_height);
+		let mut edits = Vec::new();
+		for (pos, before) in cells {
+			let step = (self.strength as f32 * self.falloff.weight(pos.unsigned_distance_to(center), self.radius)).round() as u16;
+			if step == 0 {continue}
+			let target = match self.mode {
+				BrushMode::Raise => before.saturating_add(step).min(MAX_HEIGHT),
+				BrushMode::Lower => before.saturating_sub(step),
+				BrushMode::Flatten => self.target.min(MAX_HEIGHT),
+				BrushMode::Smooth => average(pos.all_neighbors().iter().chain([&pos]).filter_map(|neighbor| heights.0.get(neighbor).copied())).unwrap_or(before),
+				BrushMode::Level => start_height,
+				BrushMode::Ramp => {
+					if ramp_length == 0 {continue}
+					let t = (pos.unsigned_distance_to(start) as f32 / ramp_length as f32).min(1.);
+					(start_height as f32 + (center_height as f32 - start_height as f32) * t).round() as u16
+				},
+			};
+			let after = move_towards(before, target, step);
+			if after != before {
+				edits.push(Edit::Height {pos: pos, before: before, after: after});
+			}
+		}
+		edits
+	}
+}
+
+/// Rounded average, or None if there are no heights.
+fn average(heights: impl Iterator<Item = u16>) -> Option<u16> {
+	let (sum, count) = heights.fold((0u32, 0u32), |(sum, count), height| (sum + height as u32, count + 1));
+	if count == 0 {return None}
+	Some((sum as f32 / count as f32).round() as u16)
+}
+
+/// Moves `from` towards `to` by at most `step`.
+const fn move_towards(from: u16, to: u16, step: u16) -> u16 {
+	if from < to {
+		if to - from < step {to} else {from + step}
+	} else if from - to < step {to} else {from - step}
+}
+
+/// Cost of moving the earth for the height edits.
+pub fn earth_cost(edits: &[Edit]) -> Money {
+	edits.iter().map(|edit| match edit {
+		Edit::Height {before, after, ..} => before.abs_diff(*after) as Money * EARTH_COST,
+		_ => 0,
+	}).sum()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn flat(height: u16) -> CellHeights {
+		CellHeights(Hex::ZERO.range(10).map(|pos| (pos, height)).collect())
+	}
+
+	fn apply(heights: &mut CellHeights, edits: &[Edit]) {
+		for edit in edits {
+			let Edit::Height {pos, after, ..} = edit else {continue};
+			heights.0.insert(*pos, *after);
+		}
+	}
+
+	#[test]
+	fn falloff_drops_towards_the_edge() {
+		for falloff in Falloff::ALL {
+			assert_eq!(falloff.weight(0, 3), 1.);
+			assert_eq!(falloff.weight(4, 3), 0.);
+			assert!(falloff.weight(3, 3) <= falloff.weight(1, 3));
+		}
+		assert!(Falloff::Linear.weight(2, 3) < 1.);
+	}
+
+	#[test]
+	fn raise_stays_within_radius() {
+		let brush = TerrainBrush {mode: BrushMode::Raise, falloff: Falloff::Constant, radius: 2, strength: 2, target: 0};
+		let edits = brush.edits(Hex::ZERO, Hex::ZERO, 5, &flat(5));
+		assert_eq!(edits.len(), Hex::ZERO.range(2).count());
+		assert!(edits.iter().all(|edit| matches!(edit, Edit::Height {pos, before: 5, after: 7} if pos.unsigned_distance_to(Hex::ZERO) <= 2)));
+		assert_eq!(earth_cost(&edits), edits.len() as Money * 2 * EARTH_COST);
+	}
+
+	#[test]
+	fn flatten_converges_on_target() {
+		let brush = TerrainBrush {mode: BrushMode::Flatten, falloff: Falloff::Constant, radius: 1, strength: 3, target: 2};
+		let mut heights = flat(10);
+		for _ in 0..3 {
+			let edits = brush.edits(Hex::ZERO, Hex::ZERO, 10, &heights);
+			apply(&mut heights, &edits);
+		}
+		assert!(Hex::ZERO.range(1).all(|pos| heights.0[&pos] == 2));
+		assert!(brush.edits(Hex::ZERO, Hex::ZERO, 10, &heights).is_empty());
+	}
+
+	#[test]
+	fn level_uses_start_height() {
+		let brush = TerrainBrush {mode: BrushMode::Level, falloff: Falloff::Constant, radius: 0, strength: MAX_STRENGTH, target: 0};
+		let heights = flat(8);
+		assert_eq!(brush.edits(Hex::new(3, 0), Hex::ZERO, 6, &heights), vec![Edit::Height {pos: Hex::new(3, 0), before: 8, after: 6}]);
+	}
+
+	#[test]
+	fn smooth_evens_out_a_spike() {
+		let brush = TerrainBrush {mode: BrushMode::Smooth, falloff: Falloff::Constant, radius: 0, strength: MAX_STRENGTH, target: 0};
+		let mut heights = flat(0);
+		heights.0.insert(Hex::ZERO, 14);
+		assert_eq!(brush.edits(Hex::ZERO, Hex::ZERO, 14, &heights), vec![Edit::Height {pos: Hex::ZERO, before: 14, after: 9}]);
+	}
+
+	#[test]
+	fn ramp_slopes_evenly_between_points() {
+		let brush = TerrainBrush {mode: BrushMode::Ramp, falloff: Falloff::Constant, radius: 0, strength: MAX_STRENGTH, target: 0};
+		let mut heights = flat(0);
+		heights.0.insert(Hex::new(4, 0), 8);
+		let halfway = brush.edits(Hex::new(2, 0), Hex::ZERO, 0, &heights);
+		assert!(halfway.is_empty(), "The center is the end of the ramp, so it keeps its height.");
+		let wide = TerrainBrush {radius: 4, ..brush};
+		let edits = wide.edits(Hex::new(4, 0), Hex::ZERO, 0, &heights);
+		assert!(edits.contains(&Edit::Height {pos: Hex::new(2, 0), before: 0, after: 4}));
+		assert!(edits.contains(&Edit::Height {pos: Hex::new(1, 0), before: 0, after: 2}));
+	}
+}
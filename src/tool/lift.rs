@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use bevy::{prelude::*, color::palettes::tailwind};
+use bevy_egui::EguiContexts;
+use hexx::Hex;
+
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	geometry::gizmo::{cell_outline, path_line},
+	object::{
+		ObjectInstance, ObjectInstanceId, PlaceObject,
+		lift::{LiftInstance, LiftTypeId, LiftTypes, Span, SpanProblem, draw_cable},
+		structure::{StructureTypes, placement_problem},
+	},
+};
+use super::{Tool, ToolState, pick::Hover};
+
+/// Lift being laid out with the lift tool, before it's placed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LiftPlan {
+	pub bottom: Hex,
+	/// None until the second click.
+	pub top: Option<Hex>,
+	/// Cells of the towers, from bottom to top.
+	pub towers: Vec<Hex>,
+	/// Index of the tower being dragged.
+	pub dragging: Option<usize>,
+}
+impl LiftPlan {
+	/// Stations and towers that hold up the cable, from bottom to top.
+	pub fn supports(&self) -> Vec<Hex> {
+		[self.bottom].into_iter().chain(self.towers.iter().copied()).chain(self.top).collect()
+	}
+}
+
+/// Why a planned lift can't be placed, or None if it can.
+/// `occupied` maps cells to the objects on them, like for `placement_problem`.
+pub fn plan_problem(plan: &LiftPlan, spans: &[Span], heights: &CellHeights, surfaces: &CellSurfaces, occupied: &HashMap<Hex, ObjectInstanceId>) -> Option<String> {
+	let top = plan.top?;
+	let (Some(bottom_height), Some(top_height)) = (heights.0.get(&plan.bottom), heights.0.get(&top)) else {return Some("The stations have to be on the terrain.".to_string())};
+	if top_height <= bottom_height {return Some("The top station has to be higher than the bottom station.".to_string())}
+	if let Some(problem) = placement_problem(plan.supports(), heights, surfaces, occupied) {return Some(format!("A station or tower would be {}.", problem.describe()))}
+	if spans.iter().any(|span| !span.problems.is_empty()) {return Some("Some spans break the rules of this lift type.".to_string())}
+	None
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Places the planned lift, if all of its spans are fine.
+pub struct ConfirmLift;
+pub fn confirm_lift(
+	_trigger: Trigger<ConfirmLift>,
+	mut commands: Commands,
+	mut state: ResMut<ToolState>,
+	lift_types: Res<LiftTypes>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+) {
+	let Some(plan) = &state.lift else {warn!("There is no lift to place."); return};
+	let Some(top) = plan.top else {warn!("The lift needs a top station before it can be placed."); return};
+	let Some(lift_type) = lift_types.0.get(&state.lift_type) else {error!("Failed to place lift of unknown type {:?}.", state.lift_type); return};
+	if let Some(problem) = plan_problem(plan, &lift_type.spans(&heights.0, &plan.supports()), &heights, &surfaces, &objects.occupied(&structure_types)) {
+		warn!("Can't place the lift: {}", problem);
+		return
+	}
+	commands.trigger(PlaceObject(ObjectInstance::Lift(LiftInstance::new(state.lift_type, plan.bottom, top, plan.towers.clone()))));
+	state.lift = None;
+}
+
+/// Places the towers again along the straight line between the stations.
+pub fn reset_towers(plan: &mut LiftPlan, lift_type: LiftTypeId, lift_types: &LiftTypes, heights: &CellHeights) {
+	let (Some(top), Some(lift_type)) = (plan.top, lift_types.0.get(&lift_type)) else {return};
+	plan.towers = lift_type.auto_towers(&heights.0, plan.bottom, top);
+	plan.dragging = None;
+}
+
+/// The first click places the bottom station and the second the top station, with towers placed automatically in between.
+/// After that, towers can be dragged to other cells. Enter places the lift and escape throws the plan away.
+pub fn plan_lift(
+	mut commands: Commands,
+	mut contexts: EguiContexts,
+	mut state: ResMut<ToolState>,
+	hover: Res<Hover>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	input: Res<ButtonInput<KeyCode>>,
+	lift_types: Res<LiftTypes>,
+	heights: Res<CellHeights>,
+) {
+	if state.tool != Tool::Lift {return}
+	if input.just_pressed(KeyCode::Escape) && state.lift.is_some() {state.lift = None}
+	if input.just_pressed(KeyCode::Enter) {commands.trigger(ConfirmLift)}
+	if mouse_button.just_released(MouseButton::Left) && let Some(plan) = &mut state.lift && plan.dragging.is_some() {
+		plan.dragging = None;
+	}
+	let Some(pos) = hover.focused_cell() else {return};
+	let lift_type = state.lift_type;
+	if mouse_button.just_pressed(MouseButton::Left) && !contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area()) {
+		match &mut state.lift {
+			None => state.lift = Some(LiftPlan {bottom: pos, top: None, towers: Vec::new(), dragging: None}),
+			Some(plan) if plan.top.is_none() => {
+				if pos == plan.bottom {return}
+				plan.top = Some(pos);
+				reset_towers(plan, lift_type, &lift_types, &heights);
+			},
+			Some(plan) => plan.dragging = plan.towers.iter().position(|tower| *tower == pos),
+		}
+	}
+	if let Some(plan) = &mut state.lift && let Some(i) = plan.dragging && plan.towers[i] != pos && !plan.supports().contains(&pos) {
+		plan.towers[i] = pos;
+	}
+}
+
+/// Draws the planned cable with spans that break the rules in red, and the ground under it.
+/// Stations and towers that can't go where they are, like on water or another object, are outlined in red.
+pub fn draw_lift_plan(
+	mut gizmos: Gizmos,
+	state: Res<ToolState>,
+	hover: Res<Hover>,
+	lift_types: Res<LiftTypes>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+) {
+	if state.tool != Tool::Lift {return}
+	let Some(plan) = &state.lift else {
+		if let Some(pos) = hover.focused_cell() {cell_outline(&mut *gizmos, &heights.0, &pos, tailwind::YELLOW_300)}
+		return
+	};
+	let Some(lift_type) = lift_types.0.get(&state.lift_type) else {return};
+	cell_outline(&mut *gizmos, &heights.0, &plan.bottom, tailwind::SKY_300);
+	let Some(top) = plan.top.or(hover.focused_cell()) else {return};
+	cell_outline(&mut *gizmos, &heights.0, &top, tailwind::SKY_300);
+	path_line(&mut *gizmos, &heights.0, plan.bottom.line_to(top), tailwind::NEUTRAL_500);
+	let supports = if plan.top.is_some() {plan.supports()} else {vec![plan.bottom, top]};
+	let spans = lift_type.spans(&heights.0, &supports);
+	draw_cable(&mut gizmos, lift_type, &heights.0, &supports, |i| if spans[i].problems.is_empty() {tailwind::GREEN_400} else {tailwind::RED_500});
+	for problem in spans.iter().flat_map(|span| span.problems.iter()) {
+		if let SpanProblem::LowClearance(pos) = problem {
+			cell_outline(&mut *gizmos, &heights.0, pos, tailwind::RED_500);
+		}
+	}
+	let occupied = objects.occupied(&structure_types);
+	for pos in supports.iter().filter(|pos| placement_problem([**pos], &heights, &surfaces, &occupied).is_some()) {
+		cell_outline(&mut *gizmos, &heights.0, pos, tailwind::RED_500);
+	}
+	let held = plan.dragging.or_else(|| hover.focused_cell().and_then(|pos| plan.towers.iter().position(|tower| *tower == pos)));
+	if let Some(i) = held {
+		cell_outline(&mut *gizmos, &heights.0, &plan.towers[i], tailwind::YELLOW_300);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grid::surface::Surface;
+
+	#[test]
+	fn stations_and_towers_need_free_land() {
+		let heights = CellHeights(Hex::ZERO.range(6).map(|pos| (pos, (10 + pos.x) as u16)).collect());
+		let mut surfaces = CellSurfaces(heights.0.keys().map(|pos| (*pos, Surface::None)).collect());
+		let plan = LiftPlan {bottom: Hex::new(-2, 0), top: Some(Hex::new(2, 0)), towers: vec![Hex::new(0, 0)], dragging: None};
+		let mut occupied = HashMap::new();
+		assert_eq!(plan_problem(&plan, &[], &heights, &surfaces, &occupied), None);
+		occupied.insert(Hex::new(0, 0), ObjectInstanceId(4));
+		assert_eq!(plan_problem(&plan, &[], &heights, &surfaces, &occupied), Some("A station or tower would be in the way of another object.".to_string()));
+		surfaces.0.insert(Hex::new(2, 0), Surface::Water);
+		assert_eq!(plan_problem(&plan, &[], &heights, &surfaces, &HashMap::new()), Some("A station or tower would be on water.".to_string()));
+	}
+}
@@ -0,0 +1,222 @@
+use bevy::{prelude::*, color::palettes::tailwind, window::PrimaryWindow};
+use bevy_egui::EguiContexts;
+use hexx::Hex;
+
+use crate::util::hex::xz_to_axial;
+use crate::grid::{
+	CellHeights, PlacedObjects,
+	geometry::{TerrainChunk, gizmo::{cell_outline, cell_top}},
+	object::{
+		ObjectInstance, ObjectInstanceId, ObjectEntity,
+		lift::{LiftTypes, draw_cable},
+		structure::StructureTypes,
+	},
+};
+use super::{Tool, ToolState};
+
+/// Something under the cursor that a tool can act on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Target {
+	Object(ObjectInstanceId),
+	/// The cable of a lift, between its supports.
+	Cable(ObjectInstanceId),
+	Cell(Hex),
+}
+impl Target {
+	/// The placed object that the target is part of, if any.
+	pub const fn object(&self) -> Option<ObjectInstanceId> {
+		match self {
+			Self::Object(instance_id) | Self::Cable(instance_id) => Some(*instance_id),
+			Self::Cell(_) => None,
+		}
+	}
+
+	pub fn describe(&self, objects: &PlacedObjects, structure_types: &StructureTypes, lift_types: &LiftTypes) -> String {
+		let name = |instance_id| objects.get(instance_id).map_or("Unknown object", |object| object.name(structure_types, lift_types));
+		match self {
+			Self::Object(instance_id) => name(instance_id).to_string(),
+			Self::Cable(instance_id) => format!("{} cable", name(instance_id)),
+			Self::Cell(pos) => format!("Cell {}, {}", pos.x, pos.y),
+		}
+	}
+
+	/// Middle of the target on the terrain, or None if it's gone.
+	pub fn center(&self, objects: &PlacedObjects, structure_types: &StructureTypes, heights: &CellHeights) -> Option<Vec3> {
+		let cells = match self {
+			Self::Cell(pos) => vec![*pos],
+			_ => objects.get(&self.object()?)?.cells(structure_types),
+		};
+		let tops: Vec<Vec3> = cells.iter().filter_map(|pos| cell_top(&heights.0, pos)).collect();
+		if tops.is_empty() {return None}
+		Some(tops.iter().sum::<Vec3>() / tops.len() as f32)
+	}
+}
+
+impl Tool {
+	/// Whether the tool acts on this kind of target. Tools that edit cells ignore objects, so the cell behind them can be picked.
+	pub const fn accepts(&self, target: &Target) -> bool {
+		matches!((self, target), (Self::None | Self::Select, _) | (_, Target::Cell(_)))
+	}
+}
+
+/// Everything under the cursor that the current tool accepts, best match first.
+/// Tab moves the focus to the next candidate, and clicks act on the focused one.
+#[derive(Resource, Debug, Default, PartialEq, Clone)]
+pub struct Hover {
+	pub candidates: Vec<Target>,
+	pub focus: usize,
+}
+impl Hover {
+	pub fn focused(&self) -> Option<Target> {self.candidates.get(self.focus).copied()}
+
+	/// The focused cell, for tools that act on cells.
+	pub fn focused_cell(&self) -> Option<Hex> {
+		match self.focused()? {Target::Cell(pos) => Some(pos), _ => None}
+	}
+
+	/// The terrain cell under the cursor, whatever has the focus, for tools that place or drag things.
+	pub fn cell(&self) -> Option<Hex> {
+		self.candidates.iter().find_map(|target| match target {Target::Cell(pos) => Some(*pos), _ => None})
+	}
+}
+
+/// Ranks what's under the cursor: objects hit by the ray from the camera (nearest first), then objects standing on the cell,
+/// then lifts whose cable passes over the cell, and the cell itself last.
+pub fn rank_candidates(hit_objects: &[ObjectInstanceId], cell: Option<Hex>, objects: &PlacedObjects, structure_types: &StructureTypes) -> Vec<Target> {
+	let mut candidates: Vec<Target> = Vec::new();
+	let mut push = |target: Target| {
+		if !candidates.contains(&target) {candidates.push(target)}
+	};
+	for instance_id in hit_objects {
+		push(Target::Object(*instance_id));
+	}
+	let Some(cell) = cell else {return candidates};
+	let mut standing: Vec<ObjectInstanceId> = objects.iter().filter(|(_, object)| object.cells(structure_types).contains(&cell)).map(|(instance_id, _)| *instance_id).collect();
+	standing.sort();
+	for instance_id in standing {
+		push(Target::Object(instance_id));
+	}
+	let mut cables: Vec<ObjectInstanceId> = objects.iter().filter_map(|(instance_id, object)| match object {
+		ObjectInstance::Lift(lift) if lift.supports().windows(2).any(|pair| pair[0].line_to(pair[1]).any(|pos| pos == cell)) => Some(*instance_id),
+		_ => None,
+	}).collect();
+	cables.sort();
+	for instance_id in cables {
+		push(Target::Cable(instance_id));
+	}
+	push(Target::Cell(cell));
+	candidates
+}
+
+/// Casts a ray from the camera through the cursor, and ranks everything it hits before the terrain for the current tool.
+/// Runs before the tools, so they always act on what's under the cursor this frame.
+pub fn pick_under_cursor(
+	mut contexts: EguiContexts,
+	state: Res<ToolState>,
+	mut hover: ResMut<Hover>,
+	mut ray_cast: MeshRayCast,
+	input: Res<ButtonInput<KeyCode>>,
+	window: Single<&Window, With<PrimaryWindow>>,
+	camera: Single<(&Camera, &GlobalTransform), With<MeshPickingCamera>>,
+	pickables: Query<(), With<Pickable>>,
+	chunks: Query<(), With<TerrainChunk>>,
+	parents: Query<&ChildOf>,
+	object_entities: Query<&ObjectEntity>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+) {
+	let over_ui = contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area());
+	let (camera, camera_transform) = *camera;
+	let ray = window.cursor_position().filter(|_| !over_ui).and_then(|cursor| camera.viewport_to_world(camera_transform, cursor).ok());
+	let mut hit_objects: Vec<ObjectInstanceId> = Vec::new();
+	let mut cell = None;
+	if let Some(ray) = ray {
+		let filter = |entity| pickables.contains(entity);
+		let settings = MeshRayCastSettings::default().with_filter(&filter).never_early_exit();
+		for (entity, hit) in ray_cast.cast_ray(ray, &settings) {
+			// The cell is where the ray hits the terrain, and nothing hidden behind it counts.
+			if chunks.contains(*entity) {
+				cell = Some(xz_to_axial(hit.point.x, hit.point.z));
+				break;
+			}
+			let instance_id = std::iter::once(*entity).chain(parents.iter_ancestors(*entity)).find_map(|ancestor| object_entities.get(ancestor).ok());
+			if let Some(ObjectEntity(instance_id)) = instance_id && !hit_objects.contains(instance_id) {
+				hit_objects.push(*instance_id);
+			}
+		}
+	}
+	let candidates: Vec<Target> = rank_candidates(&hit_objects, cell, &objects, &structure_types).into_iter().filter(|target| state.tool.accepts(target)).collect();
+	if hover.candidates != candidates {
+		hover.candidates = candidates;
+		hover.focus = 0;
+	}
+	if input.just_pressed(KeyCode::Tab) && !hover.candidates.is_empty() {
+		let count = hover.candidates.len();
+		hover.focus = if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {(hover.focus + count - 1) % count} else {(hover.focus + 1) % count};
+	}
+}
+
+/// Highlights the focused object or cable, for tools that act on objects.
+pub fn draw_focus(
+	mut gizmos: Gizmos,
+	hover: Res<Hover>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	lift_types: Res<LiftTypes>,
+	heights: Res<CellHeights>,
+) {
+	let Some(target) = hover.focused() else {return};
+	let Some(object) = target.object().and_then(|instance_id| objects.get(&instance_id)) else {return};
+	match (target, object) {
+		(Target::Cable(_), ObjectInstance::Lift(lift)) => {
+			let Some(lift_type) = lift_types.0.get(&lift.type_id) else {return};
+			draw_cable(&mut gizmos, lift_type, &heights.0, &lift.supports(), |_| tailwind::YELLOW_300);
+		},
+		_ => {
+			for pos in object.cells(&structure_types) {
+				cell_outline(&mut *gizmos, &heights.0, &pos, tailwind::YELLOW_300);
+			}
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::grid::object::{
+		lift::{LiftInstance, LiftTypeId},
+		structure::{StructureInstance, StructureTypeId},
+	};
+
+	#[test]
+	fn candidates_are_ranked() {
+		let mut objects = PlacedObjects::default();
+		let tree = objects.push(ObjectInstance::Structure(StructureInstance {type_id: StructureTypeId(1), position: Hex::new(2, 0), rotation: None}));
+		let hut = objects.push(ObjectInstance::Structure(StructureInstance {type_id: StructureTypeId(2), position: Hex::new(5, 5), rotation: None}));
+		let lift = objects.push(ObjectInstance::Lift(LiftInstance::new(LiftTypeId(1), Hex::new(0, 0), Hex::new(4, 0), Vec::new())));
+		// Unknown structure types cover only their position.
+		let structure_types = StructureTypes(Default::default());
+		let candidates = rank_candidates(&[hut], Some(Hex::new(2, 0)), &objects, &structure_types);
+		assert_eq!(candidates, vec![Target::Object(hut), Target::Object(tree), Target::Cable(lift), Target::Cell(Hex::new(2, 0))]);
+		let candidates = rank_candidates(&[], Some(Hex::new(0, 0)), &objects, &structure_types);
+		assert_eq!(candidates, vec![Target::Object(lift), Target::Cable(lift), Target::Cell(Hex::new(0, 0))], "A lift's cable can be picked apart from the lift.");
+		assert_eq!(rank_candidates(&[], None, &objects, &structure_types), vec![]);
+	}
+
+	#[test]
+	fn tools_act_on_the_focused_target() {
+		let mut hover = Hover {candidates: vec![Target::Object(ObjectInstanceId(3)), Target::Cell(Hex::new(1, 2))], focus: 0};
+		assert_eq!((hover.focused_cell(), hover.cell()), (None, Some(Hex::new(1, 2))));
+		hover.focus = 1;
+		assert_eq!(hover.focused_cell(), Some(Hex::new(1, 2)));
+		assert_eq!(Hover::default().cell(), None);
+	}
+
+	#[test]
+	fn cell_tools_only_accept_cells() {
+		let (object, cell) = (Target::Object(ObjectInstanceId(0)), Target::Cell(Hex::ZERO));
+		assert!(Tool::Select.accepts(&object) && Tool::Select.accepts(&cell));
+		assert!(!Tool::Terrain.accepts(&object) && Tool::Terrain.accepts(&cell));
+		assert!(!Tool::Structure.accepts(&Target::Cable(ObjectInstanceId(0))));
+	}
+}
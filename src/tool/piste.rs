@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+use bevy::{prelude::*, color::palettes::tailwind};
+use bevy_egui::EguiContexts;
+use hexx::{Hex, algorithms::a_star};
+
+use crate::economy::Money;
+use crate::util::hex::cell_slope;
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::{Surface, PisteGrade},
+	geometry::gizmo::{cell_outline, path_line},
+	edit::{Edit, EditStep, EditGrid},
+	object::ObjectInstance,
+};
+use super::{Tool, ToolState, PISTE_COST, pick::Hover};
+
+/// Widest piste brush, in cells from the center.
+pub const MAX_PISTE_RADIUS: u32 = 3;
+
+/// Settings for painting and routing pistes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PisteBrush {
+	/// Cells this far from the cursor or route are painted, so 0 paints a single cell wide piste.
+	pub radius: u32,
+	/// Easiest grade that routed pistes may pass through.
+	pub easiest: PisteGrade,
+	/// Hardest grade that routed pistes may pass through.
+	pub hardest: PisteGrade,
+}
+impl Default for PisteBrush {
+	fn default() -> Self {Self {radius: 0, easiest: PisteGrade::Green, hardest: PisteGrade::Red}}
+}
+
+/// Piste proposed by the route tool, waiting to be accepted.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RoutePlan {
+	/// Top station of the lift where the piste begins.
+	pub start: Hex,
+	/// Cells that the route has to pass through, in order, added to adjust it.
+	pub waypoints: Vec<Hex>,
+	pub end: Option<Hex>,
+	pub route: Route,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub enum Route {
+	/// Has to be found again, since the plan or brush changed.
+	#[default]
+	Pending,
+	Found(Vec<Hex>),
+	/// No route within the grade band goes downhill all the way.
+	NotFound,
+}
+
+/// The edits that paint piste on every cell within `radius` of the cells, or remove it with `erase`, along with their cost.
+/// Only bare ground can be turned into piste.
+pub fn paint_edits(cells: impl IntoIterator<Item = Hex>, radius: u32, erase: bool, heights: &CellHeights, surfaces: &CellSurfaces) -> (Vec<Edit>, Money) {
+	let mut seen: HashSet<Hex> = HashSet::new();
+	let mut edits = Vec::new();
+	let mut cost = 0;
+	for pos in cells.into_iter().flat_map(|center| center.range(radius)) {
+		if !seen.insert(pos) || !heights.0.contains_key(&pos) {continue}
+		let before = surfaces.0.get(&pos).copied().unwrap_or_default();
+		match (before, erase) {
+			(Surface::None, false) => {
+				edits.push(Edit::Surface {pos: pos, before: before, after: Surface::Piste});
+				cost += PISTE_COST;
+			},
+			(Surface::Piste, true) => edits.push(Edit::Surface {pos: pos, before: before, after: Surface::None}),
+			_ => (),
+		}
+	}
+	(edits, cost)
+}
+
+/// Cost of skiing from one cell to the next on a routed piste, or None if it isn't allowed.
+/// Pistes never go uphill, and every cell except the stops has to be within the grade band.
+fn route_cost(heights: &HashMap<Hex, u16>, surfaces: &HashMap<Hex, Surface>, from: Hex, to: Hex, stop: Hex, easiest: PisteGrade, hardest: PisteGrade) -> Option<u32> {
+	if surfaces.get(&to) == Some(&Surface::Water) || heights.get(&to)? > heights.get(&from)? {return None}
+	if to != stop {
+		let grade = PisteGrade::from_slope(cell_slope(heights, &to));
+		if grade < easiest || grade > hardest {return None}
+	}
+	Some(1)
+}
+
+/// Downhill route from start to end through the waypoints, staying within the grade band of the brush.
+pub fn find_route(start: Hex, waypoints: &[Hex], end: Hex, brush: &PisteBrush, heights: &CellHeights, surfaces: &CellSurfaces) -> Option<Vec<Hex>> {
+	let stops: Vec<Hex> = [start].into_iter().chain(waypoints.iter().copied()).chain([end]).collect();
+	let mut route = vec![start];
+	for leg in stops.windows(2) {
+		let path = a_star(leg[0], leg[1], |from, to| route_cost(&heights.0, &surfaces.0, from, to, leg[1], brush.easiest, brush.hardest))?;
+		route.extend(path.into_iter().skip(1));
+	}
+	Some(route)
+}
+
+/// Top stations of all placed lifts, where routed pistes begin.
+pub fn lift_tops(objects: &PlacedObjects) -> HashSet<Hex> {
+	objects.iter().filter_map(|(_, object)| match object {
+		ObjectInstance::Lift(lift) => Some(lift.top),
+		_ => None,
+	}).collect()
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+/// Paints the proposed route as piste, as a step that can be undone.
+pub struct AcceptRoute;
+pub fn accept_route(
+	_trigger: Trigger<AcceptRoute>,
+	mut commands: Commands,
+	mut state: ResMut<ToolState>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+) {
+	let Some(RoutePlan {route: Route::Found(route), ..}) = &state.route else {warn!("There is no route to accept."); return};
+	let (edits, cost) = paint_edits(route.iter().copied(), state.piste.radius, false, &heights, &surfaces);
+	commands.trigger(EditGrid(EditStep::new(edits, cost)));
+	state.route = None;
+}
+
+/// Clicks pick the start of the route, then the end, and after that add waypoints to adjust it.
+/// Enter accepts the route, backspace removes the last waypoint, and escape throws the plan away.
+pub fn plan_route(
+	mut commands: Commands,
+	mut contexts: EguiContexts,
+	mut state: ResMut<ToolState>,
+	hover: Res<Hover>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	input: Res<ButtonInput<KeyCode>>,
+	objects: Res<PlacedObjects>,
+) {
+	if state.tool != Tool::PisteRoute {return}
+	if input.just_pressed(KeyCode::Escape) && state.route.is_some() {state.route = None}
+	if input.just_pressed(KeyCode::Enter) {commands.trigger(AcceptRoute)}
+	if input.just_pressed(KeyCode::Backspace) && let Some(plan) = &mut state.route && plan.waypoints.pop().is_some() {
+		plan.route = Route::Pending;
+	}
+	if !mouse_button.just_pressed(MouseButton::Left) || contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area()) {return}
+	let Some(pos) = hover.focused_cell() else {return};
+	match &mut state.route {
+		None => {
+			if !lift_tops(&objects).contains(&pos) {
+				warn!("Pistes are routed from the top station of a lift, and {:?} isn't one.", pos);
+				return
+			}
+			state.route = Some(RoutePlan {start: pos, waypoints: Vec::new(), end: None, route: Route::Pending});
+		},
+		Some(plan) => {
+			if plan.end.is_none() {plan.end = Some(pos)} else {plan.waypoints.push(pos)}
+			plan.route = Route::Pending;
+		},
+	}
+}
+
+/// Finds the route once the plan has an end, and again whenever it or the grid changes.
+pub fn update_route(
+	mut state: ResMut<ToolState>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+) {
+	let grid_changed = heights.is_changed() || surfaces.is_changed();
+	let brush = state.piste;
+	let Some(plan) = &mut state.route else {return};
+	let Some(end) = plan.end else {return};
+	if plan.route != Route::Pending && !grid_changed {return}
+	let route = match find_route(plan.start, &plan.waypoints, end, &brush, &heights, &surfaces) {Some(route) => Route::Found(route), None => Route::NotFound};
+	if plan.route != route {
+		plan.route = route;
+	}
+}
+
+pub fn draw_piste_tools(
+	mut gizmos: Gizmos,
+	state: Res<ToolState>,
+	hover: Res<Hover>,
+	heights: Res<CellHeights>,
+) {
+	match state.tool {
+		Tool::Piste => {
+			let Some(center) = hover.focused_cell() else {return};
+			for pos in center.range(state.piste.radius) {
+				cell_outline(&mut *gizmos, &heights.0, &pos, tailwind::YELLOW_300);
+			}
+		},
+		Tool::PisteRoute => {
+			let Some(plan) = &state.route else {
+				if let Some(pos) = hover.focused_cell() {cell_outline(&mut *gizmos, &heights.0, &pos, tailwind::YELLOW_300)}
+				return
+			};
+			let stop_color = if plan.route == Route::NotFound {tailwind::RED_500} else {tailwind::SKY_300};
+			for pos in [plan.start].iter().chain(plan.waypoints.iter()).chain(plan.end.iter()) {
+				cell_outline(&mut *gizmos, &heights.0, pos, stop_color);
+			}
+			if let Route::Found(route) = &plan.route {
+				path_line(&mut *gizmos, &heights.0, route.iter().copied(), tailwind::SKY_500);
+				let painted: HashSet<Hex> = route.iter().flat_map(|center| center.range(state.piste.radius)).collect();
+				for pos in painted.iter() {
+					cell_outline(&mut *gizmos, &heights.0, pos, tailwind::SKY_800);
+				}
+			}
+		},
+		_ => (),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A slope that drops by one every cell going in the +x direction.
+	fn slope(length: i32) -> (CellHeights, CellSurfaces) {
+		let cells: Vec<Hex> = Hex::ZERO.range(length as u32).collect();
+		let heights = CellHeights(cells.iter().map(|pos| (*pos, (length * 2 - pos.x) as u16)).collect());
+		let surfaces = CellSurfaces(cells.iter().map(|pos| (*pos, Surface::None)).collect());
+		(heights, surfaces)
+	}
+
+	#[test]
+	fn painting_is_wide_and_only_on_bare_ground() {
+		let (heights, mut surfaces) = slope(4);
+		surfaces.0.insert(Hex::new(1, 0), Surface::Water);
+		let (edits, cost) = paint_edits([Hex::ZERO], 1, false, &heights, &surfaces);
+		assert_eq!(edits.len(), 6);
+		assert_eq!(cost, 6 * PISTE_COST);
+		assert!(!edits.iter().any(|edit| edit.cell() == Some(Hex::new(1, 0))));
+		let (edits, cost) = paint_edits([Hex::ZERO], 1, true, &heights, &surfaces);
+		assert!(edits.is_empty());
+		assert_eq!(cost, 0);
+	}
+
+	#[test]
+	fn route_goes_downhill_within_band() {
+		let (heights, surfaces) = slope(6);
+		let brush = PisteBrush {radius: 0, easiest: PisteGrade::Green, hardest: PisteGrade::Black};
+		let start = Hex::new(-3, 0);
+		let end = Hex::new(3, 0);
+		let route = find_route(start, &[], end, &brush, &heights, &surfaces).expect("Downhill route should be found.");
+		assert_eq!(route.first(), Some(&start));
+		assert_eq!(route.last(), Some(&end));
+		assert!(route.windows(2).all(|step| heights.0[&step[1]] <= heights.0[&step[0]]));
+		assert!(find_route(end, &[], start, &brush, &heights, &surfaces).is_none(), "Pistes can't go uphill.");
+	}
+
+	#[test]
+	fn route_avoids_grades_outside_band() {
+		let (heights, surfaces) = slope(6);
+		// Every cell on the slope has the same grade, so a band without it leaves no way down.
+		let grade = PisteGrade::from_slope(cell_slope(&heights.0, &Hex::ZERO));
+		let others: Vec<PisteGrade> = PisteGrade::ALL.into_iter().filter(|other| *other != grade).collect();
+		let brush = PisteBrush {radius: 0, easiest: others[0], hardest: others[0]};
+		assert!(find_route(Hex::new(-3, 0), &[], Hex::new(3, 0), &brush, &heights, &surfaces).is_none());
+	}
+
+	#[test]
+	fn route_passes_through_waypoints() {
+		let (heights, surfaces) = slope(6);
+		let brush = PisteBrush {radius: 0, easiest: PisteGrade::Green, hardest: PisteGrade::Black};
+		let waypoint = Hex::new(0, 2);
+		let route = find_route(Hex::new(-3, 0), &[waypoint], Hex::new(3, 0), &brush, &heights, &surfaces).expect("Route through the waypoint should be found.");
+		assert!(route.contains(&waypoint));
+	}
+}
@@ -0,0 +1,329 @@
+use std::collections::BTreeSet;
+use bevy::{prelude::*, color::palettes::tailwind};
+use bevy_egui::EguiContexts;
+use hexx::Hex;
+
+use crate::util::hex::axial_to_xz;
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::Surface,
+	geometry::gizmo::{cell_outline, path_line},
+	edit::{Edit, EditStep, EditGrid, BeginStroke, EndStroke},
+	object::{
+		ObjectInstance, ObjectInstanceId, PlaceObject,
+		lift::LiftTypes,
+		structure::{StructureTypes, placement_problem},
+	},
+};
+use super::{Tool, ToolState, pick::{Hover, Target}};
+
+/// How dragging with the select tool picks objects.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SelectShape {
+	/// Everything in the rectangle between where the drag began and ended.
+	#[default]
+	Box,
+	/// Everything inside the loop drawn by the drag.
+	Lasso,
+}
+impl SelectShape {
+	pub const ALL: [Self; 2] = [Self::Box, Self::Lasso];
+
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Box => "Box",
+			Self::Lasso => "Lasso",
+		}
+	}
+
+	/// Whether the cell is inside the shape drawn through the cells of the drag.
+	pub fn contains(&self, path: &[Hex], pos: Hex) -> bool {
+		let (Some(first), Some(last)) = (path.first(), path.last()) else {return false};
+		match self {
+			Self::Box => box_contains(*first, *last, pos),
+			Self::Lasso => lasso_contains(path, pos),
+		}
+	}
+}
+
+/// Whether the center of the cell is in the rectangle (in world space) around the centers of two cells.
+pub fn box_contains(corner: Hex, other_corner: Hex, pos: Hex) -> bool {
+	// Half a cell of slack, so cells zigzagging along the edge are included.
+	const SLACK: f32 = 0.5;
+	let ([ax, az], [bx, bz], [x, z]) = (axial_to_xz(&corner), axial_to_xz(&other_corner), axial_to_xz(&pos));
+	x >= ax.min(bx) - SLACK && x <= ax.max(bx) + SLACK && z >= az.min(bz) - SLACK && z <= az.max(bz) + SLACK
+}
+
+/// Whether the cell is on the path, or its center is inside the loop through the centers of the cells on the path.
+pub fn lasso_contains(path: &[Hex], pos: Hex) -> bool {
+	if path.contains(&pos) {return true}
+	if path.len() < 3 {return false}
+	let [x, z] = axial_to_xz(&pos);
+	let corners: Vec<[f32; 2]> = path.iter().map(axial_to_xz).collect();
+	// Counts how many edges a ray going in the +x direction crosses.
+	let mut inside = false;
+	for (i, [ax, az]) in corners.iter().enumerate() {
+		let [bx, bz] = corners[(i + 1) % corners.len()];
+		if (*az > z) != (bz > z) && x < ax + (z - az) * (bx - ax) / (bz - az) {
+			inside = !inside;
+		}
+	}
+	inside
+}
+
+/// What happens when the carried objects are put down.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum CarryMode {
+	/// The objects are taken away from where they were, keeping their ids and state.
+	Move,
+	/// Copies are placed and paid for, and the originals stay.
+	Duplicate,
+}
+
+/// The selected objects, picked up to be moved or duplicated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Carry {
+	pub mode: CarryMode,
+	/// Cell of the group that follows the cursor and that the group turns around.
+	pub pivot: Hex,
+	/// Sixths of a turn clockwise.
+	pub turns: u32,
+}
+
+/// Cell of the objects closest to their middle, for turning them around.
+pub fn group_pivot(cells: &[Hex]) -> Option<Hex> {
+	if cells.is_empty() {return None}
+	let (x, y) = cells.iter().fold((0., 0.), |(x, y), pos| (x + pos.x as f32, y + pos.y as f32));
+	let middle = Hex::round([x / cells.len() as f32, y / cells.len() as f32]);
+	cells.iter().min_by_key(|pos| pos.unsigned_distance_to(middle)).copied()
+}
+
+/// The selected objects as they would be if put down with the pivot on the target cell.
+pub fn carried(selection: &BTreeSet<ObjectInstanceId>, objects: &PlacedObjects, carry: &Carry, target: Hex) -> Vec<(ObjectInstanceId, ObjectInstance)> {
+	selection.iter().filter_map(|instance_id| objects.get(instance_id).map(|object| (*instance_id, object.moved(carry.pivot, target, carry.turns)))).collect()
+}
+
+/// Why the carried objects can't be put down where they are, or None if they can.
+pub fn carry_problem(
+	moved: &[(ObjectInstanceId, ObjectInstance)],
+	mode: CarryMode,
+	objects: &PlacedObjects,
+	structure_types: &StructureTypes,
+	lift_types: &LiftTypes,
+	heights: &CellHeights,
+	surfaces: &CellSurfaces,
+) -> Option<String> {
+	let mut occupied = objects.occupied(structure_types);
+	if mode == CarryMode::Move {
+		occupied.retain(|_, instance_id| !moved.iter().any(|(moved_id, _)| moved_id == instance_id));
+	}
+	for (_, object) in moved {
+		if let Some(problem) = placement_problem(object.cells(structure_types), heights, surfaces, &occupied) {
+			return Some(format!("Something would be {}.", problem.describe()))
+		}
+		if let ObjectInstance::Lift(lift) = object
+		&& let Some(lift_type) = lift_types.0.get(&lift.type_id)
+		&& lift_type.spans(&heights.0, &lift.supports()).iter().any(|span| !span.problems.is_empty()) {
+			return Some(format!("The {} would break the rules of its lift type.", lift_type.name))
+		}
+	}
+	None
+}
+
+/// Picks up the selected objects, to move or duplicate them.
+pub fn pick_up(state: &mut ToolState, mode: CarryMode, objects: &PlacedObjects, structure_types: &StructureTypes) {
+	let cells: Vec<Hex> = state.selection.iter().filter_map(|instance_id| objects.get(instance_id)).flat_map(|object| object.cells(structure_types)).collect();
+	let Some(pivot) = group_pivot(&cells) else {return};
+	state.carry = Some(Carry {mode: mode, pivot: pivot, turns: 0});
+}
+
+/// Clicking selects the focused object, or inspects a piste cell, and dragging selects every object in the box or lasso. Shift adds to the selection.
+/// M picks up the selection to move it, C to copy it, and escape clears it.
+/// D is left alone, since it moves the camera.
+pub fn select_objects(
+	mut contexts: EguiContexts,
+	mut state: ResMut<ToolState>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	input: Res<ButtonInput<KeyCode>>,
+	hover: Res<Hover>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	surfaces: Res<CellSurfaces>,
+) {
+	if state.tool != Tool::Select || state.carry.is_some() {return}
+	if input.just_pressed(KeyCode::Escape) {
+		if !state.selection.is_empty() {state.selection.clear()}
+		if state.inspected_cell.is_some() {state.inspected_cell = None}
+	}
+	if input.just_pressed(KeyCode::KeyM) {pick_up(&mut state, CarryMode::Move, &objects, &structure_types)}
+	if input.just_pressed(KeyCode::KeyC) {pick_up(&mut state, CarryMode::Duplicate, &objects, &structure_types)}
+	if mouse_button.just_pressed(MouseButton::Left)
+	&& !contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area())
+	&& let Some(pos) = hover.cell() {
+		state.selecting = Some(vec![pos]);
+	}
+	if mouse_button.pressed(MouseButton::Left) && let Some(pos) = hover.cell() && let Some(path) = &mut state.selecting && path.last() != Some(&pos) {
+		path.push(pos);
+	}
+	if !mouse_button.just_released(MouseButton::Left) {return}
+	let Some(path) = state.selecting.take() else {return};
+	let clicked = path.len() == 1;
+	let picked: BTreeSet<ObjectInstanceId> = if clicked {
+		// Tab may have moved the focus from the nearest object, so the click goes to whatever is focused now.
+		let occupied = objects.occupied(&structure_types);
+		state.inspected_cell = match hover.focused() {
+			Some(Target::Cell(pos)) if !occupied.contains_key(&pos) && surfaces.0.get(&pos) == Some(&Surface::Piste) => Some(pos),
+			_ => None,
+		};
+		match hover.focused() {
+			Some(Target::Cell(pos)) => occupied.get(&pos).into_iter().copied().collect(),
+			Some(target) => target.object().into_iter().collect(),
+			None => BTreeSet::new(),
+		}
+	} else {
+		objects.occupied(&structure_types).iter().filter(|(pos, _)| state.select_shape.contains(&path, **pos)).map(|(_, instance_id)| *instance_id).collect()
+	};
+	if !input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+		state.selection = picked;
+	} else if clicked {
+		for instance_id in picked {
+			if !state.selection.remove(&instance_id) {state.selection.insert(instance_id);}
+		}
+	} else {
+		state.selection.extend(picked);
+	}
+}
+
+/// The carried objects follow the cursor, R turns them clockwise and shift+R back, and left click puts them down.
+/// Moving is free and can be undone as one step. Duplicates are paid for, and more copies can be put down until escape is pressed.
+pub fn carry_objects(
+	mut commands: Commands,
+	mut contexts: EguiContexts,
+	mut state: ResMut<ToolState>,
+	hover: Res<Hover>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	input: Res<ButtonInput<KeyCode>>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	lift_types: Res<LiftTypes>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+) {
+	if state.tool != Tool::Select {return}
+	let Some(carry) = &mut state.carry else {return};
+	if input.just_pressed(KeyCode::Escape) {
+		state.carry = None;
+		return
+	}
+	if input.just_pressed(KeyCode::KeyR) {
+		carry.turns = (carry.turns + if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {5} else {1}) % 6;
+	}
+	if !mouse_button.just_pressed(MouseButton::Left) || contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area()) {return}
+	let carry = *carry;
+	let Some(target) = hover.cell() else {return};
+	let moved = carried(&state.selection, &objects, &carry, target);
+	if let Some(problem) = carry_problem(&moved, carry.mode, &objects, &structure_types, &lift_types, &heights, &surfaces) {
+		warn!("Can't put the objects down on {:?}: {}", target, problem);
+		return
+	}
+	match carry.mode {
+		CarryMode::Move => {
+			let edits = moved.into_iter().flat_map(|(instance_id, object)| [
+				Edit::Remove {instance_id: instance_id, object: objects.get(&instance_id).cloned().expect("Carried objects were just looked up.")},
+				Edit::Place {instance_id: instance_id, object: object},
+			]).collect();
+			commands.trigger(EditGrid(EditStep::new(edits, 0)));
+			state.carry = None;
+		},
+		CarryMode::Duplicate => {
+			// Each copy gets its id and is paid for when it's placed, and the stroke makes them one step in the history.
+			commands.trigger(BeginStroke);
+			for (_, object) in moved {
+				commands.trigger(PlaceObject(object));
+			}
+			commands.trigger(EndStroke);
+		},
+	}
+}
+
+/// Outlines the selected objects, the box or lasso being dragged, and where carried objects would go.
+pub fn draw_selection(
+	mut gizmos: Gizmos,
+	state: Res<ToolState>,
+	hover: Res<Hover>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	lift_types: Res<LiftTypes>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+) {
+	if state.tool != Tool::Select {return}
+	let selected_color = if state.carry.is_some() {tailwind::SKY_800} else {tailwind::SKY_300};
+	for object in state.selection.iter().filter_map(|instance_id| objects.get(instance_id)) {
+		for pos in object.cells(&structure_types) {
+			cell_outline(&mut *gizmos, &heights.0, &pos, selected_color);
+		}
+	}
+	if let Some(path) = &state.selecting && let Some(start) = path.first() {
+		if state.select_shape == SelectShape::Lasso {
+			path_line(&mut *gizmos, &heights.0, path.iter().chain([start]).copied(), tailwind::YELLOW_300);
+		}
+		let reach = path.iter().map(|pos| start.unsigned_distance_to(*pos)).max().unwrap_or(0);
+		for pos in start.range(reach + 1).filter(|pos| state.select_shape.contains(path, *pos)) {
+			cell_outline(&mut *gizmos, &heights.0, &pos, tailwind::YELLOW_600);
+		}
+	}
+	if let Some(carry) = &state.carry && let Some(target) = hover.cell() {
+		let moved = carried(&state.selection, &objects, carry, target);
+		let color = match carry_problem(&moved, carry.mode, &objects, &structure_types, &lift_types, &heights, &surfaces) {
+			None => tailwind::GREEN_400,
+			Some(_) => tailwind::RED_500,
+		};
+		for (_, object) in moved.iter() {
+			for pos in object.cells(&structure_types) {
+				cell_outline(&mut *gizmos, &heights.0, &pos, color);
+			}
+		}
+		cell_outline(&mut *gizmos, &heights.0, &target, tailwind::YELLOW_300);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::util::rotation::Rotation;
+	use crate::grid::object::structure::{StructureInstance, StructureTypeId};
+
+	#[test]
+	fn box_covers_cells_between_corners() {
+		let (corner, other_corner) = (Hex::new(0, 0), Hex::new(4, 0));
+		assert!(box_contains(corner, other_corner, Hex::new(2, 0)));
+		assert!(box_contains(corner, other_corner, Hex::new(4, 0)));
+		assert!(!box_contains(corner, other_corner, Hex::new(6, 0)));
+		assert!(!box_contains(corner, other_corner, Hex::new(2, 3)));
+	}
+
+	#[test]
+	fn lasso_covers_cells_inside_loop() {
+		let path: Vec<Hex> = Hex::ZERO.ring(3).collect();
+		assert!(lasso_contains(&path, Hex::ZERO));
+		assert!(lasso_contains(&path, Hex::new(1, 1)));
+		assert!(lasso_contains(&path, path[4]));
+		assert!(!lasso_contains(&path, Hex::new(5, 0)));
+		assert!(!lasso_contains(&path[..2], Hex::ZERO));
+	}
+
+	#[test]
+	fn group_turns_around_pivot() {
+		let tree = |x, y| ObjectInstance::Structure(StructureInstance {type_id: StructureTypeId(1), position: Hex::new(x, y), rotation: None});
+		let hut = ObjectInstance::Structure(StructureInstance {type_id: StructureTypeId(2), position: Hex::new(1, 0), rotation: Some(Rotation::F)});
+		let pivot = Hex::new(0, 0);
+		assert_eq!(tree(2, 0).moved(pivot, pivot, 0), tree(2, 0));
+		assert_eq!(tree(2, 0).moved(pivot, Hex::new(5, 5), 0), tree(7, 5));
+		assert_eq!(tree(2, 0).moved(pivot, pivot, 1), tree(0, 2));
+		assert_eq!(tree(2, 0).moved(pivot, pivot, 6), tree(2, 0));
+		let ObjectInstance::Structure(turned) = hut.moved(pivot, pivot, 2) else {unreachable!()};
+		assert_eq!(turned.rotation, Some(Rotation::B));
+		assert_eq!(group_pivot(&[Hex::new(0, 0), Hex::new(1, 0), Hex::new(2, 0)]), Some(Hex::new(1, 0)));
+	}
+}
@@ -0,0 +1,175 @@
+use bevy::{prelude::*, color::palettes::tailwind};
+use bevy_egui::EguiContexts;
+use hexx::Hex;
+
+use crate::util::{hex::axial_to_xz, rotation::Rotation};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	geometry::gizmo::cell_outline,
+	object::{
+		ObjectInstance, PlaceObject,
+		structure::{StructureInstance, StructureType, StructureTypeId, StructureTypes, PlacementProblem, placement_problem, rotation_quat},
+	},
+};
+use super::{Tool, ToolState, pick::Hover};
+
+#[derive(Component, Debug, PartialEq, Eq, Clone, Copy)]
+/// See-through copy of the structure about to be placed, following the cursor.
+pub struct Ghost(pub StructureTypeId);
+
+/// Materials that the ghost is drawn with, depending on whether it can be placed where it is.
+#[derive(Resource, Debug, Clone)]
+pub struct GhostMaterials {
+	pub valid: Handle<StandardMaterial>,
+	pub invalid: Handle<StandardMaterial>,
+}
+
+pub fn setup_ghost_materials(
+	mut commands: Commands,
+	mut material_assets: ResMut<Assets<StandardMaterial>>,
+) {
+	let ghost_material = |color: Srgba| StandardMaterial {
+		base_color: color.with_alpha(0.4).into(),
+		alpha_mode: AlphaMode::Blend,
+		unlit: true,
+		..default()
+	};
+	commands.insert_resource(GhostMaterials {
+		valid: material_assets.add(ghost_material(tailwind::GREEN_400)),
+		invalid: material_assets.add(ghost_material(tailwind::RED_500)),
+	});
+}
+
+/// Rotation that a structure of this type gets when it's placed, which is None for types that can't be rotated.
+pub const fn placed_rotation(structure_type: &StructureType, rotation: Rotation) -> Option<Rotation> {
+	if structure_type.has_rotation {Some(rotation)} else {None}
+}
+
+/// The structure that the tool would place on the cell.
+pub fn planned_structure(state: &ToolState, structure_types: &StructureTypes, pos: Hex) -> Option<StructureInstance> {
+	let structure_type = structure_types.0.get(&state.structure_type)?;
+	Some(StructureInstance {type_id: state.structure_type, position: pos, rotation: placed_rotation(structure_type, state.rotation)})
+}
+
+/// Why the structure can't be placed on the hovered cell, or None if it can.
+pub fn hovered_problem(state: &ToolState, hovered: Option<Hex>, structure_types: &StructureTypes, heights: &CellHeights, surfaces: &CellSurfaces, objects: &PlacedObjects) -> Option<PlacementProblem> {
+	let Some(pos) = hovered else {return Some(PlacementProblem::OffMap)};
+	let Some(instance) = planned_structure(state, structure_types, pos) else {return Some(PlacementProblem::OffMap)};
+	let cells = ObjectInstance::Structure(instance).cells(structure_types);
+	placement_problem(cells, heights, surfaces, &objects.occupied(structure_types))
+}
+
+/// R turns the structure clockwise and shift+R back, and left click places it.
+pub fn place_structure(
+	mut commands: Commands,
+	mut contexts: EguiContexts,
+	mut state: ResMut<ToolState>,
+	hover: Res<Hover>,
+	mouse_button: Res<ButtonInput<MouseButton>>,
+	input: Res<ButtonInput<KeyCode>>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+) {
+	if state.tool != Tool::Structure {return}
+	if input.just_pressed(KeyCode::KeyR) {
+		state.rotation = if input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {state.rotation - 1} else {state.rotation + 1};
+	}
+	if !mouse_button.just_pressed(MouseButton::Left) || contexts.try_ctx_mut().is_some_and(|ctx| ctx.is_pointer_over_area()) {return}
+	let Some(pos) = hover.focused_cell() else {return};
+	let Some(instance) = planned_structure(&state, &structure_types, pos) else {error!("Failed to place structure of unknown type {:?}.", state.structure_type); return};
+	if let Some(problem) = hovered_problem(&state, Some(pos), &structure_types, &heights, &surfaces, &objects) {
+		warn!("Can't place structure on {:?}, because it would be {}.", pos, problem.describe());
+		return
+	}
+	commands.trigger(PlaceObject(ObjectInstance::Structure(instance)));
+}
+
+/// Keeps a ghost of the selected structure on the hovered cell while the structure tool is in use.
+pub fn update_ghost(
+	mut commands: Commands,
+	state: Res<ToolState>,
+	hover: Res<Hover>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+	mut ghosts: Query<(Entity, &Ghost, &mut Transform)>,
+) {
+	let instance = match state.tool {
+		Tool::Structure => hover.focused_cell().and_then(|pos| planned_structure(&state, &structure_types, pos)),
+		_ => None,
+	};
+	let target = instance.and_then(|instance| heights.0.get(&instance.position).map(|height| {
+		let [x, z] = axial_to_xz(&instance.position);
+		Transform::from_xyz(x, *height as f32, z).with_rotation(rotation_quat(instance.rotation))
+	}));
+	let mut found = false;
+	for (entity, ghost, mut transform) in ghosts.iter_mut() {
+		match target {
+			Some(target) if ghost.0 == state.structure_type && !found => {
+				found = true;
+				if *transform != target {
+					*transform = target;
+				}
+			},
+			_ => commands.entity(entity).despawn(),
+		}
+	}
+	if found {return}
+	let (Some(target), Some(structure_type)) = (target, structure_types.0.get(&state.structure_type)) else {return};
+	commands.spawn((
+		Ghost(state.structure_type),
+		SceneRoot(structure_type.scene.clone()),
+		target,
+	));
+}
+
+/// Draws the ghost green or red depending on whether it can be placed, and keeps it from blocking the cursor.
+/// Runs every frame, since the scene's meshes only show up after it has been spawned.
+pub fn tint_ghost(
+	mut commands: Commands,
+	state: Res<ToolState>,
+	hover: Res<Hover>,
+	materials: Res<GhostMaterials>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	ghosts: Query<Entity, With<Ghost>>,
+	children: Query<&Children>,
+	mut mesh_materials: Query<&mut MeshMaterial3d<StandardMaterial>>,
+	pickables: Query<(), With<Pickable>>,
+) {
+	let Some(ghost) = ghosts.iter().next() else {return};
+	let material = match hovered_problem(&state, hover.focused_cell(), &structure_types, &heights, &surfaces, &objects) {
+		None => &materials.valid,
+		Some(_) => &materials.invalid,
+	};
+	for entity in children.iter_descendants(ghost) {
+		if let Ok(mut mesh_material) = mesh_materials.get_mut(entity) && mesh_material.0 != *material {
+			mesh_material.0 = material.clone();
+		}
+		if pickables.contains(entity) {
+			commands.entity(entity).remove::<Pickable>();
+		}
+	}
+}
+
+/// Outlines the cells that the structure would cover, in red where something is in the way.
+pub fn draw_footprint(
+	mut gizmos: Gizmos,
+	state: Res<ToolState>,
+	hover: Res<Hover>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+) {
+	if state.tool != Tool::Structure {return}
+	let Some(instance) = hover.focused_cell().and_then(|pos| planned_structure(&state, &structure_types, pos)) else {return};
+	let occupied = objects.occupied(&structure_types);
+	for pos in ObjectInstance::Structure(instance).cells(&structure_types) {
+		let color = match placement_problem([pos], &heights, &surfaces, &occupied) {None => tailwind::GREEN_400, Some(_) => tailwind::RED_500};
+		cell_outline(&mut *gizmos, &heights.0, &pos, color);
+	}
+}
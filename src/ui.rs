@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+mod pricing_ui;
+mod finance_ui;
+mod clock_ui;
+mod weather_ui;
+mod snowmaking_ui;
+mod grooming_ui;
+mod avalanche_ui;
+mod staff_ui;
+mod facility_ui;
+mod rating_ui;
+mod accident_ui;
+mod save_ui;
+mod tool_ui;
+mod build_ui;
+mod inspector_ui;
+mod overlay_ui;
+mod camera_ui;
+
+pub struct UiPlugin;
+impl Plugin for UiPlugin {
+	fn build(&self, app: &mut App) {
+		app.add_systems(EguiContextPass, (
+			pricing_ui::setup,
+			finance_ui::setup,
+			clock_ui::setup,
+			weather_ui::setup,
+			snowmaking_ui::setup,
+			grooming_ui::setup,
+			avalanche_ui::setup,
+			staff_ui::setup,
+			facility_ui::setup,
+			rating_ui::setup,
+			accident_ui::setup,
+			save_ui::setup,
+			tool_ui::setup,
+			build_ui::setup,
+			inspector_ui::setup,
+			overlay_ui::setup,
+			camera_ui::setup,
+		));
+	}
+}
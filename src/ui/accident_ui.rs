@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::util::hex::cell_slope;
+use crate::accident::Incidents;
+use crate::grid::{CellHeights, CellSurfaces, surface::PisteGrade};
+
+/// Number of dangerous runs listed.
+const SHOWN_RUNS: usize = 5;
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut incidents: ResMut<Incidents>,
+	heights: Res<CellHeights>,
+	surfaces: Res<CellSurfaces>,
+) {
+	egui::Window::new("Accidents")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		let mut show_heatmap = incidents.show_heatmap;
+		ui.checkbox(&mut show_heatmap, "Show incident heatmap");
+		if show_heatmap != incidents.show_heatmap {
+			incidents.show_heatmap = show_heatmap;
+		}
+		for (label, stats) in [("Today", incidents.today), ("Yesterday", incidents.yesterday)] {
+			ui.label(format!("{}: {} accidents, {} injuries, {} rescued, {} unresolved", label, stats.accidents, stats.injuries, stats.rescued, stats.unresolved));
+		}
+
+		ui.separator();
+		if incidents.active.is_empty() {
+			ui.label("No injured guests.");
+		}
+		for incident in incidents.active.iter() {
+			ui.label(format!("Injured at {}, {}: {} for {:.0} min", incident.position.x, incident.position.y, incident.stage.name(), incident.minutes));
+		}
+
+		ui.separator();
+		ui.label("Most dangerous runs");
+		let dangerous = incidents.most_dangerous(&surfaces.0, SHOWN_RUNS);
+		if dangerous.is_empty() {
+			ui.label("No accidents yet.");
+		}
+		for run in dangerous {
+			let grade = PisteGrade::from_slope(cell_slope(&heights.0, &run.worst));
+			ui.label(format!("Run of {} cells: {} accidents, most on the {} cell at {}, {}", run.cells, run.accidents, grade.name().to_lowercase(), run.worst.x, run.worst.y));
+		}
+	});
+}
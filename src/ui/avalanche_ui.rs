@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::avalanche::{Avalanches, ControlAvalanches, CONTROL_MIN_RISK, RELEASE_RISK, EXPLOSIVE_COST};
+use crate::economy::Money;
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut commands: Commands,
+	mut avalanches: ResMut<Avalanches>,
+) {
+	egui::Window::new("Avalanches")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		let mut show_risk = avalanches.show_risk;
+		ui.checkbox(&mut show_risk, "Show risk");
+		if show_risk != avalanches.show_risk {
+			avalanches.show_risk = show_risk;
+		}
+		let risky = avalanches.risk.values().filter(|cell_risk| **cell_risk >= CONTROL_MIN_RISK).count();
+		let critical = avalanches.risk.values().filter(|cell_risk| **cell_risk >= RELEASE_RISK * 0.8).count();
+		ui.label(format!("Risky slopes: {} ({} critical)", risky, critical));
+		ui.label(format!("Closed piste cells: {}", avalanches.closed.len()));
+		if ui.add_enabled(risky > 0, egui::Button::new(format!("Blast risky slopes (up to {})", risky as Money * EXPLOSIVE_COST))).clicked() {
+			commands.trigger(ControlAvalanches);
+		}
+
+		ui.separator();
+		if avalanches.reports.is_empty() {
+			ui.label("No avalanches yet.");
+		}
+		for report in avalanches.reports.iter() {
+			let kind = if report.slide.controlled {"Controlled"} else {"Avalanche"};
+			let mut text = format!("Day {}: {} ran {} cells", report.day + 1, kind, report.slide.path.len());
+			if report.pistes_closed > 0 {
+				text += &format!(", closed {} piste cells", report.pistes_closed);
+			}
+			if !report.structures_destroyed.is_empty() {
+				text += &format!(", destroyed {}", report.structures_destroyed.join(", "));
+			}
+			ui.label(text);
+		}
+	});
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::thumbnail::{Thumbnails, THUMBNAIL_SIZE};
+use crate::tool::{ToolState, Tool, lift::reset_towers};
+use crate::economy::Money;
+use crate::grid::{
+	CellHeights,
+	object::{
+		BuildCategory,
+		lift::{LiftTypeId, LiftTypes},
+		structure::{StructureTypeId, StructureTypes},
+	},
+};
+
+/// Size that thumbnails are shown at in the build menu.
+const ENTRY_SIZE: f32 = 72.;
+
+/// Category and search text being browsed.
+#[derive(Default)]
+pub struct BuildMenu {
+	category: BuildCategory,
+	search: String,
+}
+
+/// Something in the build menu.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Entry {
+	Lift(LiftTypeId),
+	Structure(StructureTypeId),
+}
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut menu: Local<BuildMenu>,
+	mut state: ResMut<ToolState>,
+	thumbnails: Res<Thumbnails>,
+	lift_types: Res<LiftTypes>,
+	structure_types: Res<StructureTypes>,
+	heights: Res<CellHeights>,
+) {
+	let textures: Vec<(StructureTypeId, egui::TextureId)> = thumbnails.images.iter().map(|(type_id, image)| (*type_id, contexts.add_image(image.clone_weak()))).collect();
+	egui::Window::new("Build")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(true)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.horizontal(|ui| {
+			ui.label("Search:");
+			ui.text_edit_singleline(&mut menu.search);
+			if !menu.search.is_empty() && ui.button("Clear").clicked() {menu.search.clear()}
+		});
+		let search = menu.search.trim().to_lowercase();
+		// Searching looks through every category.
+		if search.is_empty() {
+			ui.horizontal(|ui| {
+				for category in BuildCategory::ALL {
+					ui.selectable_value(&mut menu.category, category, category.name());
+				}
+			});
+		}
+		let matches = |name: &str, category: BuildCategory| if search.is_empty() {category == menu.category} else {name.to_lowercase().contains(&search)};
+		let mut entries: Vec<(Entry, &'static str, Money, Money)> = Vec::new();
+		let mut lift_ids: Vec<&LiftTypeId> = lift_types.0.keys().collect();
+		lift_ids.sort();
+		for type_id in lift_ids {
+			let lift_type = &lift_types.0[type_id];
+			if matches(lift_type.name, BuildCategory::Lifts) {entries.push((Entry::Lift(*type_id), lift_type.name, lift_type.cost, lift_type.upkeep))}
+		}
+		let mut structure_ids: Vec<&StructureTypeId> = structure_types.0.keys().collect();
+		structure_ids.sort_by_key(|type_id| (structure_types.0[type_id].cost, **type_id));
+		for type_id in structure_ids {
+			let structure_type = &structure_types.0[type_id];
+			if matches(structure_type.name, structure_type.category) {entries.push((Entry::Structure(*type_id), structure_type.name, structure_type.cost, structure_type.upkeep))}
+		}
+		if entries.is_empty() {
+			ui.label("Nothing found.");
+			return
+		}
+		let selected = match state.tool {
+			Tool::Lift => Some(Entry::Lift(state.lift_type)),
+			Tool::Structure => Some(Entry::Structure(state.structure_type)),
+			_ => None,
+		};
+		let mut picked = None;
+		egui::ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+			egui::Grid::new("build_menu").striped(true).show(ui, |ui| {
+				for (entry, name, cost, upkeep) in entries {
+					let texture = match entry {
+						Entry::Structure(type_id) => textures.iter().find(|(id, _)| *id == type_id).map(|(_, texture)| *texture),
+						Entry::Lift(_) => None,
+					};
+					let (rect, _) = ui.allocate_exact_size(egui::Vec2::splat(ENTRY_SIZE), egui::Sense::hover());
+					match texture {
+						Some(texture) => {
+							let size = egui::Vec2::splat(THUMBNAIL_SIZE as f32);
+							egui::Image::new(egui::load::SizedTexture::new(texture, size)).paint_at(ui, rect);
+						},
+						None => lift_icon(ui, rect),
+					}
+					let text = format!("{}\nCosts {}, upkeep {} per day", name, cost, upkeep);
+					if ui.selectable_label(selected == Some(entry), text).clicked() {picked = Some(entry)}
+					ui.end_row();
+				}
+			});
+		});
+		match picked {
+			Some(Entry::Lift(type_id)) => {
+				state.tool = Tool::Lift;
+				state.lift_type = type_id;
+				if let Some(plan) = &mut state.lift {
+					reset_towers(plan, type_id, &lift_types, &heights);
+				}
+			},
+			Some(Entry::Structure(type_id)) => {
+				state.tool = Tool::Structure;
+				state.structure_type = type_id;
+			},
+			None => (),
+		}
+	});
+}
+
+/// Lifts have no scene to take a picture of, so they get a drawing of a cable between two towers.
+fn lift_icon(ui: &egui::Ui, rect: egui::Rect) {
+	let painter = ui.painter_at(rect);
+	let stroke = ui.visuals().widgets.inactive.fg_stroke;
+	let (bottom, top) = (rect.left_bottom() + egui::vec2(12., -8.), rect.right_top() + egui::vec2(-12., 20.));
+	let tower = egui::vec2(0., 14.);
+	painter.line_segment([bottom, bottom - tower], stroke);
+	painter.line_segment([top, top - tower], stroke);
+	painter.line_segment([bottom - tower, top - tower], stroke);
+	for i in 1..4 {
+		let seat = (bottom - tower).lerp(top - tower, i as f32 / 4.);
+		painter.line_segment([seat, seat + egui::vec2(0., 8.)], stroke);
+	}
+}
@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::guest::Guest;
+use crate::staff::{StaffMember, StaffRole};
+use crate::grooming::Snowcat;
+use crate::camera::{
+	CameraSettings, CameraMode, CameraFollow, Followed, Agents, describe_followed,
+	bookmark::{CameraBookmarks, CameraTransition, CameraView, Flight},
+	flythrough::{Flythrough, FlythroughPlayer, Keyframe},
+};
+use crate::grid::{CellHeights, PlacedObjects, object::{structure::StructureTypes, lift::LiftTypes}};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut settings: ResMut<CameraSettings>,
+	mut follow: ResMut<CameraFollow>,
+	(mut bookmarks, mut transition): (ResMut<CameraBookmarks>, ResMut<CameraTransition>),
+	(mut flythrough, mut player): (ResMut<Flythrough>, ResMut<FlythroughPlayer>),
+	(heights, objects, structure_types, lift_types): (Res<CellHeights>, Res<PlacedObjects>, Res<StructureTypes>, Res<LiftTypes>),
+	camera: Single<&Transform, With<Camera3d>>,
+	agents: Agents,
+	(guests, staff, snowcats): (Query<Entity, With<Guest>>, Query<(Entity, &StaffMember)>, Query<Entity, With<Snowcat>>),
+) {
+	egui::Window::new("Camera")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.horizontal(|ui| {
+			ui.label("Mode (V):");
+			for mode in CameraMode::ALL {
+				if ui.selectable_label(settings.mode == mode, mode.name()).clicked() && settings.mode != mode {
+					settings.switch_mode(mode, &camera, &heights);
+				}
+			}
+		});
+		match settings.mode {
+			CameraMode::Free => {
+				ui.label("WASD moves, Q and E go down and up, and the right mouse button looks around.");
+			},
+			CameraMode::Orbit => {
+				ui.label("WASD pans, Q and E turn, the scroll wheel zooms, and the middle mouse button orbits.");
+				let mut edge_scrolling = settings.edge_scrolling;
+				ui.checkbox(&mut edge_scrolling, "Pan at the edges of the window");
+				if edge_scrolling != settings.edge_scrolling {
+					settings.edge_scrolling = edge_scrolling;
+				}
+			},
+		}
+
+		ui.separator();
+		match follow.0 {
+			Some(followed) => {
+				ui.horizontal(|ui| {
+					ui.label(format!("Following: {}", describe_followed(followed, &agents, &objects, &structure_types, &lift_types)));
+					if ui.button("Stop").clicked() {follow.0 = None}
+				});
+			},
+			None => {ui.label("Not following anything. Select something to follow it, or follow:");},
+		}
+		// Each button follows the next one of its kind, so pressing it again goes through all of them.
+		let mut guest_ids: Vec<Entity> = guests.iter().collect();
+		let mut patrol_ids: Vec<Entity> = staff.iter().filter(|(_, member)| member.role == StaffRole::SkiPatrol).map(|(entity, _)| entity).collect();
+		let mut snowcat_ids: Vec<Entity> = snowcats.iter().collect();
+		ui.horizontal(|ui| {
+			for (name, entities) in [("Guest", &mut guest_ids), ("Ski patrol", &mut patrol_ids), ("Snowcat", &mut snowcat_ids)] {
+				entities.sort();
+				let current = entities.iter().position(|entity| follow.0 == Some(Followed::Agent(*entity)));
+				let next = entities.get(current.map_or(0, |i| (i + 1) % entities.len())).copied();
+				if ui.add_enabled(next.is_some(), egui::Button::new(format!("Next {}", name.to_lowercase()))).clicked() && let Some(entity) = next {
+					follow.0 = Some(Followed::Agent(entity));
+				}
+			}
+		});
+
+		ui.separator();
+		ui.label("Bookmarks: ctrl and a number saves, the number flies back.");
+		egui::Grid::new("camera_bookmarks").show(ui, |ui| {
+			for slot in 0..bookmarks.0.len() {
+				ui.label(format!("{}", slot + 1));
+				if ui.button("Save").clicked() {
+					bookmarks.0[slot] = Some(CameraView::capture(&settings, &camera));
+				}
+				if let Some(view) = bookmarks.0[slot] {
+					if ui.button("Go").clicked() {
+						transition.0 = Some(Flight::new(view, &settings, &camera, &heights));
+						follow.0 = None;
+						player.elapsed = None;
+					}
+					if ui.button("Forget").clicked() {bookmarks.0[slot] = None}
+				}
+				ui.end_row();
+			}
+		});
+
+		ui.separator();
+		ui.label(format!("Flythrough: {} keyframes, {:.0} seconds. Saved with the resort.", flythrough.keyframes.len(), flythrough.duration()));
+		ui.horizontal(|ui| {
+			if ui.button("Add keyframe").clicked() {flythrough.keyframes.push(Keyframe::from(*camera))}
+			if ui.add_enabled(!flythrough.keyframes.is_empty(), egui::Button::new("Remove last")).clicked() {
+				flythrough.keyframes.pop();
+			}
+			if ui.add_enabled(!flythrough.keyframes.is_empty(), egui::Button::new("Clear")).clicked() {
+				flythrough.keyframes.clear();
+			}
+		});
+		ui.horizontal(|ui| {
+			let mut segment_seconds = flythrough.segment_seconds;
+			ui.label("Seconds between keyframes:");
+			ui.add(egui::DragValue::new(&mut segment_seconds).range(0.5..=30.).speed(0.1));
+			if segment_seconds != flythrough.segment_seconds {
+				flythrough.segment_seconds = segment_seconds;
+			}
+		});
+		match player.elapsed {
+			Some(elapsed) => {
+				ui.horizontal(|ui| {
+					ui.label(format!("Playing, {:.0} / {:.0} seconds", elapsed, flythrough.duration()));
+					if ui.button("Stop").clicked() {player.elapsed = None}
+				});
+			},
+			None => {
+				if ui.add_enabled(flythrough.keyframes.len() >= 2, egui::Button::new("Play")).clicked() {
+					player.elapsed = Some(0.);
+					transition.0 = None;
+				}
+			},
+		}
+	});
+}
@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::clock::{GameClock, GameSpeed};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut clock: ResMut<GameClock>,
+) {
+	egui::Window::new("Clock")
+	.collapsible(false)
+	.resizable(false)
+	.anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0., 8.))
+	.title_bar(false)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.horizontal(|ui| {
+			ui.label(format!("Year {}, day {} ({:?}) {}", clock.year() + 1, clock.day_of_year() + 1, clock.weekday(), clock.time_string()));
+			ui.label(clock.season_phase().name());
+			ui.separator();
+			for speed in GameSpeed::ALL {
+				if ui.selectable_label(clock.speed == speed, speed.name()).clicked() {
+					clock.set_speed(speed);
+				}
+			}
+		});
+	});
+}
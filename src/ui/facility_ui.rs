@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::facility::{Facilities, placed_facilities, capacity};
+use crate::guest::{Guest, Need};
+use crate::staff::Staffing;
+use crate::grid::{
+	PlacedObjects,
+	object::{ObjectInstance, structure::StructureTypes},
+};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	facilities: Res<Facilities>,
+	staffing: Res<Staffing>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	guests: Query<&Guest>,
+) {
+	egui::Window::new("Facilities")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		let mut placed: Vec<_> = placed_facilities(&objects, &structure_types).collect();
+		placed.sort_by_key(|(instance_id, _, facility)| (facility.need, *instance_id));
+		if placed.is_empty() {
+			ui.label("No facilities built.");
+			return
+		}
+		egui::Grid::new("facilities").striped(true).show(ui, |ui| {
+			for heading in ["Facility", "Need", "Queue", "In use", "Served today", "Revenue today", "Yesterday", "Total"] {
+				ui.label(heading);
+			}
+			ui.end_row();
+			for (instance_id, position, facility) in placed {
+				let name = match objects.get(&instance_id) {
+					Some(ObjectInstance::Structure(instance)) => structure_types.0.get(&instance.type_id).map_or("Unknown", |structure_type| structure_type.name),
+					_ => "Unknown",
+				};
+				let state = facilities.0.get(&instance_id).cloned().unwrap_or_default();
+				ui.label(format!("{} at {}, {}", name, position.x, position.y));
+				ui.label(facility.need.name());
+				if facility.need == Need::Parking {
+					ui.label("");
+					ui.label(format!("{} / {}", guests.iter().filter(|guest| guest.parked == Some(instance_id)).count(), facility.capacity));
+					ui.label("");
+				} else {
+					ui.label(format!("{}", state.queue.len()));
+					ui.label(format!("{} / {}", state.serving, capacity(&instance_id, &facility, &staffing)));
+					ui.label(format!("{}", state.served_today));
+				}
+				ui.label(format!("{}", state.revenue_today));
+				ui.label(format!("{}", state.revenue_yesterday));
+				ui.label(format!("{}", state.revenue_total));
+				ui.end_row();
+			}
+		});
+	});
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::economy::{
+	Economy, Money,
+	ledger::Category,
+	loan::{Loans, TakeLoan, RepayLoan},
+};
+
+/// Amounts typed in for borrowing and paying back.
+pub struct LoanAmounts {
+	borrow: Money,
+	repay: Money,
+}
+impl Default for LoanAmounts {
+	fn default() -> Self {Self {borrow: 50_000, repay: 10_000}}
+}
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut commands: Commands,
+	mut amounts: Local<LoanAmounts>,
+	economy: Res<Economy>,
+	loans: Res<Loans>,
+) {
+	egui::Window::new("Finances")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.label(format!("Balance: {}", economy.balance));
+		ui.label(format!("Income: {}, expenses: {}", economy.ledger.income(), economy.ledger.expenses()));
+		egui::CollapsingHeader::new("Ledger").show(ui, |ui| {
+			egui::Grid::new("ledger").striped(true).show(ui, |ui| {
+				for category in Category::ALL {
+					let total = economy.ledger.total(category);
+					if total == 0 {continue}
+					ui.label(category.name());
+					ui.label(format!("{}", total));
+					ui.end_row();
+				}
+			});
+			ui.label(format!("{} transactions", economy.ledger.transactions().len()));
+		});
+
+		ui.separator();
+		let owed = loans.total_owed();
+		let interest: Money = loans.0.iter().map(|loan| loan.daily_interest_cost()).sum();
+		ui.label(format!("Owing {} of {} credit, {} in interest per day", owed, Loans::CREDIT_LIMIT, interest));
+		ui.horizontal(|ui| {
+			ui.add(egui::DragValue::new(&mut amounts.borrow).range(1..=Loans::CREDIT_LIMIT).speed(1_000));
+			let fits = owed + amounts.borrow <= Loans::CREDIT_LIMIT;
+			if ui.add_enabled(fits, egui::Button::new("Borrow")).clicked() {
+				commands.trigger(TakeLoan(amounts.borrow));
+			}
+		});
+		if loans.0.is_empty() {return}
+		ui.horizontal(|ui| {
+			ui.label("Pay back:");
+			ui.add(egui::DragValue::new(&mut amounts.repay).range(1..=Loans::CREDIT_LIMIT).speed(1_000));
+		});
+		egui::Grid::new("loans").striped(true).show(ui, |ui| {
+			for (index, loan) in loans.0.iter().enumerate() {
+				ui.label(format!("{}", loan.principal));
+				ui.label(format!("{:.2}% per day", loan.daily_interest * 100.));
+				let amount = amounts.repay.min(loan.principal);
+				if ui.add_enabled(economy.can_afford(amount), egui::Button::new(format!("Repay {}", amount))).clicked() {
+					commands.trigger(RepayLoan {index: index, amount: amount});
+				}
+				if ui.add_enabled(economy.can_afford(loan.principal), egui::Button::new("Repay all")).clicked() {
+					commands.trigger(RepayLoan {index: index, amount: loan.principal});
+				}
+				ui.end_row();
+			}
+		});
+	});
+}
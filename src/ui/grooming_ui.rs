@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::economy::Money;
+use crate::grooming::{Grooming, GroomingSettings, GroomingPlans, GroomingPlan, Snowcat};
+use crate::staff::{StaffMember, StaffRole, Staffing};
+use crate::tool::{Tool, ToolState};
+use crate::grid::{
+	PlacedObjects,
+	object::{ObjectInstance, structure::{StructureTypes, StructureRole}},
+};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut settings: ResMut<GroomingSettings>,
+	mut plans: ResMut<GroomingPlans>,
+	mut tools: ResMut<ToolState>,
+	grooming: Res<Grooming>,
+	staffing: Res<Staffing>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	snowcats: Query<&Snowcat>,
+	staff: Query<&StaffMember>,
+) {
+	egui::Window::new("Grooming")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		let (mut start, mut end, mut show_routes) = (settings.start / 60., settings.end / 60., settings.show_routes);
+		ui.horizontal(|ui| {
+			ui.label("Shift from");
+			ui.add(egui::DragValue::new(&mut start).range(0..=23).suffix(":00"));
+			ui.label("to");
+			ui.add(egui::DragValue::new(&mut end).range(0..=23).suffix(":00"));
+		});
+		ui.checkbox(&mut show_routes, "Show routes");
+		if start * 60. != settings.start || end * 60. != settings.end || show_routes != settings.show_routes {
+			*settings = GroomingSettings {start: start.floor() * 60., end: end.floor() * 60., show_routes: show_routes};
+		}
+		let drivers: Vec<&StaffMember> = staff.iter().filter(|member| member.role == StaffRole::SnowcatDriver).collect();
+		ui.label(format!("Drivers: {} hired, {} on duty. Hire them in the staff window for the night shift.", drivers.len(), staffing.drivers.values().sum::<usize>()));
+		ui.label(format!("Snowcats out: {}", snowcats.iter().count()));
+		if !grooming.skipped.is_empty() {
+			ui.label(format!("{} cells can't be reached until the terrain changes", grooming.skipped.len()));
+		}
+
+		ui.separator();
+		let mut garages: Vec<_> = objects.iter()
+		.filter_map(|(instance_id, object)| match object {ObjectInstance::Structure(instance) => Some((instance_id, instance)), _ => None})
+		.filter_map(|(instance_id, instance)| match structure_types.0.get(&instance.type_id).map(|structure_type| structure_type.role) {
+			Some(StructureRole::SnowcatGarage(capacity)) => Some((*instance_id, instance.position, capacity)),
+			_ => None,
+		})
+		.collect();
+		garages.sort_by_key(|(instance_id, _, _)| *instance_id);
+		if garages.is_empty() {
+			ui.label("Build a snowcat garage to start grooming.");
+		}
+		for (instance_id, position, capacity) in garages {
+			ui.horizontal(|ui| {
+				ui.label(format!("Garage at {}, {} ({} snowcats, {} drivers on duty)", position.x, position.y, capacity, staffing.drivers.get(&instance_id).copied().unwrap_or_default()));
+				let route_length = match plans.plans.get(&instance_id) {Some(GroomingPlan::Route(route)) => Some(route.len()), _ => None};
+				match route_length {
+					Some(length) => {
+						ui.label(format!("Route of {} cells", length));
+						if ui.button("Use priority").clicked() {
+							plans.plans.remove(&instance_id);
+						}
+					},
+					None => {ui.label("Worst snow first");},
+				}
+				let recording = plans.recording == Some(instance_id);
+				if ui.selectable_label(recording, "Record route").clicked() {
+					plans.recording = if recording {None} else {Some(instance_id)};
+					// Otherwise the clicks would use the tool as well.
+					if !recording && tools.tool != Tool::None {tools.tool = Tool::None}
+				}
+			});
+		}
+
+		ui.separator();
+		egui::Grid::new("grooming_costs").striped(true).show(ui, |ui| {
+			ui.label("");
+			ui.label("Tonight");
+			ui.label("Last night");
+			ui.end_row();
+			ui.label("Cells groomed");
+			ui.label(format!("{}", grooming.today.cells_groomed));
+			ui.label(format!("{}", grooming.yesterday.cells_groomed));
+			ui.end_row();
+			ui.label("Fuel");
+			ui.label(format!("{:.0} l", grooming.today.fuel));
+			ui.label(format!("{:.0} l", grooming.yesterday.fuel));
+			ui.end_row();
+			ui.label("Fuel cost");
+			ui.label(format!("{}", grooming.today.fuel_cost()));
+			ui.label(format!("{}", grooming.yesterday.fuel_cost()));
+			ui.end_row();
+			ui.label("Driver wages");
+			let wages: Money = drivers.iter().map(|member| member.shift.wage(member.role)).sum();
+			ui.label(format!("{}", wages));
+			ui.label(format!("{}", wages));
+			ui.end_row();
+		});
+	});
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::clock::{GameClock, MINUTES_PER_DAY, minute_string};
+use crate::camera::{CameraFollow, Followed};
+use crate::facility::{Facilities, FacilityPrices, capacity};
+use crate::staff::{StaffMember, Staffing};
+use crate::accident::Incidents;
+use crate::avalanche::Avalanches;
+use crate::util::hex::cell_slope;
+use crate::tool::{
+	ToolState, Tool,
+	select::{CarryMode, carry_problem, pick_up},
+	pick::Target,
+};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::PisteGrade,
+	snow::CellSnow,
+	edit::{Edit, EditStep, EditGrid},
+	object::{
+		ObjectInstance, SellObject,
+		lift::{LiftTypes, LiftQueues},
+		structure::{StructureTypes, StructureRole},
+	},
+};
+
+/// Details and settings of the selected object, or of the inspected piste cell.
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut commands: Commands,
+	mut state: ResMut<ToolState>,
+	mut objects: ResMut<PlacedObjects>,
+	mut facility_prices: ResMut<FacilityPrices>,
+	mut follow: ResMut<CameraFollow>,
+	(structure_types, lift_types): (Res<StructureTypes>, Res<LiftTypes>),
+	(clock, queues, facilities, staffing): (Res<GameClock>, Res<LiftQueues>, Res<Facilities>, Res<Staffing>),
+	(heights, surfaces, snow, incidents, avalanches): (Res<CellHeights>, Res<CellSurfaces>, Res<CellSnow>, Res<Incidents>, Res<Avalanches>),
+	staff: Query<&StaffMember>,
+) {
+	let target = match (state.selection.len(), state.selection.first(), state.inspected_cell) {
+		(1, Some(instance_id), _) => Target::Object(*instance_id),
+		(0, _, Some(pos)) => Target::Cell(pos),
+		_ => return,
+	};
+	egui::Window::new("Inspector")
+	.collapsible(true)
+	.resizable(false)
+	.show(contexts.ctx_mut(), |ui| {
+		if let Target::Cell(pos) = target {
+			ui.heading(format!("Piste at {}, {}", pos.x, pos.y));
+			ui.label(format!("Grade: {}", PisteGrade::from_slope(cell_slope(&heights.0, &pos)).name()));
+			let cell = snow.0.get(&pos).copied().unwrap_or_default();
+			ui.label(format!("Snow: {:.0} cm, {}", cell.depth, cell.quality.name()));
+			ui.label(format!("Accidents here: {}", incidents.heatmap.get(&pos).copied().unwrap_or_default()));
+			ui.label(format!("Avalanche risk: {:.0}%", avalanches.risk(&pos) * 100.));
+			if let Some(days) = avalanches.closed.get(&pos) {
+				ui.label(format!("Closed after a slide for {} more days.", days));
+			}
+			if ui.button("Follow with camera").clicked() {follow.0 = Some(Followed::Target(target))}
+			return
+		}
+		let Some(instance_id) = target.object() else {return};
+		let Some(object) = objects.get(&instance_id).cloned() else {
+			ui.label("The selected object is gone.");
+			return
+		};
+		let [first, ..] = object.cells(&structure_types)[..] else {return};
+		ui.heading(format!("{} at {}, {}", object.name(&structure_types, &lift_types), first.x, first.y));
+		let workers: Vec<&StaffMember> = staff.iter().filter(|member| member.workplace == Some(instance_id)).collect();
+		match &object {
+			ObjectInstance::Lift(lift) => {
+				let Some(lift_type) = lift_types.0.get(&lift.type_id) else {return};
+				let queue = queues.0.get(&instance_id).copied().unwrap_or_default();
+				ui.label(format!("Status: {}", lift.status(clock.minute)));
+				ui.label(format!("Length: {} cells, {} towers", lift.bottom.unsigned_distance_to(lift.top), lift.towers.len()));
+				ui.label(format!("Throughput: {} guests per hour", lift_type.capacity));
+				ui.label(format!("Queue: {:.0} guests, {:.0} minutes wait", queue.waiting.ceil(), queue.wait_minutes(lift_type)));
+				ui.label(format!("Rides today: {}, yesterday: {}", queue.rides_today, queue.rides_yesterday));
+				let mut edited = lift.clone();
+				let mut open = !edited.closed;
+				ui.checkbox(&mut open, "Open");
+				edited.closed = !open;
+				ui.horizontal(|ui| {
+					ui.label("Hours:");
+					let time = |minute: f64, _| minute_string(minute as f32);
+					ui.add(egui::DragValue::new(&mut edited.opens_at).range(0.0..=lift.closes_at).speed(5.).custom_formatter(time));
+					ui.label("to");
+					ui.add(egui::DragValue::new(&mut edited.closes_at).range(lift.opens_at..=MINUTES_PER_DAY - 1.).speed(5.).custom_formatter(time));
+				});
+				if edited != *lift && let Some(ObjectInstance::Lift(lift)) = objects.get_mut(&instance_id) {
+					*lift = edited;
+				}
+			},
+			ObjectInstance::Structure(instance) => {
+				let Some(structure_type) = structure_types.0.get(&instance.type_id) else {return};
+				if let StructureRole::Facility(facility) = structure_type.role {
+					let facility_state = facilities.0.get(&instance_id).cloned().unwrap_or_default();
+					ui.label(format!("Satisfies: {}", facility.need.name()));
+					ui.horizontal(|ui| {
+						let mut price = facility_prices.get(&instance_id, &facility);
+						ui.label("Price:");
+						ui.add(egui::DragValue::new(&mut price).range(0..=facility.price * 5));
+						if price != facility_prices.get(&instance_id, &facility) {
+							facility_prices.0.insert(instance_id, price);
+						}
+						if facility_prices.0.contains_key(&instance_id) && ui.button(format!("Reset to {}", facility.price)).clicked() {
+							facility_prices.0.remove(&instance_id);
+						}
+					});
+					ui.label(format!("Queue: {}, in use: {} / {}", facility_state.queue.len(), facility_state.serving, capacity(&instance_id, &facility, &staffing)));
+					ui.label(format!("Served today: {}", facility_state.served_today));
+					ui.label(format!("Revenue today: {}, yesterday: {}, total: {}", facility_state.revenue_today, facility_state.revenue_yesterday, facility_state.revenue_total));
+				}
+			},
+		}
+		if !workers.is_empty() {
+			ui.label("Staff:");
+			for member in workers {
+				ui.label(format!("{}, {} shift: {}", member.role.name(), member.shift.name(), member.status()));
+			}
+		}
+		ui.separator();
+		ui.horizontal(|ui| {
+			if let ObjectInstance::Structure(instance) = &object && instance.rotation.is_some() {
+				let turned = object.moved(instance.position, instance.position, 1);
+				let problem = carry_problem(&[(instance_id, turned.clone())], CarryMode::Move, &objects, &structure_types, &lift_types, &heights, &surfaces);
+				let button = ui.add_enabled(problem.is_none(), egui::Button::new("Rotate"));
+				if button.clicked() {
+					commands.trigger(EditGrid(EditStep::new(vec![Edit::Remove {instance_id: instance_id, object: object.clone()}, Edit::Place {instance_id: instance_id, object: turned}], 0)));
+				}
+				if let Some(problem) = problem {button.on_disabled_hover_text(problem);}
+			}
+			if ui.button("Move").clicked() {
+				state.tool = Tool::Select;
+				pick_up(&mut state, CarryMode::Move, &objects, &structure_types);
+			}
+			let refund = object.refund(&structure_types, &lift_types).unwrap_or(0);
+			if ui.button(format!("Sell (+{})", refund)).clicked() {
+				commands.trigger(SellObject(instance_id));
+				state.selection.remove(&instance_id);
+			}
+			if ui.button("Follow with camera").clicked() {follow.0 = Some(Followed::Target(target))}
+		});
+	});
+}
+
@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::grid::geometry::overlay::Overlays;
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut overlays: ResMut<Overlays>,
+) {
+	egui::Window::new("Overlays")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		let mut active = overlays.active;
+		ui.radio_value(&mut active, None, "None");
+		for (i, provider) in overlays.providers.iter().enumerate() {
+			ui.radio_value(&mut active, Some(i), provider.name);
+		}
+		// Only touch the resource when the choice changes, since changing it recolors the terrain.
+		if active != overlays.active {
+			overlays.active = active;
+		}
+		if let Some(provider) = overlays.active_provider() {
+			ui.separator();
+			ui.label(provider.legend);
+		}
+	});
+}
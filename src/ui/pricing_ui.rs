@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::economy::{
+	Money,
+	pricing::{Ticket, TicketPrices, ResortValue, SeasonPassHolders, PRICE_CEILING, demand, daily_arrivals, season_pass_market},
+};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut prices: ResMut<TicketPrices>,
+	value: Res<ResortValue>,
+	holders: Res<SeasonPassHolders>,
+) {
+	egui::Window::new("Ticket Prices")
+	.collapsible(true)
+	.resizable(false)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.label(format!("Resort value: {:.2}", value.score()));
+		ui.label(format!("{} lifts, {} vertical drop, {} piste grades, {:.0}% snow quality", value.lift_count, value.vertical_drop, value.piste_variety, value.snow_quality * 100.));
+		for ticket in Ticket::ALL {
+			ui.separator();
+			let fair_price = value.fair_price(ticket);
+			let mut price = prices.get(ticket);
+			ui.horizontal(|ui| {
+				ui.label(ticket.name());
+				ui.add(egui::DragValue::new(&mut price).range(0..=10_000));
+				ui.label(format!("(fair: {:.0})", fair_price));
+			});
+			if price != prices.get(ticket) {
+				*prices.get_mut(ticket) = price;
+			}
+			match ticket {
+				Ticket::DayPass | Ticket::HalfDay => ui.label(format!("Expected guests per day: {:.0}", daily_arrivals(&value, &prices, ticket))),
+				Ticket::SeasonPass => ui.label(format!("Holders: {} (wanted: {:.0})", holders.0, season_pass_market(&value, &prices))),
+			};
+			demand_plot(ui, price, fair_price);
+		}
+	});
+}
+
+/// Draws the demand curve for prices between zero and the price ceiling, with a marker at the current price.
+fn demand_plot(ui: &mut egui::Ui, price: Money, fair_price: f32) {
+	let (response, painter) = ui.allocate_painter(egui::Vec2::new(240., 80.), egui::Sense::hover());
+	let rect = response.rect;
+	let max_price = (fair_price * PRICE_CEILING).max(1.);
+	/// Highest value of `demand`, which is reached when the ticket is free.
+	const MAX_DEMAND: f32 = 2.;
+	let to_screen = |price: f32, demand: f32| egui::Pos2::new(
+		rect.left() + rect.width() * price / max_price,
+		rect.bottom() - rect.height() * demand / MAX_DEMAND,
+	);
+
+	let axis_stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+	painter.line_segment([rect.left_bottom(), rect.right_bottom()], axis_stroke);
+	painter.line_segment([rect.left_bottom(), rect.left_top()], axis_stroke);
+	// Demand at the fair price.
+	painter.line_segment([to_screen(fair_price, 0.), to_screen(fair_price, MAX_DEMAND)], axis_stroke);
+
+	let points: Vec<egui::Pos2> = (0..=60).map(|i| {
+		let curve_price = max_price * i as f32 / 60.;
+		to_screen(curve_price, demand(curve_price as Money, fair_price))
+	}).collect();
+	painter.add(egui::Shape::line(points, egui::Stroke::new(2., ui.visuals().hyperlink_color)));
+
+	let marker = to_screen((price as f32).min(max_price), demand(price, fair_price));
+	painter.circle_filled(marker, 4., ui.visuals().warn_fg_color);
+
+	response.on_hover_text(format!("Demand at current price: {:.0}%", demand(price, fair_price) * 100.));
+}
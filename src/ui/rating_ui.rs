@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::clock::DAYS_PER_YEAR;
+use crate::guest::Guest;
+use crate::satisfaction::ResortRating;
+
+/// Number of recent reviews shown.
+const SHOWN_REVIEWS: usize = 10;
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	rating: Res<ResortRating>,
+	guests: Query<&Guest>,
+) {
+	egui::Window::new("Reviews")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.label(format!("Rating: {:.1} stars from {} reviews", rating.stars(), rating.reviews.len()));
+		let count = guests.iter().count();
+		if count > 0 {
+			let satisfaction = guests.iter().map(|guest| guest.experience.satisfaction()).sum::<f32>() / count as f32;
+			ui.label(format!("Current guests: {:.0}% satisfied", satisfaction * 100.));
+		}
+
+		ui.separator();
+		ui.label("Top complaints");
+		let complaints = rating.top_complaints();
+		if complaints.is_empty() {
+			ui.label("None.");
+		}
+		for (factor, count) in complaints {
+			ui.label(format!("{}: {} guests", factor.name(), count));
+		}
+
+		ui.separator();
+		ui.label("Recent reviews");
+		for review in rating.reviews.iter().take(SHOWN_REVIEWS) {
+			ui.label(format!("Year {}, day {}: {}", review.day / DAYS_PER_YEAR + 1, review.day % DAYS_PER_YEAR + 1, review.text()));
+		}
+	});
+}
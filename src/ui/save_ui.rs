@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::save::{SaveFormat, SaveResort, LoadResort};
+
+/// Save name and format being edited.
+pub struct SaveSlot {
+	name: String,
+	format: SaveFormat,
+}
+impl Default for SaveSlot {
+	fn default() -> Self {Self {name: "resort".to_string(), format: SaveFormat::default()}}
+}
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut commands: Commands,
+	mut slot: Local<SaveSlot>,
+) {
+	egui::Window::new("Save / Load")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.horizontal(|ui| {
+			ui.label("Name:");
+			ui.text_edit_singleline(&mut slot.name);
+		});
+		ui.horizontal(|ui| {
+			ui.label("Format:");
+			for format in SaveFormat::ALL {
+				ui.selectable_value(&mut slot.format, format, format.name());
+			}
+		});
+		let path = slot.format.path(slot.name.trim());
+		ui.label(format!("File: {}", path.display()));
+		let valid = !slot.name.trim().is_empty();
+		ui.horizontal(|ui| {
+			if ui.add_enabled(valid, egui::Button::new("Save")).clicked() {
+				commands.trigger(SaveResort(path.clone()));
+			}
+			if ui.add_enabled(valid && path.exists(), egui::Button::new("Load")).clicked() {
+				commands.trigger(LoadResort(path.clone()));
+			}
+		});
+		ui.label("F5 quick saves, F9 quick loads.");
+	});
+}
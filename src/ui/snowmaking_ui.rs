@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::economy::Money;
+use crate::snowmaking::{Snowmaking, SnowmakingSettings, SnowmakingUsage, CannonStatus, skiable_estimate};
+use crate::grid::{
+	CellSurfaces, PlacedObjects,
+	snow::CellSnow,
+	object::{ObjectInstance, structure::{StructureTypes, StructureRole}},
+};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut settings: ResMut<SnowmakingSettings>,
+	snowmaking: Res<Snowmaking>,
+	snow: Res<CellSnow>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+) {
+	egui::Window::new("Snowmaking")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		let (mut enabled, mut target_depth, mut show_coverage) = (settings.enabled, settings.target_depth, settings.show_coverage);
+		ui.checkbox(&mut enabled, "Cannons enabled");
+		ui.checkbox(&mut show_coverage, "Show coverage");
+		ui.horizontal(|ui| {
+			ui.label("Target depth");
+			ui.add(egui::DragValue::new(&mut target_depth).range(0..=300).suffix(" cm"));
+		});
+		if enabled != settings.enabled || target_depth != settings.target_depth || show_coverage != settings.show_coverage {
+			*settings = SnowmakingSettings {enabled: enabled, target_depth: target_depth, show_coverage: show_coverage};
+		}
+
+		ui.separator();
+		ui.label(format!("Reservoir: {:.0} / {:.0} m³", snowmaking.reservoir, snowmaking.reservoir_capacity));
+		for status in [CannonStatus::Running, CannonStatus::Idle, CannonStatus::Disabled, CannonStatus::TooWarm, CannonStatus::NoWater, CannonStatus::ReservoirEmpty] {
+			let count = snowmaking.statuses.values().filter(|cannon_status| **cannon_status == status).count();
+			if count > 0 {
+				ui.label(format!("{}: {}", status.name(), count));
+			}
+		}
+
+		ui.separator();
+		let upkeep: Money = objects.iter()
+		.filter_map(|(_, object)| match object {ObjectInstance::Structure(instance) => structure_types.0.get(&instance.type_id), _ => None})
+		.filter(|structure_type| matches!(structure_type.role, StructureRole::SnowCannon(_)))
+		.map(|structure_type| structure_type.upkeep)
+		.sum();
+		egui::Grid::new("snowmaking_costs").striped(true).show(ui, |ui| {
+			ui.label("");
+			ui.label("Today");
+			ui.label("Yesterday");
+			ui.end_row();
+			let rows: [(&str, fn(&SnowmakingUsage) -> String); 4] = [
+				("Water", |usage| format!("{:.0} m³", usage.water)),
+				("Energy", |usage| format!("{:.0} kWh", usage.energy)),
+				("Energy cost", |usage| format!("{}", usage.energy_cost())),
+				("Snow made", |usage| format!("{:.0} cm", usage.snow)),
+			];
+			for (name, value) in rows {
+				ui.label(name);
+				ui.label(value(&snowmaking.today));
+				ui.label(value(&snowmaking.yesterday));
+				ui.end_row();
+			}
+			ui.label("Cannon upkeep");
+			ui.label(format!("{}", upkeep));
+			ui.label(format!("{}", upkeep));
+			ui.end_row();
+		});
+		if snowmaking.yesterday.snow > 0. {
+			ui.label(format!("Yesterday's cost per cm of snow: {:.2}", (snowmaking.yesterday.energy_cost() + upkeep) as f32 / snowmaking.yesterday.snow));
+		}
+
+		ui.separator();
+		let estimate = skiable_estimate(&snow, &surfaces.0, &objects, &structure_types);
+		ui.label(format!("{} cells covered by cannons with water.", estimate.covered_cells));
+		if estimate.hours > 0. {
+			ui.label(format!(
+				"Making them all skiable takes {:.0} cold hours, {:.0} m³ of water and costs {} in energy.",
+				estimate.hours, estimate.water, estimate.energy_cost,
+			));
+		} else if estimate.covered_cells > 0 {
+			ui.label("All covered cells are skiable.");
+		}
+	});
+}
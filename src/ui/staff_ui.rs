@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::economy::Money;
+use crate::staff::{StaffMember, StaffRole, Shift, Staffing, HireStaff, FireStaff, AssignStaff};
+use crate::grid::{
+	PlacedObjects,
+	object::{ObjectInstanceId, ObjectInstance, structure::StructureTypes, lift::LiftTypes},
+};
+
+/// Shift that newly hired staff get.
+#[derive(Default)]
+pub struct HiringShift(Shift);
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut commands: Commands,
+	mut hiring_shift: Local<HiringShift>,
+	staffing: Res<Staffing>,
+	objects: Res<PlacedObjects>,
+	structure_types: Res<StructureTypes>,
+	lift_types: Res<LiftTypes>,
+	staff: Query<(Entity, &StaffMember)>,
+) {
+	egui::Window::new("Staff")
+	.collapsible(true)
+	.resizable(true)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		let object_name = |instance_id: &ObjectInstanceId| -> String {
+			match objects.get(instance_id) {
+				Some(ObjectInstance::Structure(instance)) => format!("{} at {}, {}", structure_types.0.get(&instance.type_id).map_or("Unknown", |structure_type| structure_type.name), instance.position.x, instance.position.y),
+				Some(ObjectInstance::Lift(lift)) => format!("{} at {}, {}", lift_types.0.get(&lift.type_id).map_or("Unknown", |lift_type| lift_type.name), lift.bottom.x, lift.bottom.y),
+				None => "Removed".to_string(),
+			}
+		};
+
+		ui.horizontal(|ui| {
+			ui.label("Shift for new staff:");
+			for shift in Shift::ALL {
+				ui.selectable_value(&mut hiring_shift.0, shift, shift.name());
+			}
+		});
+		ui.horizontal(|ui| {
+			for role in StaffRole::ALL {
+				if ui.button(format!("Hire {} ({})", role.name().to_lowercase(), hiring_shift.0.wage(role))).clicked() {
+					commands.trigger(HireStaff {role: role, shift: hiring_shift.0});
+				}
+			}
+		});
+
+		let wages: Money = staff.iter().map(|(_, member)| member.shift.wage(member.role)).sum();
+		let unstaffed = objects.iter().filter(|(_, object)| matches!(object, ObjectInstance::Lift(lift) if lift.unstaffed)).count();
+		ui.label(format!("{} staff, {} in wages per day", staff.iter().count(), wages));
+		ui.label(format!("Lifts without an operator: {}", unstaffed));
+		ui.label(format!("Instructors teaching: {}, cashiers selling: {}, patrolled cells: {}", staffing.instructors.values().sum::<usize>(), staffing.cashiers, staffing.patrolled.len()));
+		ui.separator();
+
+		let mut members: Vec<(Entity, &StaffMember)> = staff.iter().collect();
+		members.sort_by_key(|(entity, member)| (member.role, *entity));
+		egui::ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+			egui::Grid::new("staff").striped(true).show(ui, |ui| {
+				for (entity, member) in members {
+					ui.label(member.role.name());
+					ui.label(member.shift.name());
+					ui.label(member.status());
+					let selected = member.workplace.as_ref().map_or("Unassigned".to_string(), object_name);
+					egui::ComboBox::from_id_salt(entity).selected_text(selected).show_ui(ui, |ui| {
+						if ui.selectable_label(member.workplace.is_none(), "Unassigned").clicked() {
+							commands.trigger(AssignStaff {staff: entity, workplace: None});
+						}
+						let mut workplaces: Vec<ObjectInstanceId> = objects.iter()
+						.filter(|(_, object)| member.role.can_work_at(object, &structure_types))
+						.map(|(instance_id, _)| *instance_id)
+						.collect();
+						workplaces.sort();
+						for instance_id in workplaces {
+							if ui.selectable_label(member.workplace == Some(instance_id), object_name(&instance_id)).clicked() {
+								commands.trigger(AssignStaff {staff: entity, workplace: Some(instance_id)});
+							}
+						}
+					});
+					if ui.button("Fire").clicked() {
+						commands.trigger(FireStaff(entity));
+					}
+					ui.end_row();
+				}
+			});
+		});
+	});
+}
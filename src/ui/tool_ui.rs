@@ -0,0 +1,268 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::tool::{
+	ToolState, Tool, EARTH_COST, PISTE_COST, MAX_HEIGHT,
+	brush::{BrushMode, Falloff, MAX_RADIUS, MAX_STRENGTH, earth_cost},
+	piste::{Route, AcceptRoute, MAX_PISTE_RADIUS},
+	lift::{LiftPlan, ConfirmLift, plan_problem, reset_towers},
+	structure::hovered_problem,
+	select::{SelectShape, CarryMode, pick_up},
+	pick::Hover,
+};
+use crate::grid::{
+	CellHeights, CellSurfaces, PlacedObjects,
+	surface::PisteGrade,
+	edit::{EditHistory, Undo, Redo},
+	object::{
+		lift::{LiftType, LiftTypes, Span},
+		structure::StructureTypes,
+	},
+};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	mut commands: Commands,
+	mut state: ResMut<ToolState>,
+	history: Res<EditHistory>,
+	heights: Res<CellHeights>,
+	lift_types: Res<LiftTypes>,
+	structure_types: Res<StructureTypes>,
+	surfaces: Res<CellSurfaces>,
+	objects: Res<PlacedObjects>,
+	hover: Res<Hover>,
+) {
+	egui::Window::new("Tools")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(true)
+	.show(contexts.ctx_mut(), |ui| {
+		let mut tool = state.tool;
+		ui.horizontal(|ui| {
+			for option in Tool::ALL {
+				ui.selectable_value(&mut tool, option, option.name());
+			}
+		});
+		if tool != state.tool {
+			state.tool = tool;
+		}
+		match state.tool {
+			Tool::None => ui.label("Pick a tool to edit the resort."),
+			Tool::Terrain => ui.label(format!("Hold or drag to use the brush, shift swaps raise and lower. Moving earth costs {} per step.", EARTH_COST)),
+			Tool::Piste => ui.label(format!("Click or drag to paint piste, shift to remove it. Costs {} per cell.", PISTE_COST)),
+			Tool::PisteRoute => ui.label("Click a lift's top station, then where the piste should end. Further clicks add waypoints, backspace removes the last."),
+			Tool::Lift => ui.label("Click where the bottom station goes, then the top station. Towers are placed in between and can be dragged."),
+			Tool::Structure => ui.label("Click to place the structure. R turns it, shift+R turns it back."),
+			Tool::Select => ui.label("Click or drag to select objects, shift adds to the selection. M moves them, C copies them."),
+		};
+		if let Some(target) = hover.focused() && (hover.candidates.len() > 1 || target.object().is_some()) {
+			ui.label(format!("Under the cursor: {} ({}/{}, tab cycles)", target.describe(&objects, &structure_types, &lift_types), hover.focus + 1, hover.candidates.len()));
+		}
+		if matches!(state.tool, Tool::Piste | Tool::PisteRoute) {
+			let mut brush = state.piste;
+			ui.add(egui::Slider::new(&mut brush.radius, 0..=MAX_PISTE_RADIUS).text("Width"));
+			if state.tool == Tool::PisteRoute {
+				ui.horizontal(|ui| {
+					ui.label("Easiest:");
+					for grade in PisteGrade::ALL {
+						ui.selectable_value(&mut brush.easiest, grade, grade.name());
+					}
+				});
+				ui.horizontal(|ui| {
+					ui.label("Hardest:");
+					for grade in PisteGrade::ALL {
+						ui.selectable_value(&mut brush.hardest, grade, grade.name());
+					}
+				});
+				brush.hardest = brush.hardest.max(brush.easiest);
+			}
+			if brush != state.piste {
+				state.piste = brush;
+				if let Some(plan) = &mut state.route {
+					plan.route = Route::Pending;
+				}
+			}
+		}
+		if state.tool == Tool::PisteRoute && let Some(plan) = &state.route {
+			let found = matches!(plan.route, Route::Found(_));
+			match &plan.route {
+				_ if plan.end.is_none() => ui.label("Click where the piste should end."),
+				Route::Pending => ui.label("Finding a route..."),
+				Route::Found(route) => ui.label(format!("Proposed piste: {} cells. Enter accepts it, escape cancels.", route.len())),
+				Route::NotFound => ui.label("No downhill route within these grades. Try other grades or waypoints."),
+			};
+			ui.horizontal(|ui| {
+				if ui.add_enabled(found, egui::Button::new("Accept")).clicked() {
+					commands.trigger(AcceptRoute);
+				}
+				if ui.button("Cancel").clicked() {
+					state.route = None;
+				}
+			});
+		}
+		if state.tool == Tool::Lift {
+			let lift_type = state.lift_type;
+			if let Some(selected) = lift_types.0.get(&lift_type) {
+				ui.label(format!("{} (pick another type in the build menu)", selected.name));
+				ui.label(format!("Costs {}. Spans up to {} cells, rising at most {} per cell, with the cable {} above the ground.", selected.cost, selected.max_span, selected.max_gradient, selected.min_clearance));
+				let (mut reset, mut cancel) = (false, false);
+				match &state.lift {
+					None => {ui.label("Click where the bottom station goes.");},
+					Some(LiftPlan {top: None, ..}) => {ui.label("Click where the top station goes.");},
+					Some(plan) => {
+						let spans = selected.spans(&heights.0, &plan.supports());
+						lift_profile(ui, selected, &heights, &spans);
+						for (i, span) in spans.iter().enumerate() {
+							if span.problems.is_empty() {continue}
+							let problems: Vec<&str> = span.problems.iter().map(|problem| problem.describe()).collect();
+							ui.colored_label(ui.visuals().warn_fg_color, format!("Span {}: {}", i + 1, problems.join(", ")));
+						}
+						let problem = plan_problem(plan, &spans, &heights, &surfaces, &objects.occupied(&structure_types));
+						match &problem {
+							Some(problem) => ui.colored_label(ui.visuals().warn_fg_color, problem),
+							None => ui.label("Drag towers to adjust the lift. Enter places it, escape cancels."),
+						};
+						ui.horizontal(|ui| {
+							if ui.add_enabled(problem.is_none(), egui::Button::new("Place")).clicked() {
+								commands.trigger(ConfirmLift);
+							}
+							reset = ui.button("Reset towers").clicked();
+							cancel = ui.button("Cancel").clicked();
+						});
+					},
+				}
+				if reset && let Some(plan) = &mut state.lift {
+					reset_towers(plan, lift_type, &lift_types, &heights);
+				}
+				if cancel {
+					state.lift = None;
+				}
+			}
+		}
+		if state.tool == Tool::Structure {
+			let structure_type = state.structure_type;
+			ui.label(format!("{} (pick another structure in the build menu)", structure_types.0.get(&structure_type).map_or("Unknown", |selected| selected.name)));
+			if structure_types.0.get(&structure_type).is_some_and(|selected| selected.has_rotation) {
+				ui.label(format!("Rotation: {}°", state.rotation as usize * 60));
+			}
+			if hover.focused_cell().is_some() && let Some(problem) = hovered_problem(&state, hover.focused_cell(), &structure_types, &heights, &surfaces, &objects) {
+				ui.colored_label(ui.visuals().warn_fg_color, format!("Can't place here, it would be {}.", problem.describe()));
+			}
+		}
+		if state.tool == Tool::Select {
+			match state.carry {
+				None => {
+					let mut shape = state.select_shape;
+					ui.horizontal(|ui| {
+						ui.label("Drag selects:");
+						for option in SelectShape::ALL {
+							ui.selectable_value(&mut shape, option, option.name());
+						}
+					});
+					if shape != state.select_shape {
+						state.select_shape = shape;
+					}
+					ui.label(format!("{} selected", state.selection.len()));
+					ui.horizontal(|ui| {
+						let any = !state.selection.is_empty();
+						if ui.add_enabled(any, egui::Button::new("Move")).clicked() {
+							pick_up(&mut state, CarryMode::Move, &objects, &structure_types);
+						}
+						if ui.add_enabled(any, egui::Button::new("Duplicate")).clicked() {
+							pick_up(&mut state, CarryMode::Duplicate, &objects, &structure_types);
+						}
+						if ui.add_enabled(any, egui::Button::new("Clear")).clicked() {
+							state.selection.clear();
+						}
+					});
+				},
+				Some(carry) => {
+					let action = match carry.mode {CarryMode::Move => "Moving", CarryMode::Duplicate => "Duplicating"};
+					ui.label(format!("{} {} objects, turned {}°. R turns them, click puts them down.", action, state.selection.len(), carry.turns * 60));
+					if ui.button("Cancel").clicked() {
+						state.carry = None;
+					}
+				},
+			}
+		}
+		if state.tool == Tool::Terrain {
+			let mut brush = state.brush;
+			ui.horizontal_wrapped(|ui| {
+				for mode in BrushMode::ALL {
+					ui.selectable_value(&mut brush.mode, mode, mode.name());
+				}
+			});
+			ui.horizontal(|ui| {
+				ui.label("Falloff:");
+				for falloff in Falloff::ALL {
+					ui.selectable_value(&mut brush.falloff, falloff, falloff.name());
+				}
+			});
+			ui.add(egui::Slider::new(&mut brush.radius, 0..=MAX_RADIUS).text("Radius"));
+			ui.add(egui::Slider::new(&mut brush.strength, 1..=MAX_STRENGTH).text("Strength"));
+			match brush.mode {
+				BrushMode::Flatten => {ui.add(egui::Slider::new(&mut brush.target, 0..=MAX_HEIGHT).text("Target height"));},
+				BrushMode::Level => {ui.label("Levels to the height of the cell where you start.");},
+				BrushMode::Ramp => {ui.label("Drag from one end of the ramp to the other.");},
+				_ => (),
+			}
+			if brush != state.brush {
+				state.brush = brush;
+			}
+			if let Some((start, start_height)) = state.stroke_start(hover.focused_cell(), &heights) && let Some(center) = hover.focused_cell() {
+				ui.label(format!("Cost per use here: {}", earth_cost(&brush.edits(center, start, start_height, &heights))));
+			}
+		}
+		ui.separator();
+		ui.horizontal(|ui| {
+			if ui.add_enabled(history.can_undo(), egui::Button::new("Undo")).clicked() {
+				commands.trigger(Undo);
+			}
+			if ui.add_enabled(history.can_redo(), egui::Button::new("Redo")).clicked() {
+				commands.trigger(Redo);
+			}
+		});
+		ui.label("Ctrl+Z undoes, Ctrl+Y redoes.");
+	});
+}
+
+/// Draws the terrain under the cable and the cable itself, with spans that break the rules in red.
+/// Distances are measured along the cable, so towers dragged off the straight line still show up in order.
+fn lift_profile(ui: &mut egui::Ui, lift_type: &LiftType, heights: &CellHeights, spans: &[Span]) {
+	let (response, painter) = ui.allocate_painter(egui::Vec2::new(240., 80.), egui::Sense::hover());
+	let rect = response.rect;
+	// Ground and cable heights by distance from the bottom station.
+	let mut ground: Vec<(f32, f32)> = Vec::new();
+	let mut cable: Vec<(f32, f32)> = Vec::new();
+	let mut distance = 0.;
+	for span in spans {
+		let from = lift_type.cable_height(&heights.0, &span.from).unwrap_or_default();
+		let to = lift_type.cable_height(&heights.0, &span.to).unwrap_or_default();
+		let length = span.from.unsigned_distance_to(span.to).max(1) as f32;
+		for (i, pos) in span.from.line_to(span.to).enumerate() {
+			if let Some(height) = heights.0.get(&pos) {ground.push((distance + i as f32, *height as f32))}
+		}
+		cable.push((distance, from));
+		distance += length;
+		cable.push((distance, to));
+	}
+	let lowest = ground.iter().map(|(_, height)| *height).fold(f32::INFINITY, f32::min);
+	let highest = cable.iter().map(|(_, height)| *height).fold(f32::NEG_INFINITY, f32::max);
+	if !lowest.is_finite() || !highest.is_finite() {return}
+	let to_screen = |x: f32, y: f32| egui::Pos2::new(
+		rect.left() + rect.width() * x / distance.max(1.),
+		rect.bottom() - rect.height() * (y - lowest) / (highest - lowest).max(1.),
+	);
+
+	let axis_stroke = ui.visuals().widgets.noninteractive.bg_stroke;
+	painter.line_segment([rect.left_bottom(), rect.right_bottom()], axis_stroke);
+	painter.add(egui::Shape::line(ground.iter().map(|(x, y)| to_screen(*x, *y)).collect(), egui::Stroke::new(2., ui.visuals().weak_text_color())));
+	for (span, pair) in spans.iter().zip(cable.chunks(2)) {
+		let color = if span.problems.is_empty() {ui.visuals().hyperlink_color} else {ui.visuals().error_fg_color};
+		painter.line_segment([to_screen(pair[0].0, pair[0].1), to_screen(pair[1].0, pair[1].1)], egui::Stroke::new(2., color));
+		painter.line_segment([to_screen(pair[0].0, pair[0].1), to_screen(pair[0].0, lowest)], axis_stroke);
+	}
+	if let Some((x, y)) = cable.last() {
+		painter.line_segment([to_screen(*x, *y), to_screen(*x, lowest)], axis_stroke);
+	}
+}
@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+
+use crate::clock::GameClock;
+use crate::weather::{Weather, FORECAST_DAYS};
+
+pub fn setup(
+	mut contexts: EguiContexts,
+	clock: Res<GameClock>,
+	weather: Res<Weather>,
+) {
+	egui::Window::new("Weather")
+	.collapsible(true)
+	.resizable(false)
+	.default_open(false)
+	.show(contexts.ctx_mut(), |ui| {
+		ui.label(format!(
+			"Now: {}, {:.0} °C at the bottom, wind {:.0} m/s",
+			weather.today.condition.name(), weather.temperature_at(0, clock.minute), weather.today.wind,
+		));
+		ui.separator();
+		egui::Grid::new("forecast").striped(true).show(ui, |ui| {
+			ui.label("Day");
+			ui.label("Weather");
+			ui.label("Temp.");
+			ui.label("Wind");
+			ui.label("Snow");
+			ui.label("Visibility");
+			ui.end_row();
+			for days_ahead in 0..=FORECAST_DAYS {
+				let day = match weather.forecast(days_ahead) {Some(day) => day, None => {error_once!("Weather is missing a forecast {} days ahead.", days_ahead); break}};
+				ui.label(match days_ahead {0 => "Today".to_string(), 1 => "Tomorrow".to_string(), _ => format!("In {} days", days_ahead)});
+				ui.label(day.condition.name());
+				ui.label(format!("{:.0} °C", day.temperature));
+				ui.label(format!("{:.0} m/s", day.wind));
+				ui.label(format!("{:.0} cm", day.snowfall));
+				ui.label(format!("{:.0}%", day.visibility * 100.));
+				ui.end_row();
+			}
+		});
+	});
+}
@@ -1 +1,2 @@
-pub mod rotation;
\ No newline at end of file
+pub mod rotation;
+pub mod hex;
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use bevy::math::Vec3;
+use hexx::Hex;
+
+use crate::util::rotation::Rotation;
+
+// TODO: Use fancy new std::f32::consts::SQRT_3 when available. https://github.com/rust-lang/rust/issues/103883
+const SQRT_3: f32 = 1.732050807568877293527446341505872367;
+
+/// Converts axial hex coordinates to xz world coordinates.
+pub const fn axial_to_xz(pos: &Hex) -> [f32; 2] {
+	[pos.x as f32 * 3. / 2., pos.x as f32 * SQRT_3 / 2. + pos.y as f32 * SQRT_3]
+}
+
+/// Converts xz world coordinates to the axial coordinates of the closest hex.
+pub fn xz_to_axial(x: f32, z: f32) -> Hex {
+	let q = x * 2. / 3.;
+	let r = (z - q * SQRT_3 / 2.) / SQRT_3;
+	// Round in cube coordinates, and recalculate the component with the largest rounding error.
+	let s = -q - r;
+	let (mut q_round, mut r_round, s_round) = (q.round(), r.round(), s.round());
+	let (q_diff, r_diff, s_diff) = ((q_round - q).abs(), (r_round - r).abs(), (s_round - s).abs());
+	if q_diff > r_diff && q_diff > s_diff {q_round = -r_round - s_round}
+	else if r_diff > s_diff {r_round = -q_round - s_round}
+	Hex::new(q_round as i32, r_round as i32)
+}
+
+/// Converts odd-q vertical layout hexagonal coordinates to axial hexagonal coordinates.
+/// https://www.redblobgames.com/grids/hexagons/#coordinates-offset
+pub fn offset_to_axial(col: i32, row: i32) -> Hex {
+	Hex {x: col, y: row - (col + 1) / 2}
+}
+
+/// Calculates the height of a corner of a hex cell in a grid (which is an average of all surrounding cell heights).
+/// Panics if pos is not in heights.
+pub fn corner_height(heights: &HashMap<Hex, u16>, pos: &Hex, corner: Rotation) -> f32 {
+	let center_y = *heights.get(pos).unwrap() as f32;
+	// Average height of all 1-3 cells.
+	let (sum, count) = [corner.edge_last(), corner.edge_next()].iter()
+	.filter_map(|edge| heights.get(&(*pos + *edge)))
+	.fold((center_y, 1.), |(sum, count), height| (sum + *height as f32, count + 1.));
+	sum / count
+}
+
+/// Difference between the highest and lowest neighboring cell, relative to the cell itself.
+/// Panics if pos is not in heights.
+pub fn cell_slope(heights: &HashMap<Hex, u16>, pos: &Hex) -> u16 {
+	let height = *heights.get(pos).unwrap() as i32;
+	let deltas: Vec<i32> = Rotation::ALL
+	.iter()
+	.map(|rotation| match heights.get(&(*pos + rotation.edge_last())) {Some(cell_height) => *cell_height as i32 - height, None => 0})
+	.collect();
+	let min = deltas.iter().min().unwrap();
+	let max = deltas.iter().max().unwrap();
+	(max - min).unsigned_abs() as u16
+}
+
+/// Approximate surface normal of a cell, fitted to the heights of its neighbors.
+/// Panics if pos is not in heights.
+pub fn cell_normal(heights: &HashMap<Hex, u16>, pos: &Hex) -> Vec3 {
+	let height = *heights.get(pos).unwrap() as f32;
+	let (mut gradient_x, mut gradient_z) = (0., 0.);
+	for rotation in Rotation::ALL {
+		let edge = rotation.edge_last();
+		let delta = match heights.get(&(*pos + edge)) {Some(cell_height) => *cell_height as f32 - height, None => 0.};
+		let [x, z] = axial_to_xz(&edge);
+		gradient_x += delta * x;
+		gradient_z += delta * z;
+	}
+	/// Sum of squared x (or z) offsets to all six neighbors.
+	const OFFSET_SQUARES: f32 = 9.;
+	Vec3::new(-gradient_x / OFFSET_SQUARES, 1., -gradient_z / OFFSET_SQUARES).normalize()
+}
+
+/// The lower neighbor that snow and water would flow towards, or None if no neighbor is lower.
+pub fn flow_direction(heights: &HashMap<Hex, u16>, pos: &Hex) -> Option<Hex> {
+	let height = *heights.get(pos)?;
+	Rotation::ALL
+	.iter()
+	.map(|rotation| *pos + rotation.edge_last())
+	.filter_map(|neighbor| heights.get(&neighbor).map(|neighbor_height| (neighbor, *neighbor_height)))
+	.filter(|(_, neighbor_height)| *neighbor_height < height)
+	.min_by_key(|(_, neighbor_height)| *neighbor_height)
+	.map(|(neighbor, _)| neighbor)
+}
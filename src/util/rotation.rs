@@ -1,5 +1,6 @@
 use std::ops::{Add, Sub};
 use hexx::Hex;
+use serde::{Serialize, Deserialize};
 
 // TODO: Use fancy new std::f32::consts::SQRT_3 when available. https://github.com/rust-lang/rust/issues/103883
 const SQRT_3: f32 = 1.732050807568877293527446341505872367;
@@ -11,7 +12,7 @@ const SQRT_3: f32 = 1.732050807568877293527446341505872367;
 ///   \D-C/
 /// ```
 /// Default corner is `A`.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default, Serialize, Deserialize)]
 pub enum Rotation {
 	#[default]
 	A,
@@ -23,16 +24,9 @@ pub enum Rotation {
 }
 impl Rotation {
 	pub const ALL: [Self; 6] = [Self::A, Self::B, Self::C, Self::D, Self::E, Self::F];
-	pub const EVEN: [Self; 3] = [Self::A, Self::C, Self::E];
-	pub const ODD: [Self; 3] = [Self::B, Self::D, Self::F];
 
 	pub const fn from_usize(value: usize) -> Self {Self::ALL[value % 6]}
 
-	pub const fn is_even(self) -> bool {
-		//match self {Self::A => true, Self::B => false, Self::C => true, Self::D => false, Self::E => true, Self::F => false} // TODO: Remove if not needed.
-		self as usize % 2 == 0
-	}
-	
 	/// 2D coordinates of the specified corner.
 	pub const fn corner_xz(&self) -> [f32; 2] {
 		const SQRT_3_DIV_2: f32 = SQRT_3 / 2.;
@@ -46,7 +40,8 @@ impl Rotation {
 		}
 	}
 	pub const fn corner_next(self) -> Self {Self::from_usize(self as usize + 1)}
-	pub const fn corner_last(self) -> Self {Self::from_usize(self as usize - 1)} // TODO: Test this.
+	#[cfg(test)]
+	pub const fn corner_last(self) -> Self {Self::from_usize(self as usize + 5)}
 
 	/// Axial coordinates of the edge 
 	pub const fn edge_last(&self) -> Hex {
@@ -70,5 +65,19 @@ impl Add<usize> for Rotation {
 }
 impl Sub<usize> for Rotation {
 	type Output = Self;
-	fn sub(self, other: usize) -> Self {Self::from(self as usize - other)}
+	fn sub(self, other: usize) -> Self {Self::from(self as usize + 6 - other % 6)}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stepping_back_wraps_around() {
+		assert_eq!(Rotation::A.corner_last(), Rotation::F);
+		assert_eq!(Rotation::C.corner_last(), Rotation::B);
+		assert_eq!(Rotation::A - 1, Rotation::F);
+		assert_eq!(Rotation::B - 8, Rotation::F);
+		assert_eq!(Rotation::F + 1, Rotation::A);
+	}
 }
\ No newline at end of file
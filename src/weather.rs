@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+use bevy::{prelude::*, ecs::schedule::Condition as _};
+use rand::{prelude::*, rngs::StdRng};
+use serde::{Serialize, Deserialize};
+
+use crate::util::rotation::Rotation;
+use crate::clock::{DayStarted, SeasonPhase, DAYS_PER_YEAR};
+use crate::grid::{
+	PlacedObjects,
+	surface::PisteGrade,
+	object::{ObjectInstanceId, ObjectInstance, lift::LiftTypes},
+};
+
+/// Simulates weather fronts one day at a time, and keeps a forecast for the coming days.
+pub struct WeatherPlugin;
+impl Plugin for WeatherPlugin {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(Weather::new(rand::random()));
+		app.add_observer(next_day);
+		app.add_systems(Update, hold_lifts_in_wind.run_if(resource_changed::<Weather>.or(resource_changed::<PlacedObjects>)));
+	}
+}
+
+/// Number of days ahead that the forecast covers.
+pub const FORECAST_DAYS: usize = 5;
+/// Temperature change (°C) per unit of cell height.
+pub const LAPSE_RATE: f32 = -0.15;
+/// Difference between the warmest and coldest time of the day (°C).
+pub const DAILY_TEMPERATURE_RANGE: f32 = 6.;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Condition {
+	Sunny,
+	Cloudy,
+	Snowing,
+	Fog,
+	/// Heavy snowfall and strong wind.
+	Storm,
+}
+impl Condition {
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::Sunny => "Sunny",
+			Self::Cloudy => "Cloudy",
+			Self::Snowing => "Snowing",
+			Self::Fog => "Fog",
+			Self::Storm => "Storm",
+		}
+	}
+
+	pub const fn is_snowing(&self) -> bool {matches!(self, Self::Snowing | Self::Storm)}
+}
+
+/// The weather for a whole day.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct DailyWeather {
+	pub condition: Condition,
+	/// Average temperature at height 0 (°C).
+	pub temperature: f32,
+	/// Wind speed (m/s).
+	pub wind: f32,
+	/// The wind blows towards the `edge_last` of this rotation.
+	pub wind_direction: Rotation,
+	/// New snow over the day (cm).
+	pub snowfall: f32,
+	/// 0-1, where 1 is clear.
+	pub visibility: f32,
+}
+impl DailyWeather {
+	/// Generates the weather for the specified day, which depends on the previous day since fronts take time to pass.
+	/// The same seed, day and previous weather always give the same result.
+	pub fn generate(seed: u64, day: u32, previous: &DailyWeather) -> Self {
+		let mut rng = StdRng::seed_from_u64(seed ^ (day as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+		let phase = SeasonPhase::from_day_of_year(day % DAYS_PER_YEAR);
+		let (snow_chance, mean_temperature) = match phase {
+			SeasonPhase::PreSeason => (0.35, -2.),
+			SeasonPhase::Peak | SeasonPhase::Holidays => (0.4, -8.),
+			SeasonPhase::Spring => (0.2, 2.),
+			SeasonPhase::Closed => (0.05, 10.),
+		};
+		let fog_chance = if phase == SeasonPhase::Spring {0.15} else {0.08};
+
+		let condition = if previous.condition.is_snowing() && rng.random_bool(0.5) {
+			// The front hasn't passed yet.
+			if rng.random_bool(0.25) {Condition::Storm} else {previous.condition}
+		} else {
+			let roll: f64 = rng.random();
+			if roll < snow_chance {if rng.random_bool(0.15) {Condition::Storm} else {Condition::Snowing}}
+			else if roll < snow_chance + fog_chance {Condition::Fog}
+			else if rng.random_bool(0.5) {Condition::Sunny}
+			else {Condition::Cloudy}
+		};
+
+		// Temperatures drift from day to day instead of jumping around.
+		let temperature = (previous.temperature + mean_temperature) / 2. + rng.random_range(-3. ..=3.) + match condition {
+			Condition::Sunny => 1.,
+			Condition::Storm => -3.,
+			_ => 0.,
+		};
+		let (wind, visibility) = match condition {
+			Condition::Sunny => (rng.random_range(0. ..6.), 1.),
+			Condition::Cloudy => (rng.random_range(2. ..10.), 0.9),
+			Condition::Snowing => (rng.random_range(3. ..12.), rng.random_range(0.5..0.7)),
+			Condition::Fog => (rng.random_range(0. ..3.), rng.random_range(0.1..0.3)),
+			Condition::Storm => (rng.random_range(14. ..25.), rng.random_range(0.2..0.4)),
+		};
+		let snowfall = match condition {
+			_ if temperature > 1. => 0., // Rain.
+			Condition::Snowing => rng.random_range(5. ..20.),
+			Condition::Storm => rng.random_range(15. ..40.),
+			_ => 0.,
+		};
+		// Fronts tend to keep the same wind direction.
+		let wind_direction = if rng.random_bool(0.7) {previous.wind_direction} else {Rotation::from_usize(rng.random_range(0..6))};
+
+		Self {condition: condition, temperature: temperature, wind: wind, wind_direction: wind_direction, snowfall: snowfall, visibility: visibility}
+	}
+}
+impl Default for DailyWeather {
+	fn default() -> Self {
+		Self {condition: Condition::Cloudy, temperature: -2., wind: 4., wind_direction: Rotation::default(), snowfall: 0., visibility: 0.9}
+	}
+}
+
+#[derive(Resource, Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Weather {
+	pub seed: u64,
+	pub today: DailyWeather,
+	/// The coming days, starting with tomorrow.
+	pub forecast: VecDeque<DailyWeather>,
+}
+impl Weather {
+	pub fn new(seed: u64) -> Self {
+		let today = DailyWeather::generate(seed, 0, &DailyWeather::default());
+		let mut weather = Self {seed: seed, today: today, forecast: VecDeque::new()};
+		for day in 1..=FORECAST_DAYS as u32 {
+			let previous = *weather.forecast.back().unwrap_or(&weather.today);
+			weather.forecast.push_back(DailyWeather::generate(seed, day, &previous));
+		}
+		weather
+	}
+
+	/// Temperature at a height and minute of the day (°C).
+	/// It's coldest at sunrise and warmest in the afternoon.
+	pub fn temperature_at(&self, height: u16, minute: f32) -> f32 {
+		let daily = -(2. * std::f32::consts::PI * (minute / 60. - 3.) / 24.).cos() * DAILY_TEMPERATURE_RANGE / 2.;
+		self.today.temperature + daily + height as f32 * LAPSE_RATE
+	}
+
+	/// New snow per in-game minute (cm).
+	pub fn snowfall_per_minute(&self) -> f32 {
+		self.today.snowfall / (24. * 60.)
+	}
+
+	/// Forecast for the specified number of days ahead (0 is today).
+	pub fn forecast(&self, days_ahead: usize) -> Option<&DailyWeather> {
+		if days_ahead == 0 {Some(&self.today)} else {self.forecast.get(days_ahead - 1)}
+	}
+
+	/// How willing guests are to ski a piste grade in the current visibility, from 0 to 1.
+	/// Fog mostly scares guests away from black and red runs.
+	pub fn grade_willingness(&self, grade: PisteGrade) -> f32 {
+		let visibility = self.today.visibility.clamp(0., 1.);
+		match grade {
+			PisteGrade::Green | PisteGrade::Blue => 1.,
+			PisteGrade::Red => visibility.sqrt(),
+			PisteGrade::Black => visibility * visibility,
+		}
+	}
+}
+
+fn next_day(
+	trigger: Trigger<DayStarted>,
+	mut weather: ResMut<Weather>,
+) {
+	let day = trigger.0;
+	let seed = weather.seed;
+	weather.today = match weather.forecast.pop_front() {Some(today) => today, None => {error!("Weather forecast ran out before day {}.", day); DailyWeather::generate(seed, day, &weather.today)}};
+	let previous = *weather.forecast.back().unwrap_or(&weather.today);
+	weather.forecast.push_back(DailyWeather::generate(seed, day + FORECAST_DAYS as u32, &previous));
+}
+
+/// Stops exposed lifts while the wind is above their limit, and restarts them once it calms down.
+fn hold_lifts_in_wind(
+	weather: Res<Weather>,
+	mut objects: ResMut<PlacedObjects>,
+	lift_types: Res<LiftTypes>,
+) {
+	// Only lifts that change are mutated, to avoid triggering change detection every time.
+	let changed: Vec<(ObjectInstanceId, bool)> = objects.iter().filter_map(|(instance_id, object)| {
+		let ObjectInstance::Lift(lift) = object else {return None};
+		let max_wind = match lift_types.0.get(&lift.type_id) {Some(lift_type) => lift_type.max_wind, None => {error_once!("Lift has unknown lift type {:?}.", lift.type_id); return None}};
+		let wind_hold = max_wind.is_some_and(|max_wind| weather.today.wind > max_wind);
+		if lift.wind_hold != wind_hold {Some((*instance_id, wind_hold))} else {None}
+	}).collect();
+	for (instance_id, wind_hold) in changed {
+		if let Some(ObjectInstance::Lift(lift)) = objects.get_mut(&instance_id) {
+			lift.wind_hold = wind_hold;
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn weather_is_the_same_for_a_seed() {
+		assert_eq!(Weather::new(42), Weather::new(42));
+		let previous = DailyWeather::default();
+		assert_eq!(DailyWeather::generate(42, 7, &previous), DailyWeather::generate(42, 7, &previous));
+		let days = |seed| (0..10).map(|day| DailyWeather::generate(seed, day, &previous)).collect::<Vec<_>>();
+		assert_ne!(days(42), days(43), "Different seeds give the same weather.");
+	}
+
+	#[test]
+	fn forecast_rolls_over_each_day() {
+		let mut world = World::new();
+		world.add_observer(next_day);
+		world.insert_resource(Weather::new(42));
+		let before = world.resource::<Weather>().clone();
+		world.trigger(DayStarted(1));
+		let after = world.resource::<Weather>();
+		assert_eq!(after.today, before.forecast[0]);
+		assert_eq!(after.forecast.len(), FORECAST_DAYS);
+		assert!(after.forecast.iter().zip(before.forecast.iter().skip(1)).all(|(day, expected)| day == expected), "The forecast changed.");
+		let last = DailyWeather::generate(42, 1 + FORECAST_DAYS as u32, &before.forecast[FORECAST_DAYS - 1]);
+		assert_eq!(after.forecast.back(), Some(&last));
+	}
+}